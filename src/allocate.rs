@@ -0,0 +1,95 @@
+use std::fs::OpenOptions;
+use std::io::Result;
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Reserves disk space for the file at `path`, creating it if necessary,
+    /// so that it's at least `len` bytes long and writes within that length
+    /// won't fail for lack of space. Files longer than `len` are left alone.
+    ///
+    /// This fails up front if the disk doesn't have enough free space,
+    /// which lets downloads and databases report the problem before doing
+    /// any work. Preallocated bytes read as zeros.
+    ///
+    /// See: `posix_fallocate(3)` on Linux, `fcntl(2)` with `F_PREALLOCATE`
+    /// on macOS. On other platforms, the file is extended with
+    /// [`std::fs::File::set_len`], which may not reserve space on
+    /// filesystems that support sparse files.
+    pub fn allocate<P2: AsRef<Path>>(&self, path: P2, len: u64) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("allocate", self.0.as_ref(), path, || {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+            if file.metadata()?.len() < len {
+                sys::allocate(&file, len)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::fs::File;
+    use std::io::{Error, Result};
+    use std::os::fd::AsRawFd;
+
+    pub(super) fn allocate(file: &File, len: u64) -> Result<()> {
+        match unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t) } {
+            0 => Ok(()),
+            errno => Err(Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod sys {
+    use std::fs::File;
+    use std::io::{Error, Result};
+    use std::os::fd::AsRawFd;
+
+    pub(super) fn allocate(file: &File, len: u64) -> Result<()> {
+        let current = file.metadata()?.len();
+        let mut store = libc::fstore_t {
+            fst_flags: libc::F_ALLOCATECONTIG,
+            fst_posmode: libc::F_PEOFPOSMODE,
+            fst_offset: 0,
+            fst_length: (len - current) as libc::off_t,
+            fst_bytesalloc: 0,
+        };
+        // Prefer contiguous space, but accept fragmented space if that's
+        // all that's available
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) } == -1 {
+            store.fst_flags = libc::F_ALLOCATEALL;
+            if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) } == -1 {
+                return Err(Error::last_os_error());
+            }
+        }
+        // Preallocation reserves space without changing the file's length
+        file.set_len(len)
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+mod sys {
+    use std::fs::File;
+    use std::io::Result;
+
+    pub(super) fn allocate(file: &File, len: u64) -> Result<()> {
+        file.set_len(len)
+    }
+}
@@ -0,0 +1,113 @@
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, Read, Result, Seek, SeekFrom, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::instrument::timed;
+use crate::Dir;
+
+/// An unnamed file, which becomes visible in its directory only once it's
+/// given a name with [`AnonymousFile::persist`]. If it's dropped first, its
+/// contents are discarded, and nothing is left behind.
+///
+/// Created with [`Dir::create_anonymous`]. Only available on Linux.
+#[derive(Debug)]
+pub struct AnonymousFile {
+    file: File,
+    dir: PathBuf,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates an unnamed file in the directory at `dir`, open for reading
+    /// and writing. Once it's completely written, it can be linked into
+    /// place with [`AnonymousFile::persist`], so a half-written file can
+    /// never be observed under its final name, even if the process crashes.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the directory doesn't exist, or if
+    /// its filesystem doesn't support `O_TMPFILE`.
+    ///
+    /// See: `open(2)` with `O_TMPFILE`
+    pub fn create_anonymous<P2: AsRef<Path>>(&self, dir: P2) -> Result<AnonymousFile> {
+        let dir = self.join(dir);
+        let file = timed("create_anonymous", self.0.as_ref(), &dir, || {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .mode(0o666)
+                .custom_flags(libc::O_TMPFILE)
+                .open(&dir)
+        })?;
+        Ok(AnonymousFile { file, dir })
+    }
+}
+
+impl AnonymousFile {
+    /// Returns the underlying file
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the underlying file, mutably
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Gives the file a name, relative to the directory it was created in,
+    /// making it visible there. Returns the file, which stays open.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind
+    /// [`ErrorKind::AlreadyExists`](std::io::ErrorKind::AlreadyExists) if
+    /// something already exists with that name. Existing files are never
+    /// replaced.
+    ///
+    /// See: `linkat(2)`
+    pub fn persist<P: AsRef<Path>>(self, name: P) -> Result<File> {
+        let proc_path = CString::new(format!("/proc/self/fd/{}", self.file.as_raw_fd()))?;
+        let dest = CString::new(self.dir.join(name).as_os_str().as_bytes())?;
+        let linked = unsafe {
+            libc::linkat(
+                libc::AT_FDCWD,
+                proc_path.as_ptr(),
+                libc::AT_FDCWD,
+                dest.as_ptr(),
+                libc::AT_SYMLINK_FOLLOW,
+            )
+        };
+        if linked != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(self.file)
+    }
+}
+
+impl Read for AnonymousFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for AnonymousFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for AnonymousFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.file.seek(pos)
+    }
+}
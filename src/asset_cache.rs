@@ -0,0 +1,214 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::temp::unique_name;
+use crate::Dir;
+
+/// A read-through cache of compressed assets, stored in a working directory.
+///
+/// Each asset is identified by a string key, and stored compressed in a
+/// file sharded by a hash of the key (e.g. `3f/3fa47c0b9e21d8a5`), so that no
+/// single directory grows too large. On a miss, [`AssetCache::get`] fetches
+/// the asset with the provided closure and stores it. Whenever an asset is
+/// stored, the least recently used assets are evicted until the cache fits
+/// within its byte budget.
+///
+/// The cache keeps a running total of the bytes it stores, so that it only
+/// walks the directory to evict assets once it's over budget. The total is
+/// computed on the first insert, and afterwards only counts this cache's
+/// own inserts and removals. Assets stored by other processes are counted
+/// the next time this cache evicts.
+///
+/// Available with the `asset-cache` feature.
+///
+/// ```no_run
+/// use working_dir::{AssetCache, Dir};
+///
+/// let cache = AssetCache::new(Dir("cache/textures"), 64 << 20);
+/// let texture = cache.get("grass.png", || std::fs::read("assets/grass.png"))?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct AssetCache<P>
+where
+    P: AsRef<Path>,
+{
+    dir: Dir<P>,
+    budget: u64,
+    level: Compression,
+    /// The bytes used by stored assets, or `None` until the first insert
+    used: Mutex<Option<u64>>,
+}
+
+impl<P> AssetCache<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a cache in the given working directory, which may use at
+    /// most `budget` bytes of disk space for compressed assets
+    pub fn new(dir: Dir<P>, budget: u64) -> AssetCache<P> {
+        AssetCache {
+            dir,
+            budget,
+            level: Compression::default(),
+            used: Mutex::new(None),
+        }
+    }
+
+    /// Sets the compression level used for newly stored assets, from 0
+    /// (no compression) to 9 (best compression)
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.level = Compression::new(level.min(9));
+        self
+    }
+
+    /// Returns the working directory holding the cache
+    #[inline]
+    pub fn dir(&self) -> &Dir<P> {
+        &self.dir
+    }
+
+    /// Returns the asset stored under `key`, calling `fetch` to obtain and
+    /// store it if it isn't cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `fetch`, or from reading or writing the cache.
+    /// A cached asset which is corrupt is treated as a miss.
+    pub fn get<F>(&self, key: &str, fetch: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce() -> Result<Vec<u8>>,
+    {
+        if let Some(asset) = self.load(key)? {
+            return Ok(asset);
+        }
+        let asset = fetch()?;
+        self.insert(key, &asset)?;
+        Ok(asset)
+    }
+
+    /// Returns the asset stored under `key`, or `None` if it isn't cached
+    pub fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = shard_path(key);
+        // Hits are recorded below, and only once the asset has decoded, so
+        // the read itself isn't recorded even if the root tracks accesses
+        let Some(stored) = self.dir.as_fs_dir().read_opt(&path)? else {
+            return Ok(None);
+        };
+        match decode(key, &stored) {
+            Some(asset) => {
+                self.dir.record_access(&path)?;
+                Ok(Some(asset))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Stores an asset under `key`, replacing any existing asset, then
+    /// evicts the least recently used assets until the cache fits its budget
+    pub fn insert(&self, key: &str, asset: &[u8]) -> Result<()> {
+        let path = shard_path(key);
+        let encoded = encode(key, asset, self.level)?;
+
+        let mut used = self
+            .used
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let replaced = self.dir.metadata_opt(&path)?.map_or(0, |meta| meta.len());
+
+        // Write to a temporary file first, so a partially-written asset is
+        // never observed under its final name
+        let mut prefix = path.clone().into_os_string();
+        prefix.push(".tmp-");
+        let tmp = unique_name(Path::new(&prefix));
+        self.dir.create_parents(&path)?;
+        self.dir.write(&tmp, &encoded)?;
+        if let Err(err) = self.dir.rename(&tmp, &path) {
+            let _ = self.dir.remove_file(&tmp);
+            return Err(err);
+        }
+        self.dir.record_access(&path)?;
+
+        // Taken, so that the total is recomputed if eviction fails
+        let total = used
+            .take()
+            .map(|total| total.saturating_sub(replaced) + encoded.len() as u64);
+        *used = match total {
+            Some(total) if total <= self.budget => Some(total),
+            _ => Some(self.dir.evict_lru("", self.budget)?.bytes_remaining),
+        };
+        Ok(())
+    }
+
+    /// Removes the asset stored under `key`. Returns `true` if it was cached.
+    pub fn remove(&self, key: &str) -> Result<bool> {
+        let path = shard_path(key);
+        let mut used = self
+            .used
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(meta) = self.dir.metadata_opt(&path)? else {
+            return Ok(false);
+        };
+        match self.dir.remove_file(&path) {
+            Ok(()) => {
+                if let Some(total) = used.as_mut() {
+                    *total = total.saturating_sub(meta.len());
+                }
+                Ok(true)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// FNV-1a, which is stable across platforms and releases, unlike the
+/// standard library's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn shard_path(key: &str) -> PathBuf {
+    let name = format!("{:016x}", fnv1a(key.as_bytes()));
+    Path::new(&name[..2]).join(&name)
+}
+
+/// An asset is stored as the length of its key (a little-endian `u32`), the
+/// key itself, then the zlib-compressed asset. Storing the key guards
+/// against hash collisions.
+fn encode(key: &str, asset: &[u8], level: Compression) -> Result<Vec<u8>> {
+    let key_len = u32::try_from(key.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "asset key is too long"))?;
+    let mut out = Vec::with_capacity(4 + key.len() + asset.len() / 2);
+    out.extend_from_slice(&key_len.to_le_bytes());
+    out.extend_from_slice(key.as_bytes());
+
+    let mut encoder = ZlibEncoder::new(out, level);
+    encoder.write_all(asset)?;
+    encoder.finish()
+}
+
+fn decode(key: &str, stored: &[u8]) -> Option<Vec<u8>> {
+    let key_len = u32::from_le_bytes(stored.get(..4)?.try_into().ok()?) as usize;
+    let stored_key = stored.get(4..4 + key_len)?;
+    if stored_key != key.as_bytes() {
+        return None;
+    }
+
+    let mut asset = Vec::new();
+    ZlibDecoder::new(&stored[4 + key_len..])
+        .read_to_end(&mut asset)
+        .ok()?;
+    Some(asset)
+}
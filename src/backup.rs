@@ -0,0 +1,52 @@
+use std::ffi::OsString;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::Dir;
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Keeps a copy of the file at `path` before it's overwritten, rotating
+    /// older copies: `file.1` becomes `file.2`, `file.2` becomes `file.3`,
+    /// and so on, and then the file is copied to `file.1`. At most `keep`
+    /// copies are kept, and the oldest is removed.
+    ///
+    /// Returns the path of the new copy, or `None` if the file doesn't
+    /// exist or `keep` is zero.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let config = Dir::new("/etc/myapp");
+    /// config.backup("settings.toml", 3)?;
+    /// config.write("settings.toml", "theme = \"dark\"\n")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backup<P2: AsRef<Path>>(&self, path: P2, keep: usize) -> Result<Option<PathBuf>> {
+        let path = path.as_ref();
+        if keep == 0 || self.symlink_metadata_opt(path)?.is_none() {
+            return Ok(None);
+        }
+
+        let numbered = |n: usize| {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if self.symlink_metadata_opt(numbered(keep))?.is_some() {
+            self.remove_any(numbered(keep))?;
+        }
+        for n in (1..keep).rev() {
+            if self.symlink_metadata_opt(numbered(n))?.is_some() {
+                self.rename(numbered(n), numbered(n + 1))?;
+            }
+        }
+
+        let copy = numbered(1);
+        self.copy(path, &copy)?;
+        Ok(Some(copy))
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result};
+use std::path::Path;
+
+use crate::Dir;
+
+/// An iterator over the contents of a file in fixed-size chunks. Every
+/// chunk is full except possibly the last.
+///
+/// Created with [`Dir::read_chunks`].
+#[derive(Debug)]
+pub struct ReadChunks {
+    file: File,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Opens the file at `path` for reading in chunks of `chunk_size` bytes,
+    /// so large files can be hashed, uploaded, or streamed without reading
+    /// them into memory all at once.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = Dir::new("/var/lib/myapp");
+    /// for chunk in dir.read_chunks("backup.tar", 1 << 20)? {
+    ///     let chunk = chunk?;
+    ///     // upload the chunk
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidInput`]
+    /// if `chunk_size` is zero.
+    pub fn read_chunks<P2: AsRef<Path>>(&self, path: P2, chunk_size: usize) -> Result<ReadChunks> {
+        if chunk_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "chunk size must be non-zero",
+            ));
+        }
+        Ok(ReadChunks {
+            file: self.open_readonly(path)?,
+            chunk_size,
+            done: false,
+        })
+    }
+}
+
+impl ReadChunks {
+    /// Reads the next chunk into `buf` rather than a new `Vec`, reusing the
+    /// caller's buffer. Reads until `buf` is full or the end of the file is
+    /// reached, and returns the number of bytes read, which is zero at the
+    /// end of the file. The chunk size is ignored.
+    pub fn read_into(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.file.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl Iterator for ReadChunks {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut chunk = vec![0u8; self.chunk_size];
+        match self.read_into(&mut chunk) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(n) => {
+                chunk.truncate(n);
+                self.done = n < self.chunk_size;
+                Some(Ok(chunk))
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::reflink::reflink;
+use crate::sync::symlink;
+use crate::{Dir, FileKind};
+
+/// How [`Dir::clone_subtree`] cloned a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CloneMethod {
+    /// The filesystem cloned the whole tree at once, as a Btrfs subvolume
+    /// snapshot or an APFS directory clone
+    Snapshot,
+    /// The directories were recreated, and every file was cloned with
+    /// [`Dir::copy_reflink`]
+    Reflink,
+    /// The directories were recreated, and at least one file had to be
+    /// copied because it couldn't be cloned
+    Copy,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Clones the tree at `src` to `dest`, using the cheapest method the
+    /// filesystem supports, and returns the method used.
+    ///
+    /// On Btrfs, if `src` is the root of a subvolume, `dest` is created as a
+    /// writable snapshot of it. Ordinary directories on Btrfs are cloned file
+    /// by file, since snapshotting them would copy the whole subvolume which
+    /// contains them. On APFS, the whole directory is cloned with
+    /// `clonefile(2)`. Either way, this takes constant time regardless of
+    /// the size of the tree. Otherwise, the tree is recreated with each file
+    /// cloned as with [`Dir::copy_reflink`], falling back to a regular copy
+    /// for files which can't be cloned.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let artifacts = Dir::new("/srv/artifacts");
+    /// let method = artifacts.clone_subtree("builds/latest", "builds/candidate")?;
+    /// println!("cloned by {method:?}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Symbolic links are copied as links, and permissions are preserved.
+    ///
+    /// # Errors
+    ///
+    /// `dest` must not already exist, although its parents are created as
+    /// necessary.
+    pub fn clone_subtree<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        src: P2,
+        dest: P3,
+    ) -> Result<CloneMethod> {
+        let (src, dest) = (src.as_ref(), dest.as_ref());
+        self.create_parents(dest)?;
+        if sys::clone_tree(&self.join(src), &self.join(dest)).is_ok() {
+            return Ok(CloneMethod::Snapshot);
+        }
+
+        let base = self.relative_base(src);
+        let permissions = self.metadata(src)?.permissions();
+        self.create_dir(dest)?;
+        let mut dirs: Vec<(PathBuf, fs::Permissions)> = vec![(dest.to_owned(), permissions)];
+        let mut method = CloneMethod::Reflink;
+        for entry in self.walk(src) {
+            let entry = entry?;
+            let to = dest.join(entry.path().strip_prefix(&base).unwrap_or(entry.path()));
+            match FileKind::from(entry.file_type()?) {
+                FileKind::Dir => {
+                    self.create_dir(&to)?;
+                    dirs.push((to, entry.metadata()?.permissions()));
+                }
+                FileKind::File => {
+                    let (from, to) = (entry.abs_path(), self.join(&to));
+                    if reflink(&from, &to).is_err() {
+                        fs::copy(&from, &to)?;
+                        method = CloneMethod::Copy;
+                    }
+                }
+                FileKind::Symlink => {
+                    let from = entry.abs_path();
+                    symlink(&fs::read_link(&from)?, &self.join(&to), &from)?;
+                }
+                // Sockets, devices, and the like can't be cloned
+                FileKind::Other => {}
+            }
+        }
+
+        // Read-only directories can only be made so after they're populated
+        for (dir, permissions) in dirs.into_iter().rev() {
+            fs::set_permissions(self.join(dir), permissions)?;
+        }
+        Ok(method)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::fs::File;
+    use std::io::{Error, ErrorKind, Result};
+    use std::mem::MaybeUninit;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    const BTRFS_SUPER_MAGIC: i64 = 0x9123_683e;
+    /// The inode number of the root directory of every Btrfs subvolume
+    const BTRFS_FIRST_FREE_OBJECTID: u64 = 256;
+
+    /// `_IOW(BTRFS_IOCTL_MAGIC, 23, struct btrfs_ioctl_vol_args_v2)`
+    const BTRFS_IOC_SNAP_CREATE_V2: u32 = 0x5000_9417;
+    const BTRFS_SUBVOL_NAME_MAX: usize = 4039;
+
+    /// `struct btrfs_ioctl_vol_args_v2`, without the unions we don't use
+    #[repr(C)]
+    struct VolArgsV2 {
+        fd: i64,
+        transid: u64,
+        flags: u64,
+        unused: [u64; 4],
+        name: [u8; BTRFS_SUBVOL_NAME_MAX + 1],
+    }
+
+    // The size is part of the ioctl number
+    const _: () = assert!(std::mem::size_of::<VolArgsV2>() == 4096);
+
+    /// Returns true if `file` is the root directory of a Btrfs subvolume,
+    /// as `btrfs subvolume snapshot` checks
+    fn is_subvolume(file: &File) -> Result<bool> {
+        let mut stat = MaybeUninit::<libc::statfs>::uninit();
+        if unsafe { libc::fstatfs(file.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        let stat = unsafe { stat.assume_init() };
+        #[allow(clippy::unnecessary_cast)]
+        let on_btrfs = stat.f_type as i64 == BTRFS_SUPER_MAGIC;
+        Ok(on_btrfs && file.metadata()?.ino() == BTRFS_FIRST_FREE_OBJECTID)
+    }
+
+    /// Snapshots the Btrfs subvolume at `from` as `to`. Fails with an error
+    /// of kind [`ErrorKind::Unsupported`] if `from` isn't a subvolume root,
+    /// since the kernel would snapshot the subvolume containing it instead.
+    pub(super) fn clone_tree(from: &Path, to: &Path) -> Result<()> {
+        let name = to
+            .file_name()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "destination has no name"))?
+            .as_bytes();
+        if name.len() > BTRFS_SUBVOL_NAME_MAX {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "destination name too long",
+            ));
+        }
+        let parent = to
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+
+        let source = File::open(from)?;
+        if !is_subvolume(&source)? {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "source isn't a Btrfs subvolume",
+            ));
+        }
+        let parent = File::open(parent)?;
+        let mut args = VolArgsV2 {
+            fd: source.as_raw_fd().into(),
+            transid: 0,
+            flags: 0,
+            unused: [0; 4],
+            name: [0; BTRFS_SUBVOL_NAME_MAX + 1],
+        };
+        args.name[..name.len()].copy_from_slice(name);
+        let request = BTRFS_IOC_SNAP_CREATE_V2 as _;
+        if unsafe { libc::ioctl(parent.as_raw_fd(), request, &mut args) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod sys {
+    use std::ffi::CString;
+    use std::io::{Error, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    /// Clones the directory at `from` to `to` with `clonefile(2)`, which
+    /// clones directories recursively on APFS
+    pub(super) fn clone_tree(from: &Path, to: &Path) -> Result<()> {
+        let source = CString::new(from.as_os_str().as_bytes())?;
+        let dest = CString::new(to.as_os_str().as_bytes())?;
+        if unsafe { libc::clonefile(source.as_ptr(), dest.as_ptr(), 0) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+mod sys {
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+
+    pub(super) fn clone_tree(_: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "cloning directories isn't supported on this platform",
+        ))
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read, Result};
+use std::path::Path;
+
+use crate::{Dir, FileKind};
+
+/// Returns true if two working directories contain identical trees: the
+/// same relative paths, with the same types, where every pair of files has
+/// identical contents and every pair of symbolic links has the same target.
+///
+/// Sizes and types are compared for the whole tree before any file contents
+/// are read, so trees which differ structurally are rejected quickly.
+/// Permissions and timestamps are not compared.
+///
+/// ```no_run
+/// use working_dir::{dirs_equal, Dir};
+///
+/// assert!(dirs_equal(&Dir("target/output"), &Dir("tests/golden"))?);
+/// # std::io::Result::Ok(())
+/// ```
+pub fn dirs_equal<P, Q>(a: &Dir<P>, b: &Dir<Q>) -> Result<bool>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let (left, right) = (a.snapshot("")?, b.snapshot("")?);
+    if left.entries.len() != right.entries.len() {
+        return Ok(false);
+    }
+
+    let pairs = || left.iter().zip(right.iter());
+    let same_shape = pairs().all(|(l, r)| {
+        l.path == r.path && l.kind == r.kind && (l.kind != FileKind::File || l.size == r.size)
+    });
+    if !same_shape {
+        return Ok(false);
+    }
+
+    for (l, r) in pairs() {
+        let same = match l.kind {
+            FileKind::File => files_equal(&a.join(&l.path), &b.join(&r.path))?,
+            FileKind::Symlink => a.read_link(&l.path)? == b.read_link(&r.path)?,
+            FileKind::Dir | FileKind::Other => true,
+        };
+        if !same {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Compares the contents of two files, without reading either into memory
+/// all at once
+pub(crate) fn files_equal(a: &Path, b: &Path) -> Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut left = vec![0u8; 64 * 1024];
+    let mut right = vec![0u8; 64 * 1024];
+    loop {
+        let n = read_full(&mut a, &mut left)?;
+        if read_full(&mut b, &mut right[..n.max(1)])? != n || left[..n] != right[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads until `buf` is full or the end of the file is reached, returning
+/// the number of bytes read
+fn read_full(file: &mut File, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fs::{self, File, FileTimes, Metadata};
+use std::io::{self, Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::merge::{Placement, Resolver};
+use crate::sync::symlink;
+use crate::{ConflictPolicy, Dir, FileKind, SymlinkPolicy};
+
+#[derive(Debug, Clone)]
+/// Options for [`Dir::copy_with`] and [`Dir::copy_dir_with`]
+pub struct CopyOptions {
+    conflict: ConflictPolicy,
+    preserve_permissions: bool,
+    preserve_times: bool,
+    preserve_ownership: bool,
+    symlinks: SymlinkPolicy,
+    preserve_hard_links: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> CopyOptions {
+        CopyOptions::new()
+    }
+}
+
+impl CopyOptions {
+    /// Creates the default options, which match [`Dir::copy`]: existing
+    /// files are overwritten, and permissions are preserved, but timestamps
+    /// and ownership aren't. Symbolic links are copied as links.
+    pub fn new() -> CopyOptions {
+        CopyOptions {
+            conflict: ConflictPolicy::Overwrite,
+            preserve_permissions: true,
+            preserve_times: false,
+            preserve_ownership: false,
+            symlinks: SymlinkPolicy::Preserve,
+            preserve_hard_links: false,
+        }
+    }
+
+    /// Sets what happens when a destination already exists. Within a tree,
+    /// directories which exist on both sides are merged rather than treated
+    /// as conflicts, as with [`Dir::merge_from`].
+    pub fn conflict(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict = policy;
+        self
+    }
+
+    /// Sets whether destinations which already exist are skipped. This is
+    /// shorthand for [`ConflictPolicy::KeepExisting`], or for
+    /// [`ConflictPolicy::Overwrite`] if `skip` is false.
+    pub fn skip_existing(self, skip: bool) -> Self {
+        self.conflict(match skip {
+            true => ConflictPolicy::KeepExisting,
+            false => ConflictPolicy::Overwrite,
+        })
+    }
+
+    /// Sets whether copied files have the same permissions as the source.
+    /// Otherwise, they're created with the default permissions.
+    pub fn preserve_permissions(mut self, preserve: bool) -> Self {
+        self.preserve_permissions = preserve;
+        self
+    }
+
+    /// Sets whether copied files are given the same access and
+    /// modification times as the source
+    pub fn preserve_times(mut self, preserve: bool) -> Self {
+        self.preserve_times = preserve;
+        self
+    }
+
+    /// Sets whether copies are given the same owner and group as the
+    /// source. This usually requires elevated privileges, and does nothing
+    /// on platforms other than Unix.
+    pub fn preserve_ownership(mut self, preserve: bool) -> Self {
+        self.preserve_ownership = preserve;
+        self
+    }
+
+    /// Sets how symbolic links are copied: recreated with the same target
+    /// (the default), followed to copy what they point to, or skipped. When
+    /// following links in a tree, links to directories are copied
+    /// recursively, and an error is returned if they form a cycle.
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Sets whether symbolic links are followed, copying what they point to,
+    /// rather than recreated with the same target. This is shorthand for
+    /// [`SymlinkPolicy::Follow`], or for [`SymlinkPolicy::Preserve`] if
+    /// `follow` is false.
+    pub fn follow_symlinks(self, follow: bool) -> Self {
+        self.symlinks(match follow {
+            true => SymlinkPolicy::Follow,
+            false => SymlinkPolicy::Preserve,
+        })
+    }
+
+    /// Sets whether files which are hard links to each other in the source
+    /// are recreated as hard links to each other in the destination, rather
+    /// than copied once per link. This keeps trees which share files between
+    /// many paths, such as package manager stores, from multiplying in size.
+    ///
+    /// Only links within the copied tree are preserved. This does nothing
+    /// on platforms other than Unix.
+    pub fn preserve_hard_links(mut self, preserve: bool) -> Self {
+        self.preserve_hard_links = preserve;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::copy_with`] or [`Dir::copy_dir_with`].
+/// Paths are destinations, relative to the working directory.
+pub struct CopyReport {
+    /// Files and symlinks which didn't conflict with an existing entry
+    pub copied: Vec<PathBuf>,
+    /// Entries skipped under [`ConflictPolicy::KeepExisting`]
+    pub skipped: Vec<PathBuf>,
+    /// Existing entries replaced under [`ConflictPolicy::Overwrite`]
+    pub overwritten: Vec<PathBuf>,
+    /// Entries stored under a new name with [`ConflictPolicy::Rename`], as
+    /// `(original, renamed)` pairs
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Files recreated as hard links to an earlier copy, with
+    /// [`CopyOptions::preserve_hard_links`]. These are also listed in
+    /// `copied`, `overwritten`, or `renamed`, as usual.
+    pub linked: Vec<PathBuf>,
+    /// The total number of bytes copied
+    pub bytes: u64,
+}
+
+/// An entry to be copied by [`Dir::copy_items`]
+struct Item {
+    from: PathBuf,
+    to: PathBuf,
+    kind: FileKind,
+    meta: Metadata,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Copies a single file or symlink, as configured by `options`. With
+    /// [`SymlinkPolicy::Skip`], a symlink isn't copied at all.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidInput`]
+    /// if `from` is a directory (use [`Dir::copy_dir_with`] instead), and an
+    /// error of kind [`ErrorKind::AlreadyExists`] if `to` exists and the
+    /// conflict policy is [`ConflictPolicy::Error`].
+    pub fn copy_with<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        from: P2,
+        to: P3,
+        options: &CopyOptions,
+    ) -> Result<CopyReport> {
+        let from = from.as_ref();
+        let item = self.plan_item(from, to.as_ref(), options)?;
+        if item.kind == FileKind::Dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{from:?} is a directory"),
+            ));
+        }
+        if is_skipped(&item, options) {
+            return Ok(CopyReport::default());
+        }
+        self.copy_items(vec![item], options)
+    }
+
+    /// Recursively copies the tree at `from` to `to`, as configured by
+    /// `options`. Directories are created as necessary, and directories
+    /// which already exist are merged into.
+    ///
+    /// # Errors
+    ///
+    /// With [`ConflictPolicy::Error`], all conflicts are detected before
+    /// anything is copied. Otherwise, this function returns the first error
+    /// encountered, and changes made before it are not undone.
+    pub fn copy_dir_with<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        from: P2,
+        to: P3,
+        options: &CopyOptions,
+    ) -> Result<CopyReport> {
+        let mut items = Vec::new();
+        self.plan_tree(
+            from.as_ref(),
+            to.as_ref(),
+            options,
+            &mut items,
+            &mut Vec::new(),
+        )?;
+        self.copy_items(items, options)
+    }
+
+    fn plan_item(&self, from: &Path, to: &Path, options: &CopyOptions) -> Result<Item> {
+        let meta = match options.symlinks {
+            SymlinkPolicy::Follow => self.metadata(from)?,
+            _ => self.symlink_metadata(from)?,
+        };
+        Ok(Item {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            kind: FileKind::from(meta.file_type()),
+            meta,
+        })
+    }
+
+    /// Lists everything to be copied, parents before their contents.
+    /// `ancestors` holds the canonical paths of the directories being
+    /// listed, to detect cycles when following symlinks.
+    fn plan_tree(
+        &self,
+        from: &Path,
+        to: &Path,
+        options: &CopyOptions,
+        items: &mut Vec<Item>,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let item = self.plan_item(from, to, options)?;
+        if is_skipped(&item, options) {
+            return Ok(());
+        }
+        let is_dir = item.kind == FileKind::Dir;
+        items.push(item);
+        if !is_dir {
+            return Ok(());
+        }
+
+        if options.symlinks == SymlinkPolicy::Follow {
+            let canonical = self.canonicalize(from)?;
+            if ancestors.contains(&canonical) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{from:?} is part of a symlink cycle"),
+                ));
+            }
+            ancestors.push(canonical);
+        }
+        for entry in self.read_dir_sorted(from)? {
+            let name = entry.file_name();
+            self.plan_tree(
+                &from.join(&name),
+                &to.join(&name),
+                options,
+                items,
+                ancestors,
+            )?;
+        }
+        if options.symlinks == SymlinkPolicy::Follow {
+            ancestors.pop();
+        }
+        Ok(())
+    }
+
+    fn copy_items(&self, items: Vec<Item>, options: &CopyOptions) -> Result<CopyReport> {
+        let entries = items.iter().map(|item| (item.to.as_path(), item.kind));
+        let mut resolver = Resolver::new(self, options.conflict, entries)?;
+
+        let mut report = CopyReport::default();
+        // The first copy of each source file with several links
+        let mut first_copies: HashMap<(u64, u64), PathBuf> = HashMap::new();
+
+        for item in &items {
+            let Some(placement) = resolver.place(&item.to, item.kind)? else {
+                continue;
+            };
+            let Placement {
+                target,
+                dest,
+                existing,
+                conflicted,
+            } = placement;
+
+            let to = self.join(&dest);
+            match item.kind {
+                FileKind::Dir => {
+                    if existing != Some(FileKind::Dir) || dest != target {
+                        self.create_dir(&dest)?;
+                    }
+                }
+                FileKind::File => {
+                    let id = file_id(&item.meta).filter(|_| options.preserve_hard_links);
+                    match id.and_then(|id| first_copies.get(&id)) {
+                        Some(first) => {
+                            fs::hard_link(self.join(first), &to)?;
+                            report.linked.push(dest.clone());
+                        }
+                        None => {
+                            report.bytes +=
+                                copy_file(&self.join(&item.from), &to, &item.meta, options)?;
+                            if let Some(id) = id {
+                                first_copies.insert(id, dest.clone());
+                            }
+                        }
+                    }
+                }
+                FileKind::Symlink => {
+                    let from = self.join(&item.from);
+                    symlink(&fs::read_link(&from)?, &to, &from)?;
+                }
+                // Sockets, devices, and the like can't be copied
+                FileKind::Other => continue,
+            }
+            if options.preserve_ownership {
+                chown(&to, &item.meta)?;
+            }
+            if item.kind != FileKind::Dir && !conflicted {
+                report.copied.push(dest);
+            }
+        }
+        report.skipped = resolver.skipped;
+        report.overwritten = resolver.overwritten;
+        report.renamed = resolver.renamed;
+        Ok(report)
+    }
+}
+
+/// Returns true if `item` is a symlink which `options` say to leave out
+fn is_skipped(item: &Item, options: &CopyOptions) -> bool {
+    item.kind == FileKind::Symlink && options.symlinks == SymlinkPolicy::Skip
+}
+
+/// Copies the contents of a file, setting its times before its permissions
+/// so that read-only files can still be updated
+fn copy_file(from: &Path, to: &Path, meta: &Metadata, options: &CopyOptions) -> Result<u64> {
+    let mut dest = File::create(to)?;
+    let bytes = io::copy(&mut File::open(from)?, &mut dest)?;
+    if options.preserve_times {
+        dest.set_times(
+            FileTimes::new()
+                .set_accessed(meta.accessed()?)
+                .set_modified(meta.modified()?),
+        )?;
+    }
+    if options.preserve_permissions {
+        dest.set_permissions(meta.permissions())?;
+    }
+    Ok(bytes)
+}
+
+/// Identifies a file with more than one link by its device and inode
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn chown(path: &Path, meta: &Metadata) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    std::os::unix::fs::lchown(path, Some(meta.uid()), Some(meta.gid()))
+}
+
+#[cfg(not(unix))]
+fn chown(_: &Path, _: &Metadata) -> Result<()> {
+    Ok(())
+}
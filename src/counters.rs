@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fs::{File, Metadata, ReadDir};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::Dir;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Cumulative counts for one category of operation. See [`OpStats`]
+pub struct OpCounter {
+    /// The number of operations performed, including failed ones
+    pub calls: u64,
+    /// The number of operations which returned an error
+    pub errors: u64,
+    /// The number of bytes read, written, or copied by the operations
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// A snapshot of the operations performed through a working directory,
+/// taken with [`Dir::stats_counters`]
+pub struct OpStats {
+    /// [`Dir::read`], [`Dir::read_to_string`], [`Dir::read_with_capacity`],
+    /// [`Dir::read_max`], and [`Dir::read_max_truncating`]
+    pub reads: OpCounter,
+    /// [`Dir::write`], [`Dir::sync_file`], and [`Dir::sync_root`]
+    pub writes: OpCounter,
+    /// [`Dir::rename`], [`Dir::rename_noreplace`], [`Dir::swap`], and
+    /// [`Dir::move_to`]
+    pub renames: OpCounter,
+    /// [`Dir::copy`], [`Dir::copy_with_progress`], and
+    /// [`Dir::copy_cancellable`]
+    pub copies: OpCounter,
+    /// [`Dir::remove_file`], [`Dir::remove_dir`], and [`Dir::remove_dir_all`]
+    pub removes: OpCounter,
+    /// Creation of directories and links
+    pub creates: OpCounter,
+    /// [`Dir::open`] and [`Dir::open_readonly`]
+    pub opens: OpCounter,
+    /// Directory listings, including those made while walking a tree
+    pub listings: OpCounter,
+    /// Metadata queries, including existence checks
+    pub metadata: OpCounter,
+}
+
+impl OpStats {
+    /// Returns the total number of failed operations, across all categories
+    pub fn errors(&self) -> u64 {
+        self.counters().iter().map(|counter| counter.errors).sum()
+    }
+
+    /// Returns the total number of operations, across all categories
+    pub fn calls(&self) -> u64 {
+        self.counters().iter().map(|counter| counter.calls).sum()
+    }
+
+    fn counters(&self) -> [&OpCounter; 9] {
+        [
+            &self.reads,
+            &self.writes,
+            &self.renames,
+            &self.copies,
+            &self.removes,
+            &self.creates,
+            &self.opens,
+            &self.listings,
+            &self.metadata,
+        ]
+    }
+}
+
+#[derive(Default)]
+struct AtomicCounter {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl AtomicCounter {
+    fn load(&self) -> OpCounter {
+        OpCounter {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// One counter per category, in the order of the fields of [`OpStats`]
+#[derive(Default)]
+struct Counters([AtomicCounter; 9]);
+
+/// Checked before looking up counters, so that tracking costs a single
+/// atomic load when no working directory is tracked
+static ANY_TRACKED: AtomicBool = AtomicBool::new(false);
+static TRACKED: RwLock<Option<HashMap<PathBuf, Arc<Counters>>>> = RwLock::new(None);
+
+fn category(op: &str) -> usize {
+    match op {
+        "read" | "read_to_string" | "read_with_capacity" | "read_max" | "read_max_truncating" => 0,
+        "write" | "set_len" | "allocate" | "sync_file" | "sync_root" => 1,
+        "rename" | "rename_noreplace" | "swap" | "move_to" | "move_to_as" => 2,
+        "copy" | "copy_file_to" | "copy_reflink" | "copy_sparse" | "copy_with_hash"
+        | "copy_with_progress" | "copy_cancellable" => 3,
+        "remove_file" | "remove_dir" | "remove_dir_all" | "remove_to_trash" => 4,
+        "create_dir" | "create_dir_all" | "create_parents" | "hard_link" | "hard_link_to" => 5,
+        "open" | "open_readonly" | "create_new" | "create_anonymous" => 6,
+        "read_dir" => 7,
+        _ => 8,
+    }
+}
+
+/// The result of an operation, as seen by the counters
+pub(crate) trait Outcome {
+    fn failed(&self) -> bool;
+
+    fn bytes(&self) -> u64 {
+        0
+    }
+
+    #[cfg(feature = "tracing")]
+    fn error(&self) -> Option<&std::io::Error> {
+        None
+    }
+}
+
+impl Outcome for bool {
+    fn failed(&self) -> bool {
+        false
+    }
+}
+
+/// The value produced by a successful operation
+pub(crate) trait Payload {
+    fn bytes(&self) -> u64 {
+        0
+    }
+}
+
+/// A value which doesn't count towards the bytes of an operation
+pub(crate) struct Opaque<T>(pub T);
+
+impl<T> Payload for Opaque<T> {}
+impl Payload for () {}
+impl Payload for bool {}
+impl Payload for File {}
+impl Payload for Metadata {}
+impl Payload for ReadDir {}
+impl Payload for PathBuf {}
+
+impl Payload for u64 {
+    fn bytes(&self) -> u64 {
+        *self
+    }
+}
+
+impl Payload for Vec<u8> {
+    fn bytes(&self) -> u64 {
+        self.len() as u64
+    }
+}
+
+impl Payload for String {
+    fn bytes(&self) -> u64 {
+        self.len() as u64
+    }
+}
+
+impl<T: Payload> Outcome for Result<T> {
+    fn failed(&self) -> bool {
+        self.is_err()
+    }
+
+    fn bytes(&self) -> u64 {
+        self.as_ref().map_or(0, Payload::bytes)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn error(&self) -> Option<&std::io::Error> {
+        self.as_ref().err()
+    }
+}
+
+#[inline]
+pub(crate) fn enabled() -> bool {
+    ANY_TRACKED.load(Ordering::Acquire)
+}
+
+/// Records an operation against the counters of `root`, if it is tracked
+pub(crate) fn record(root: &Path, op: &str, outcome: &impl Outcome) {
+    let tracked = TRACKED
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(counters) = tracked.as_ref().and_then(|tracked| tracked.get(root)) else {
+        return;
+    };
+    let counter = &counters.0[category(op)];
+    counter.calls.fetch_add(1, Ordering::Relaxed);
+    if outcome.failed() {
+        counter.errors.fetch_add(1, Ordering::Relaxed);
+    }
+    counter.bytes.fetch_add(outcome.bytes(), Ordering::Relaxed);
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Starts counting the operations performed through this working
+    /// directory, by any `Dir` in the process with the same root. Does
+    /// nothing if the root is already tracked.
+    ///
+    /// Counting is done with atomic increments, so it's cheap enough to
+    /// leave enabled for the lifetime of a service.
+    ///
+    /// See: [`Dir::stats_counters`]
+    pub fn track_stats(&self) {
+        let mut tracked = TRACKED
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        tracked
+            .get_or_insert_with(HashMap::new)
+            .entry(self.0.as_ref().to_owned())
+            .or_default();
+        ANY_TRACKED.store(true, Ordering::Release);
+    }
+
+    /// Stops counting the operations performed through this working
+    /// directory, discarding its counters
+    pub fn untrack_stats(&self) {
+        let mut tracked = TRACKED
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(map) = tracked.as_mut() {
+            map.remove(self.0.as_ref());
+            if map.is_empty() {
+                ANY_TRACKED.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    /// Returns the cumulative counts of operations performed through this
+    /// working directory since [`Dir::track_stats`] was called, or `None`
+    /// if it isn't tracked.
+    pub fn stats_counters(&self) -> Option<OpStats> {
+        let tracked = TRACKED
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let counters = tracked.as_ref()?.get(self.0.as_ref())?;
+        let [reads, writes, renames, copies, removes, creates, opens, listings, metadata] =
+            counters.0.each_ref().map(AtomicCounter::load);
+        Some(OpStats {
+            reads,
+            writes,
+            renames,
+            copies,
+            removes,
+            creates,
+            opens,
+            listings,
+            metadata,
+        })
+    }
+}
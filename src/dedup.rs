@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::compare::files_equal;
+use crate::temp::unique_name;
+use crate::Dir;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::dedup_hardlink`]. Paths are relative to
+/// the working directory.
+pub struct DedupReport {
+    /// Duplicate files which were replaced with hard links, as
+    /// `(duplicate, original)` pairs
+    pub linked: Vec<(PathBuf, PathBuf)>,
+    /// The total size of the files which were freed by replacing duplicates.
+    /// A duplicate with several hard links is only counted once, after the
+    /// last of them is replaced, so a duplicate which is also linked from
+    /// outside the path isn't counted at all.
+    pub bytes_saved: u64,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Finds files under `path` with identical contents, and replaces each
+    /// duplicate with a hard link to the first copy (in path order).
+    ///
+    /// Candidates are grouped by length and CRC-32, and then compared byte
+    /// by byte, so files are only linked if their contents are equal. Files
+    /// which are already hard links to the same inode as the first copy are
+    /// left alone, as are empty files and symlinks. Each duplicate is
+    /// replaced atomically, by creating the link under a unique temporary
+    /// name and renaming it over the duplicate.
+    ///
+    /// Note that linked files share permissions, ownership, and timestamps,
+    /// and that a later write through one path is visible through all of
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered. Duplicates replaced
+    /// before the error remain linked.
+    pub fn dedup_hardlink<P2: AsRef<Path>>(&self, path: P2) -> Result<DedupReport> {
+        let mut by_len: BTreeMap<u64, Vec<(PathBuf, Metadata)>> = BTreeMap::new();
+        for entry in self.walk(path) {
+            let entry = entry?;
+            let meta = fs::symlink_metadata(entry.abs_path())?;
+            if meta.is_file() && meta.len() > 0 {
+                by_len
+                    .entry(meta.len())
+                    .or_default()
+                    .push((entry.path().to_owned(), meta));
+            }
+        }
+
+        let mut report = DedupReport::default();
+        // The number of replaced links to each duplicate with several links
+        let mut replaced: HashMap<(u64, u64), u64> = HashMap::new();
+        for (len, files) in by_len {
+            if files.len() < 2 {
+                continue;
+            }
+            let mut by_crc: BTreeMap<u32, Vec<(PathBuf, Metadata)>> = BTreeMap::new();
+            for (path, meta) in files {
+                by_crc
+                    .entry(self.crc32_file(&path)?)
+                    .or_default()
+                    .push((path, meta));
+            }
+
+            for (_, mut files) in by_crc {
+                files.sort_by(|a, b| a.0.cmp(&b.0));
+                // The first copy of each distinct content in this group
+                let mut originals: Vec<(PathBuf, Metadata)> = Vec::new();
+                for (path, meta) in files {
+                    if originals.iter().any(|(_, orig)| same_inode(orig, &meta)) {
+                        continue;
+                    }
+                    let mut found = None;
+                    for (i, (orig, _)) in originals.iter().enumerate() {
+                        if files_equal(&self.join(orig), &self.join(&path))? {
+                            found = Some(i);
+                            break;
+                        }
+                    }
+                    match found {
+                        Some(i) => {
+                            let original = &originals[i].0;
+                            self.replace_with_link(original, &path)?;
+                            report.linked.push((path, original.clone()));
+                            // The duplicate's data is only freed with its last link
+                            let freed = match links(&meta) {
+                                Some((inode, nlink)) if nlink > 1 => {
+                                    let count = replaced.entry(inode).or_default();
+                                    *count += 1;
+                                    *count == nlink
+                                }
+                                _ => true,
+                            };
+                            if freed {
+                                report.bytes_saved += len;
+                            }
+                        }
+                        None => originals.push((path, meta)),
+                    }
+                }
+            }
+        }
+        report.linked.sort();
+        Ok(report)
+    }
+
+    /// Atomically replaces `path` with a hard link to `original`
+    fn replace_with_link(&self, original: &Path, path: &Path) -> Result<()> {
+        let mut prefix = OsString::from(".");
+        prefix.push(path.file_name().unwrap_or_default());
+        prefix.push(".dedup-");
+        let tmp = unique_name(&path.with_file_name(prefix));
+
+        self.hard_link(original, &tmp)?;
+        self.rename(&tmp, path).inspect_err(|_| {
+            let _ = self.remove_file(&tmp);
+        })
+    }
+}
+
+/// Returns true if both files are known to be the same inode
+#[cfg(unix)]
+fn same_inode(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Returns true if both files are known to be the same inode
+#[cfg(not(unix))]
+fn same_inode(_: &Metadata, _: &Metadata) -> bool {
+    false
+}
+
+/// Returns the device and inode of a file, and its number of hard links
+#[cfg(unix)]
+fn links(meta: &Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some(((meta.dev(), meta.ino()), meta.nlink()))
+}
+
+/// Returns the device and inode of a file, and its number of hard links
+#[cfg(not(unix))]
+fn links(_: &Metadata) -> Option<((u64, u64), u64)> {
+    None
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{Dir, FileSystem, FileSystemMut, FsDir, StdFs};
+
+/// A change which a [`DryRun`] filesystem was asked to make, but didn't.
+///
+/// The `Display` impl describes the change on a single line, such as
+/// `write 12 bytes to "/srv/app/config.toml"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DryRunOp {
+    /// A file would have been written with this many bytes
+    Write { path: PathBuf, len: u64 },
+    /// A file would have been copied
+    Copy { from: PathBuf, to: PathBuf },
+    /// A file or directory would have been renamed
+    Rename { from: PathBuf, to: PathBuf },
+    /// A directory would have been created
+    CreateDir { path: PathBuf },
+    /// A directory and its parents would have been created
+    CreateDirAll { path: PathBuf },
+    /// A file would have been removed
+    RemoveFile { path: PathBuf },
+    /// An empty directory would have been removed
+    RemoveDir { path: PathBuf },
+    /// A directory and its contents would have been removed
+    RemoveDirAll { path: PathBuf },
+}
+
+impl fmt::Display for DryRunOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DryRunOp::Write { path, len } => write!(f, "write {len} bytes to {path:?}"),
+            DryRunOp::Copy { from, to } => write!(f, "copy {from:?} to {to:?}"),
+            DryRunOp::Rename { from, to } => write!(f, "rename {from:?} to {to:?}"),
+            DryRunOp::CreateDir { path } => write!(f, "create directory {path:?}"),
+            DryRunOp::CreateDirAll { path } => write!(f, "create directories {path:?}"),
+            DryRunOp::RemoveFile { path } => write!(f, "remove file {path:?}"),
+            DryRunOp::RemoveDir { path } => write!(f, "remove directory {path:?}"),
+            DryRunOp::RemoveDirAll { path } => write!(f, "remove directory tree {path:?}"),
+        }
+    }
+}
+
+/// A filesystem which records changes instead of making them, for "show me
+/// what you would do" modes.
+///
+/// Reads go to the wrapped filesystem as usual, so they don't reflect the
+/// changes which were recorded. Mutating calls succeed without touching the
+/// disk, and are appended to a log which can be inspected with
+/// [`DryRun::ops`]:
+///
+/// ```no_run
+/// # use working_dir::Dir;
+/// # fn main() -> std::io::Result<()> {
+/// let project = Dir::new("my/project");
+/// let dry = project.dry_run();
+/// dry.write("out/report.txt", "done")?;
+/// dry.remove_file("stale.lock")?;
+/// for op in dry.fs().ops() {
+///     println!("would {op}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Cloning a `DryRun` shares its log.
+#[derive(Debug, Clone, Default)]
+pub struct DryRun<F = StdFs> {
+    inner: F,
+    log: Arc<Mutex<Vec<DryRunOp>>>,
+}
+
+impl<F> DryRun<F> {
+    /// Wraps `inner`, recording changes instead of making them
+    pub fn new(inner: F) -> Self {
+        DryRun {
+            inner,
+            log: Arc::default(),
+        }
+    }
+
+    /// Returns the changes recorded so far, in the order they were made
+    pub fn ops(&self) -> Vec<DryRunOp> {
+        self.lock().clone()
+    }
+
+    /// Returns the changes recorded so far, and clears the log
+    pub fn take_ops(&self) -> Vec<DryRunOp> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<DryRunOp>> {
+        self.log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record(&self, op: DryRunOp) -> Result<()> {
+        self.lock().push(op);
+        Ok(())
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a view of this working directory whose mutating methods
+    /// record what they would have done, without doing it. See [`DryRun`].
+    pub fn dry_run(&self) -> FsDir<&Path, DryRun> {
+        Dir::with_fs(self.0.as_ref(), DryRun::new(StdFs))
+    }
+}
+
+impl<F: FileSystem> FileSystem for DryRun<F> {
+    type File = F::File;
+    type Metadata = F::Metadata;
+    type ReadDir = F::ReadDir;
+
+    fn open_readonly(&self, path: &Path) -> Result<F::File> {
+        self.inner.open_readonly(path)
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        self.inner.try_exists(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<F::Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<F::Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        self.inner.read_to_string(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<F::ReadDir> {
+        self.inner.read_dir(path)
+    }
+}
+
+impl<F: FileSystem> FileSystemMut for DryRun<F> {
+    /// Always fails with [`ErrorKind::Unsupported`], since there's no file
+    /// to return without creating one
+    fn create_new(&self, path: &Path) -> Result<F::File> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("can't create {path:?} in a dry run"),
+        ))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.record(DryRunOp::Write {
+            path: path.to_owned(),
+            len: contents.len() as u64,
+        })
+    }
+
+    /// Records the copy, returning the length the file has now
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+        let len = self.inner.read(from)?.len() as u64;
+        self.record(DryRunOp::Copy {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        })?;
+        Ok(len)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.record(DryRunOp::Rename {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        })
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        self.record(DryRunOp::CreateDir {
+            path: path.to_owned(),
+        })
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.record(DryRunOp::CreateDirAll {
+            path: path.to_owned(),
+        })
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.record(DryRunOp::RemoveFile {
+            path: path.to_owned(),
+        })
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        self.record(DryRunOp::RemoveDir {
+            path: path.to_owned(),
+        })
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.record(DryRunOp::RemoveDirAll {
+            path: path.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::flush::sync_dir;
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Flushes the contents and metadata of the file at the provided path
+    /// to disk, so that they survive a crash or power loss.
+    ///
+    /// See: [`std::fs::File::sync_all`]
+    pub fn sync_file<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("sync_file", self.0.as_ref(), path, || {
+            // Windows requires write access to flush a file
+            let file = OpenOptions::new()
+                .read(true)
+                .write(cfg!(windows))
+                .open(path)?;
+            file.sync_all()
+        })
+    }
+
+    /// Flushes the working directory itself to disk, making the creation,
+    /// removal, and renaming of its entries durable. On Linux, a newly
+    /// created or renamed file may disappear after a crash unless its
+    /// directory is synced, even if the file itself was.
+    ///
+    /// Directories can't be opened for syncing on Windows, where this does
+    /// nothing.
+    pub fn sync_root(&self) -> Result<()> {
+        let root = self.0.as_ref();
+        timed("sync_root", root, root, || sync_dir(root))
+    }
+
+    /// Writes a slice as the entire contents of a file, replacing it
+    /// atomically: the contents are written to a temporary file in the same
+    /// directory, which is then renamed over the destination. Readers see
+    /// either the old contents or the new contents, never a partial write.
+    ///
+    /// This doesn't sync anything to disk, so after a crash the file may
+    /// hold either version. Use [`Dir::write_atomic_durable`] when the new
+    /// contents must survive a crash once this returns.
+    pub fn write_atomic<P2: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P2,
+        contents: C,
+    ) -> Result<()> {
+        self.replace_with(path.as_ref(), contents.as_ref(), false)
+    }
+
+    /// Like [`Dir::write_atomic`], but durable: the temporary file is synced
+    /// before it's renamed, and the parent directory is synced afterwards,
+    /// so once this returns, the new contents survive a crash or power loss.
+    pub fn write_atomic_durable<P2: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P2,
+        contents: C,
+    ) -> Result<()> {
+        self.replace_with(path.as_ref(), contents.as_ref(), true)
+    }
+
+    fn replace_with(&self, path: &Path, contents: &[u8], durable: bool) -> Result<()> {
+        let dest = self.join(path);
+        let tmp = temp_sibling(&dest);
+        timed("write", self.0.as_ref(), &dest, || {
+            let write = || {
+                let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+                file.write_all(contents)?;
+                if durable {
+                    file.sync_all()?;
+                }
+                drop(file);
+                fs::rename(&tmp, &dest)
+            };
+            if let Err(err) = write() {
+                let _ = fs::remove_file(&tmp);
+                return Err(err);
+            }
+            if durable {
+                match dest.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => sync_dir(parent)?,
+                    _ => sync_dir(Path::new("."))?,
+                }
+            }
+            Ok(contents.len() as u64)
+        })
+        .map(|_| ())
+    }
+}
+
+/// Picks a unique name for a temporary file next to `path`
+fn temp_sibling(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
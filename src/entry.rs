@@ -0,0 +1,169 @@
+use std::ffi::OsString;
+use std::fs::{DirEntry, FileType, Metadata, ReadDir};
+use std::io::Result;
+use std::path::{Component, Path, PathBuf};
+
+use crate::Dir;
+
+/// An entry within a working directory, as yielded by
+/// [`Dir::read_dir_relative`] and [`Dir::walk`].
+///
+/// Unlike [`DirEntry`], the path of an `Entry` is relative to the
+/// working directory it was read from.
+#[derive(Debug)]
+pub struct Entry {
+    path: PathBuf,
+    depth: usize,
+    inner: DirEntry,
+}
+
+impl Entry {
+    pub(crate) fn new(parent: &Path, depth: usize, inner: DirEntry) -> Entry {
+        Entry {
+            path: parent.join(inner.file_name()),
+            depth,
+            inner,
+        }
+    }
+
+    /// Returns the path of this entry, relative to the working directory
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the full path of this entry, joined onto the working directory
+    ///
+    /// See: [`DirEntry::path`]
+    #[inline]
+    pub fn abs_path(&self) -> PathBuf {
+        self.inner.path()
+    }
+
+    /// Returns the number of components between the directory that was
+    /// read and this entry. Entries directly within it have depth 1.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the bare file name of this entry, without any leading path
+    ///
+    /// See: [`DirEntry::file_name`]
+    #[inline]
+    pub fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+
+    /// Returns the file type of this entry. Symbolic links are not followed.
+    ///
+    /// See: [`DirEntry::file_type`]
+    #[inline]
+    pub fn file_type(&self) -> Result<FileType> {
+        self.inner.file_type()
+    }
+
+    /// Returns the metadata of this entry. Symbolic links are not followed.
+    ///
+    /// See: [`DirEntry::metadata`]
+    #[inline]
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.inner.metadata()
+    }
+
+    /// Returns the underlying [`DirEntry`]
+    #[inline]
+    pub fn into_inner(self) -> DirEntry {
+        self.inner
+    }
+}
+
+/// An iterator over the entries within a directory, with paths relative to
+/// the working directory.
+///
+/// Created with [`Dir::read_dir_relative`].
+#[derive(Debug)]
+pub struct ReadDirRelative {
+    parent: PathBuf,
+    inner: ReadDir,
+}
+
+impl Iterator for ReadDirRelative {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next()?;
+        Some(entry.map(|entry| Entry::new(&self.parent, 1, entry)))
+    }
+}
+
+/// An iterator over the entries within a directory, along with their
+/// metadata.
+///
+/// Created with [`Dir::read_dir_with_metadata`].
+#[derive(Debug)]
+pub struct ReadDirMetadata {
+    inner: ReadDir,
+}
+
+impl Iterator for ReadDirMetadata {
+    type Item = Result<(DirEntry, Metadata)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next()?;
+        Some(entry.and_then(|entry| {
+            let metadata = entry.metadata()?;
+            Ok((entry, metadata))
+        }))
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns an iterator over the entries within a directory, yielding
+    /// each entry together with its metadata.
+    ///
+    /// The metadata is obtained through [`DirEntry::metadata`], which does
+    /// not follow symbolic links. On Windows it is returned by the directory
+    /// listing itself, and on Unix it is queried relative to the open
+    /// directory, so this is cheaper than calling [`Dir::metadata`] on the
+    /// path of each entry.
+    ///
+    /// See: [`Dir::read_dir`]
+    pub fn read_dir_with_metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<ReadDirMetadata> {
+        Ok(ReadDirMetadata {
+            inner: self.read_dir(path)?,
+        })
+    }
+
+    /// Returns an iterator over the entries within a directory, whose
+    /// paths are relative to this working directory rather than joined
+    /// onto it.
+    ///
+    /// See: [`Dir::read_dir`], [`Entry`]
+    pub fn read_dir_relative<P2: AsRef<Path>>(&self, path: P2) -> Result<ReadDirRelative> {
+        let path = path.as_ref();
+        Ok(ReadDirRelative {
+            parent: self.relative_base(path),
+            inner: self.read_dir(path)?,
+        })
+    }
+
+    /// Returns the path that entries beneath `path` are reported relative
+    /// to: `path` itself, with the working directory and any `.` components
+    /// removed
+    pub(crate) fn relative_base(&self, path: &Path) -> PathBuf {
+        let joined = self.join(path);
+        self.relative(&joined)
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    }
+
+    /// Strips the working directory from a path produced by joining onto it
+    pub(crate) fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.0.as_ref()).unwrap_or(path)
+    }
+}
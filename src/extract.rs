@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read, Result};
+use std::path::{Component, Path, PathBuf};
+
+use crate::sync::symlink;
+use crate::Dir;
+
+/// The kind of an archive entry, as far as extraction is concerned
+// Only tar archives contain hard links and special files
+#[cfg_attr(not(feature = "tar"), allow(dead_code))]
+enum Kind {
+    Dir,
+    File { mode: Option<u32> },
+    Symlink(PathBuf),
+    HardLink(PathBuf),
+    Skip,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Extracts the zip archive at `archive` into the directory `dest`,
+    /// creating it if necessary. Returns the number of files and links
+    /// extracted.
+    ///
+    /// This is safe to use with untrusted archives: every entry is checked
+    /// before anything is written, and the archive is rejected if any entry
+    /// would be written outside of `dest` (an absolute path, or one which
+    /// climbs out with `..`), or if any symbolic link points outside of it.
+    /// Entries are never written through symbolic links: existing links are
+    /// replaced rather than followed, and an entry beneath a link is an
+    /// error.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidData`] if
+    /// the archive is malformed or contains an unsafe entry.
+    #[cfg(feature = "zip")]
+    pub fn extract_zip<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        archive: P2,
+        dest: P3,
+    ) -> Result<u64> {
+        let mut zip = zip::ZipArchive::new(io::BufReader::new(self.open_readonly(archive)?))?;
+        let mut entries = Vec::with_capacity(zip.len());
+        let mut links = Links::default();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            let path = checked_path(Path::new(&*file.name()?))?;
+            let kind = if file.is_dir() {
+                Kind::Dir
+            } else if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                Kind::Symlink(links.check(&path, Path::new(&target))?)
+            } else {
+                Kind::File {
+                    mode: file.unix_mode(),
+                }
+            };
+            entries.push((path, kind));
+        }
+
+        let dest = self.join(dest);
+        fs::create_dir_all(&dest)?;
+        let mut extracted = 0;
+        for (i, (path, kind)) in entries.into_iter().enumerate() {
+            extracted += unpack_entry(&dest, &path, kind, &mut zip.by_index(i)?)?;
+        }
+        Ok(extracted)
+    }
+
+    /// Extracts the tar archive at `archive` into the directory `dest`,
+    /// creating it if necessary. Returns the number of files and links
+    /// extracted.
+    ///
+    /// Like [`Dir::extract_zip`], every entry is checked before anything is
+    /// written, so this is safe to use with untrusted archives. Devices and
+    /// other special files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidData`] if
+    /// the archive is malformed or contains an unsafe entry.
+    #[cfg(feature = "tar")]
+    pub fn extract_tar<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        archive: P2,
+        dest: P3,
+    ) -> Result<u64> {
+        let archive = archive.as_ref();
+        // Tar has no index, so the archive is read once to check it, and
+        // again to extract it
+        let mut links = Links::default();
+        for entry in
+            tar::Archive::new(io::BufReader::new(self.open_readonly(archive)?)).entries()?
+        {
+            tar_entry_kind(&entry?, &mut links)?;
+        }
+        let reader = io::BufReader::new(self.open_readonly(archive)?);
+        unpack_tar(reader, &self.join(dest))
+    }
+}
+
+/// Extracts a tar stream into `dest`, checking each entry before it's
+/// written
+#[cfg(feature = "tar")]
+pub(crate) fn unpack_tar<R: Read>(reader: R, dest: &Path) -> Result<u64> {
+    fs::create_dir_all(dest)?;
+    let mut extracted = 0;
+    let mut links = Links::default();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let (path, kind) = tar_entry_kind(&entry, &mut links)?;
+        extracted += unpack_entry(dest, &path, kind, &mut entry)?;
+    }
+    Ok(extracted)
+}
+
+#[cfg(feature = "tar")]
+fn tar_entry_kind<R: Read>(
+    entry: &tar::Entry<'_, R>,
+    links: &mut Links,
+) -> Result<(PathBuf, Kind)> {
+    let path = checked_path(&entry.path()?)?;
+    let header = entry.header();
+    let entry_type = header.entry_type();
+    let link_name = || {
+        entry.link_name()?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("link {path:?} has no target"),
+            )
+        })
+    };
+    let kind = if entry_type.is_dir() {
+        Kind::Dir
+    } else if entry_type.is_file() || entry_type.is_contiguous() {
+        Kind::File {
+            mode: header.mode().ok(),
+        }
+    } else if entry_type.is_symlink() {
+        Kind::Symlink(links.check(&path, &link_name()?)?)
+    } else if entry_type.is_hard_link() {
+        Kind::HardLink(checked_path(&link_name()?)?)
+    } else {
+        Kind::Skip
+    };
+    Ok((path, kind))
+}
+
+/// Writes a single checked entry beneath `dest`, returning the number of
+/// files and links written
+fn unpack_entry(dest: &Path, path: &Path, kind: Kind, contents: &mut dyn Read) -> Result<u64> {
+    let target = dest.join(path);
+    if matches!(kind, Kind::Skip) {
+        return Ok(0);
+    }
+    check_parents(dest, path)?;
+    if let Kind::Dir = kind {
+        fs::create_dir_all(&target)?;
+        return Ok(0);
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Replace anything other than a directory, so that a symlink which
+    // already exists can't redirect the write
+    match fs::symlink_metadata(&target) {
+        Ok(meta) if !meta.is_dir() => fs::remove_file(&target)?,
+        _ => {}
+    }
+    match kind {
+        Kind::File { mode } => {
+            let mut file = File::create(&target)?;
+            io::copy(contents, &mut file)?;
+            set_mode(&file, mode)?;
+        }
+        Kind::Symlink(link) => symlink(&link, &target, &target)?,
+        Kind::HardLink(original) => {
+            check_parents(dest, &original)?;
+            fs::hard_link(dest.join(original), &target)?
+        }
+        Kind::Dir | Kind::Skip => unreachable!(),
+    }
+    Ok(1)
+}
+
+#[cfg(unix)]
+fn set_mode(file: &File, mode: Option<u32>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => file.set_permissions(fs::Permissions::from_mode(mode & 0o777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_: &File, _: Option<u32>) -> Result<()> {
+    Ok(())
+}
+
+/// Checks that none of the directories leading to `path` are symlinks,
+/// since an earlier entry could have created one pointing anywhere
+fn check_parents(dest: &Path, path: &Path) -> Result<()> {
+    let mut parent = dest.to_owned();
+    for component in path.parent().into_iter().flat_map(Path::components) {
+        parent.push(component);
+        match fs::symlink_metadata(&parent) {
+            Ok(meta) if meta.file_type().is_symlink() => return Err(unsafe_entry(path)),
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+fn unsafe_entry(path: &Path) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("archive entry {path:?} would be extracted outside of the destination"),
+    )
+}
+
+/// Checks that an entry path is relative and stays beneath the
+/// destination, returning it without any `.` components
+fn checked_path(path: &Path) -> Result<PathBuf> {
+    let mut checked = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => checked.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(unsafe_entry(path))
+            }
+        }
+    }
+    if checked.as_os_str().is_empty() {
+        return Err(unsafe_entry(path));
+    }
+    Ok(checked)
+}
+
+/// The symbolic links in an archive, by the path they're extracted to, so
+/// that each link's target can be resolved through the others
+#[derive(Default)]
+struct Links(BTreeMap<PathBuf, PathBuf>);
+
+impl Links {
+    /// Checks that a symlink at `link` pointing to `target` resolves to
+    /// somewhere beneath the destination, and records it. Links checked
+    /// earlier are checked again, since a link can only be resolved through
+    /// those which exist when it's followed, whatever order they were
+    /// extracted in.
+    fn check(&mut self, link: &Path, target: &Path) -> Result<PathBuf> {
+        self.0.insert(link.to_owned(), target.to_owned());
+        if !self.stays_inside(link, target) {
+            return Err(unsafe_entry(link));
+        }
+        // Only a target with `..` in it can be led astray by a new link
+        for (earlier, target) in &self.0 {
+            let climbs = target.components().any(|c| c == Component::ParentDir);
+            if climbs && !self.stays_inside(earlier, target) {
+                return Err(unsafe_entry(link));
+            }
+        }
+        Ok(target.to_owned())
+    }
+
+    /// Resolves `target` against the directory containing `link`. `..`
+    /// can't be resolved lexically once the path has passed through another
+    /// link, since it leads out of wherever that link points, so it's
+    /// rejected.
+    fn stays_inside(&self, link: &Path, target: &Path) -> bool {
+        let mut resolved = link.parent().map_or_else(PathBuf::new, Path::to_owned);
+        for component in target.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if resolved.ancestors().any(|dir| self.0.contains_key(dir)) {
+                        return false;
+                    }
+                    if !resolved.pop() {
+                        return false;
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => return false,
+            }
+        }
+        true
+    }
+}
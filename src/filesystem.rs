@@ -0,0 +1,185 @@
+use std::fs::{self, File, Metadata, ReadDir};
+use std::io::{Read, Result};
+use std::path::Path;
+
+/// The filesystem an [`FsDir`](crate::FsDir) performs its operations on.
+///
+/// The methods of an `FsDir`, such as [`FsDir::read`](crate::FsDir::read),
+/// [`FsDir::write`](crate::FsDir::write), and
+/// [`FsDir::metadata`](crate::FsDir::metadata), join their paths onto the
+/// working directory and then call the corresponding method here, so code
+/// written against an `FsDir<P, F>` works the same whichever backend it's
+/// given. A [`Dir`](crate::Dir) always uses [`StdFs`], which calls into
+/// [`std::fs`].
+///
+/// Every path passed to a `FileSystem` has already been joined onto the
+/// working directory. Methods which depend on the operating system, such as
+/// walks, archives, and anything taking [`std::fs::OpenOptions`], are only
+/// available on a `Dir`.
+pub trait FileSystem {
+    /// An open file, as returned by [`FileSystem::open_readonly`]
+    type File;
+    /// Information about an entry, as returned by [`FileSystem::metadata`]
+    type Metadata;
+    /// The entries of a directory, as returned by [`FileSystem::read_dir`]
+    type ReadDir;
+
+    /// See: [`Dir::open_readonly`](crate::Dir::open_readonly)
+    fn open_readonly(&self, path: &Path) -> Result<Self::File>;
+
+    /// See: [`Dir::try_exists`](crate::Dir::try_exists)
+    fn try_exists(&self, path: &Path) -> Result<bool>;
+
+    /// See: [`Dir::exists`](crate::Dir::exists)
+    fn exists(&self, path: &Path) -> bool {
+        self.try_exists(path).unwrap_or(false)
+    }
+
+    /// See: [`Dir::metadata`](crate::Dir::metadata)
+    fn metadata(&self, path: &Path) -> Result<Self::Metadata>;
+
+    /// See: [`Dir::symlink_metadata`](crate::Dir::symlink_metadata)
+    fn symlink_metadata(&self, path: &Path) -> Result<Self::Metadata>;
+
+    /// See: [`Dir::read`](crate::Dir::read)
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// See: [`Dir::read_to_string`](crate::Dir::read_to_string)
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+
+    /// See: [`Dir::read_dir`](crate::Dir::read_dir)
+    fn read_dir(&self, path: &Path) -> Result<Self::ReadDir>;
+}
+
+/// The operations of a [`FileSystem`] which modify it.
+///
+/// An [`FsDir`](crate::FsDir) only offers its mutating methods, such as
+/// [`FsDir::write`](crate::FsDir::write) and
+/// [`FsDir::remove_file`](crate::FsDir::remove_file), when its filesystem
+/// implements this trait. See [`ReadOnly`](crate::ReadOnly) for a filesystem
+/// which doesn't.
+pub trait FileSystemMut: FileSystem {
+    /// See: [`Dir::create_new`](crate::Dir::create_new)
+    fn create_new(&self, path: &Path) -> Result<Self::File>;
+
+    /// See: [`Dir::write`](crate::Dir::write)
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()>;
+
+    /// See: [`Dir::copy`](crate::Dir::copy)
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64>;
+
+    /// See: [`Dir::rename`](crate::Dir::rename)
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// See: [`Dir::create_dir`](crate::Dir::create_dir)
+    fn create_dir(&self, path: &Path) -> Result<()>;
+
+    /// See: [`Dir::create_dir_all`](crate::Dir::create_dir_all)
+    fn create_dir_all(&self, path: &Path) -> Result<()>;
+
+    /// See: [`Dir::remove_file`](crate::Dir::remove_file)
+    fn remove_file(&self, path: &Path) -> Result<()>;
+
+    /// See: [`Dir::remove_dir`](crate::Dir::remove_dir)
+    fn remove_dir(&self, path: &Path) -> Result<()>;
+
+    /// See: [`Dir::remove_dir_all`](crate::Dir::remove_dir_all)
+    fn remove_dir_all(&self, path: &Path) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The operating system's filesystem, accessed through [`std::fs`]. This is
+/// the [`FileSystem`] a [`Dir`](crate::Dir) uses, and the default one for an
+/// [`FsDir`](crate::FsDir).
+pub struct StdFs;
+
+impl FileSystem for StdFs {
+    type File = File;
+    type Metadata = Metadata;
+    type ReadDir = ReadDir;
+
+    fn open_readonly(&self, path: &Path) -> Result<File> {
+        File::open(path)
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        path.try_exists()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        fs::metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        fs::symlink_metadata(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut contents = Vec::with_capacity(len_hint(&file));
+        file.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        let mut file = File::open(path)?;
+        let mut contents = String::with_capacity(len_hint(&file));
+        file.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        fs::read_dir(path)
+    }
+}
+
+/// Returns the length of `file`, for sizing the buffer it's read into, or
+/// zero if it's unknown. `read_to_end` probes for the end of the file before
+/// growing a full buffer, so a buffer of exactly this size is never
+/// reallocated.
+pub(crate) fn len_hint(file: &File) -> usize {
+    file.metadata()
+        .map_or(0, |meta| usize::try_from(meta.len()).unwrap_or(0))
+}
+
+impl FileSystemMut for StdFs {
+    fn create_new(&self, path: &Path) -> Result<File> {
+        File::create_new(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+        fs::copy(from, to)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        fs::remove_dir_all(path)
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::Dir;
+
+impl Dir<PathBuf> {
+    /// Returns the directory containing the `Cargo.toml` of the package
+    /// being built, run, or tested, as given by the `CARGO_MANIFEST_DIR`
+    /// environment variable which Cargo sets.
+    ///
+    /// Paths relative to this don't depend on where `cargo test` was
+    /// invoked from. Cargo only sets the variable when running a program,
+    /// so this fails when a binary is run directly. The
+    /// [`manifest_dir!`](crate::manifest_dir) macro reads the variable at
+    /// compile time instead, which always works.
+    pub fn manifest_dir() -> Result<Dir<PathBuf>> {
+        match std::env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => Ok(Dir::new(PathBuf::from(dir))),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                "CARGO_MANIFEST_DIR isn't set, since this wasn't run by cargo",
+            )),
+        }
+    }
+
+    /// Returns the directory `path` beneath [`Dir::manifest_dir`], such as
+    /// `tests/data`, failing if it isn't a directory.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let data = Dir::fixture("tests/data")?;
+    /// let expected = data.read_to_string("expected.txt")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// See also the [`fixture!`](crate::fixture) macro.
+    pub fn fixture<P2: AsRef<Path>>(path: P2) -> Result<Dir<PathBuf>> {
+        let dir = Dir::new(Dir::manifest_dir()?.join(path));
+        match dir.metadata("") {
+            Ok(meta) if meta.is_dir() => Ok(dir),
+            Ok(_) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("fixture {:?} isn't a directory", dir.0),
+            )),
+            Err(err) => Err(Error::new(
+                err.kind(),
+                format!("couldn't find fixture {:?}: {err}", dir.0),
+            )),
+        }
+    }
+}
+
+/// Returns a [`Dir`] for the directory containing the `Cargo.toml` of the
+/// crate this is used in, from `CARGO_MANIFEST_DIR` at compile time.
+///
+/// ```no_run
+/// let root = working_dir::manifest_dir!();
+/// let manifest = root.read_to_string("Cargo.toml")?;
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// The result is a `Dir<&'static Path>`. See also [`Dir::manifest_dir`].
+#[macro_export]
+macro_rules! manifest_dir {
+    () => {
+        $crate::Dir::new(::std::path::Path::new(env!("CARGO_MANIFEST_DIR")))
+    };
+}
+
+/// Returns a [`Dir`] for a directory beneath the `Cargo.toml` of the crate
+/// this is used in, such as `tests/data`, from `CARGO_MANIFEST_DIR` at
+/// compile time.
+///
+/// ```no_run
+/// let data = working_dir::fixture!("tests/data");
+/// let input = data.read("input.bin")?;
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// The path must be a string literal, and the result is a
+/// `Dir<&'static Path>`. See also [`Dir::fixture`].
+#[macro_export]
+macro_rules! fixture {
+    ($path:literal) => {
+        $crate::Dir::new(::std::path::Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/",
+            $path
+        )))
+    };
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::Dir;
+
+type FlushFn = Box<dyn FnMut() -> Result<()> + Send>;
+
+enum Target {
+    /// A file, opened for writing and synced when flushed
+    Path(PathBuf),
+    /// An open handle, synced when flushed
+    File(File),
+    /// Any other finalization step
+    Custom(FlushFn),
+}
+
+struct Registration {
+    deps: Vec<String>,
+    target: Target,
+}
+
+/// A registry of files and finalizers that must be made durable before a
+/// service using a working directory shuts down.
+///
+/// Components enroll the files or handles they have written, along with the
+/// names of any other registrations that must be flushed first. A single
+/// call to [`FlushRegistry::flush_all`] or [`FlushRegistry::shutdown`] (for
+/// example, from a `SIGTERM` handler) then syncs everything in dependency
+/// order, followed by the working directory itself.
+///
+/// The registry can be shared between threads.
+///
+/// ```no_run
+/// use working_dir::{Dir, FlushRegistry};
+///
+/// let registry = FlushRegistry::new(Dir("/var/lib/app"));
+/// registry.register_path("segments", "data/segment-0001", &[])?;
+/// // The index refers to the segments, so they must reach the disk first
+/// registry.register_path("index", "data/index", &["segments"])?;
+///
+/// registry.shutdown()?;
+/// # std::io::Result::Ok(())
+/// ```
+pub struct FlushRegistry<P>
+where
+    P: AsRef<Path>,
+{
+    dir: Dir<P>,
+    registrations: Mutex<HashMap<String, Registration>>,
+}
+
+impl<P> FlushRegistry<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates an empty registry for the given working directory
+    pub fn new(dir: Dir<P>) -> FlushRegistry<P> {
+        FlushRegistry {
+            dir,
+            registrations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the working directory this registry flushes
+    #[inline]
+    pub fn dir(&self) -> &Dir<P> {
+        &self.dir
+    }
+
+    /// Registers a file, relative to the working directory, which is opened
+    /// for writing and synced to disk when the registry is flushed. The file
+    /// must exist by then, and isn't truncated.
+    ///
+    /// `deps` names registrations which must be flushed before this one.
+    /// Registering a name which is already registered replaces it.
+    pub fn register_path<P2: AsRef<Path>>(
+        &self,
+        name: &str,
+        path: P2,
+        deps: &[&str],
+    ) -> Result<()> {
+        let path = path.as_ref().to_owned();
+        self.insert(name, deps, Target::Path(path))
+    }
+
+    /// Registers an open file handle, which is synced to disk when the
+    /// registry is flushed
+    ///
+    /// See: [`FlushRegistry::register_path`]
+    pub fn register_file(&self, name: &str, file: File, deps: &[&str]) -> Result<()> {
+        self.insert(name, deps, Target::File(file))
+    }
+
+    /// Registers a finalizer, such as flushing a buffered writer or writing
+    /// a footer, which is run when the registry is flushed
+    ///
+    /// See: [`FlushRegistry::register_path`]
+    pub fn register<F>(&self, name: &str, deps: &[&str], finalize: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()> + Send + 'static,
+    {
+        self.insert(name, deps, Target::Custom(Box::new(finalize)))
+    }
+
+    /// Removes a registration. Returns `true` if it was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.lock().remove(name).is_some()
+    }
+
+    /// Flushes every registration, each after its dependencies, then syncs
+    /// the working directory itself so that newly created and renamed
+    /// files are durable. Registrations are kept, so this may be called
+    /// repeatedly.
+    ///
+    /// # Errors
+    ///
+    /// Flushing stops at the first failure, so a registration is never
+    /// flushed unless its dependencies were. An error is also returned if
+    /// a dependency is not registered, or if the dependencies form a cycle.
+    pub fn flush_all(&self) -> Result<()> {
+        let mut registrations = self.lock();
+        for name in flush_order(&registrations)? {
+            let registration = registrations
+                .get_mut(&name)
+                .expect("flush order names a registration");
+            let result = match &mut registration.target {
+                // Windows only flushes handles with write access
+                Target::Path(path) => self
+                    .dir
+                    .open(&path, OpenOptions::new().write(true))
+                    .and_then(|file| file.sync_all()),
+                Target::File(file) => file.sync_all(),
+                Target::Custom(finalize) => finalize(),
+            };
+            result.map_err(|err| {
+                Error::new(err.kind(), format!("failed to flush {name:?}: {err}"))
+            })?;
+        }
+        sync_dir(self.dir.as_ref())
+    }
+
+    /// Flushes every registration like [`FlushRegistry::flush_all`], then
+    /// removes them all, closing any registered handles.
+    pub fn shutdown(&self) -> Result<()> {
+        self.flush_all()?;
+        self.lock().clear();
+        Ok(())
+    }
+
+    fn insert(&self, name: &str, deps: &[&str], target: Target) -> Result<()> {
+        if deps.contains(&name) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{name:?} can not depend on itself"),
+            ));
+        }
+        let deps = deps.iter().map(|&dep| dep.to_owned()).collect();
+        self.lock()
+            .insert(name.to_owned(), Registration { deps, target });
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Registration>> {
+        // A panicking finalizer doesn't invalidate the other registrations
+        self.registrations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<P> Debug for FlushRegistry<P>
+where
+    P: AsRef<Path>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = self.lock().keys().cloned().collect();
+        names.sort();
+        f.debug_struct("FlushRegistry")
+            .field("dir", &self.dir)
+            .field("registrations", &names)
+            .finish()
+    }
+}
+
+/// Orders registrations so that each comes after its dependencies. Ties are
+/// broken by name, so the order is deterministic.
+fn flush_order(registrations: &HashMap<String, Registration>) -> Result<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        name: &'a str,
+        registrations: &'a HashMap<String, Registration>,
+        state: &mut HashMap<&'a str, State>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        match state.get(name) {
+            Some(State::Done) => return Ok(()),
+            Some(State::Visiting) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("flush dependencies of {name:?} form a cycle"),
+                ))
+            }
+            None => {}
+        }
+        let registration = registrations.get(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("flush dependency {name:?} is not registered"),
+            )
+        })?;
+
+        state.insert(name, State::Visiting);
+        let mut deps: Vec<_> = registration.deps.iter().collect();
+        deps.sort();
+        for dep in deps {
+            visit(dep, registrations, state, order)?;
+        }
+        state.insert(name, State::Done);
+        order.push(name.to_owned());
+        Ok(())
+    }
+
+    let mut names: Vec<_> = registrations.keys().collect();
+    names.sort();
+    let mut state = HashMap::new();
+    let mut order = Vec::with_capacity(names.len());
+    for name in names {
+        visit(name, registrations, &mut state, &mut order)?;
+    }
+    Ok(order)
+}
+
+/// Syncs a directory, making the creation and renaming of its entries
+/// durable. Directories can't be opened as files on Windows, where this
+/// does nothing.
+pub(crate) fn sync_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        File::open(path)?.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+use crate::crc32::crc32;
+use crate::Dir;
+
+/// Identifies a framed file, and the version of the format
+const MAGIC: &[u8; 4] = b"WDF1";
+
+/// A length which marks the end of the frames
+const END: u32 = u32::MAX;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Writes a sequence of chunks to a file, each protected by a checksum.
+    ///
+    /// The file starts with the 4-byte magic `WDF1`. Each chunk is stored as
+    /// a little-endian `u32` length, the chunk's bytes, and a little-endian
+    /// CRC-32 of the length and bytes. The file ends with a `u32::MAX`
+    /// length marker followed by the number of chunks, so that truncated
+    /// files can be detected. Chunks must be smaller than 4 GiB.
+    ///
+    /// This function will create a file if it does not exist, and will
+    /// entirely replace its contents if it does.
+    ///
+    /// See: [`Dir::read_framed`]
+    pub fn write_framed<P2, I, C>(&self, path: P2, chunks: I) -> Result<()>
+    where
+        P2: AsRef<Path>,
+        I: IntoIterator<Item = C>,
+        C: AsRef<[u8]>,
+    {
+        let mut out = BufWriter::new(File::create(self.join(path))?);
+        out.write_all(MAGIC)?;
+
+        let mut count: u32 = 0;
+        for chunk in chunks {
+            let chunk = chunk.as_ref();
+            let len = u32::try_from(chunk.len())
+                .ok()
+                .filter(|&len| len != END)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "chunk is too large to frame"))?;
+
+            let mut framed = Vec::with_capacity(chunk.len() + 8);
+            framed.extend_from_slice(&len.to_le_bytes());
+            framed.extend_from_slice(chunk);
+            let crc = crc32(&framed);
+            framed.extend_from_slice(&crc.to_le_bytes());
+            out.write_all(&framed)?;
+
+            count = count
+                .checked_add(1)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "too many chunks to frame"))?;
+        }
+
+        out.write_all(&END.to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+        out.into_inner().map_err(|err| err.into_error())?.sync_all()
+    }
+
+    /// Reads every chunk from a file written by [`Dir::write_framed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidData`] if the file is not
+    /// a framed file, if any chunk fails its checksum, or if the file was
+    /// truncated. A chunk's length is checked against the size of the file
+    /// before any memory is allocated for it, so a corrupt length can't
+    /// exhaust memory.
+    pub fn read_framed<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<Vec<u8>>> {
+        let file = self.open_readonly(path)?;
+        let file_len = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+        let truncated = |err: Error| match err.kind() {
+            ErrorKind::UnexpectedEof => invalid("framed file is truncated"),
+            _ => err,
+        };
+
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic).map_err(truncated)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a framed file"));
+        }
+
+        // The magic number, and each length, chunk, and checksum read so far
+        let mut consumed = MAGIC.len() as u64;
+        let mut chunks = Vec::new();
+        loop {
+            let mut word = [0u8; 4];
+            input.read_exact(&mut word).map_err(truncated)?;
+            consumed += 4;
+            let len = u32::from_le_bytes(word);
+
+            if len == END {
+                input.read_exact(&mut word).map_err(truncated)?;
+                if u32::from_le_bytes(word) as usize != chunks.len() {
+                    return Err(invalid("framed file has the wrong number of chunks"));
+                }
+                return Ok(chunks);
+            }
+
+            // The chunk and its checksum must fit in the rest of the file
+            let needed = u64::from(len) + 4;
+            if needed > file_len.saturating_sub(consumed) {
+                return Err(invalid("framed chunk is longer than the rest of the file"));
+            }
+            consumed += needed;
+
+            // The length is checksummed along with the chunk
+            let framed_len = usize::try_from(len)
+                .ok()
+                .and_then(|len| len.checked_add(4))
+                .ok_or_else(|| invalid("framed chunk is too large to read into memory"))?;
+            let mut framed = vec![0u8; framed_len];
+            framed[..4].copy_from_slice(&word);
+            input.read_exact(&mut framed[4..]).map_err(truncated)?;
+            input.read_exact(&mut word).map_err(truncated)?;
+            if crc32(&framed) != u32::from_le_bytes(word) {
+                return Err(invalid("framed chunk failed its checksum"));
+            }
+
+            framed.drain(..4);
+            chunks.push(framed);
+        }
+    }
+}
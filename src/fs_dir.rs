@@ -0,0 +1,297 @@
+use core::fmt::Debug;
+use std::io::Result;
+use std::ops::{Deref, Div};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::instrument::{timed, timed_opaque};
+use crate::{not_found_to_none, with_paths, Dir, FileSystem, FileSystemMut, StdFs};
+
+#[derive(Clone, PartialEq, PartialOrd, Eq, Ord)]
+/// A working directory which performs its operations on the [`FileSystem`]
+/// `F`, such as a [`MemoryFs`](crate::MemoryFs), rather than on the
+/// operating system's filesystem.
+///
+/// An `FsDir` has the methods of [`Dir`] which go through a `FileSystem`,
+/// such as [`FsDir::read`], [`FsDir::write`], and [`FsDir::metadata`]. Every
+/// one of them calls into `F`. Methods which depend on the operating system,
+/// such as walks, archives, and anything taking [`std::fs::OpenOptions`], are
+/// only available on a [`Dir`].
+///
+/// Code which should run against any backend takes an `FsDir`, and a `Dir`
+/// can be passed to it with [`Dir::as_fs_dir`]:
+///
+/// ```no_run
+/// # use working_dir::{Dir, FileSystemMut, FsDir};
+/// # use std::path::Path;
+/// fn save<P: AsRef<Path>, F: FileSystemMut>(dir: &FsDir<P, F>) -> std::io::Result<()> {
+///     dir.create_dir_all("out")?;
+///     dir.write("out/result.txt", "done")
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// save(&Dir::in_memory("/project"))?;
+/// save(&Dir::new("my/project").as_fs_dir())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FsDir<P, F = StdFs>(pub P, pub(crate) F)
+where
+    P: AsRef<Path>;
+
+impl<P, F> Debug for FsDir<P, F>
+where
+    P: AsRef<Path>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&Dir(self.0.as_ref()), f)
+    }
+}
+
+impl<P> From<Dir<P>> for FsDir<P>
+where
+    P: AsRef<Path>,
+{
+    #[inline]
+    fn from(dir: Dir<P>) -> Self {
+        FsDir(dir.0, StdFs)
+    }
+}
+
+impl<P, F> FsDir<P, F>
+where
+    P: AsRef<Path>,
+    F: FileSystem,
+{
+    /// Returns the filesystem this working directory performs its
+    /// operations on
+    #[inline]
+    pub fn fs(&self) -> &F {
+        &self.1
+    }
+
+    /// Returns the root of this working directory as a [`Path`]
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.0.as_ref()
+    }
+
+    /// Consumes this working directory, returning its root and filesystem
+    #[inline]
+    pub fn into_parts(self) -> (P, F) {
+        (self.0, self.1)
+    }
+
+    /// Converts this working directory into one whose root is shared. See
+    /// [`Dir::shared`].
+    pub fn into_shared(self) -> FsDir<Arc<Path>, F> {
+        FsDir(Arc::from(self.0.as_ref()), self.1)
+    }
+
+    /// See: [`Dir::join`]
+    #[inline]
+    pub fn join<P2: AsRef<Path>>(&self, path: P2) -> PathBuf {
+        Dir(self.0.as_ref()).join(path)
+    }
+
+    /// See: [`Dir::open_readonly`]
+    pub fn open_readonly<P2: AsRef<Path>>(&self, path: P2) -> Result<F::File> {
+        with_paths! { path = self / path }
+        timed_opaque("open_readonly", self.path(), path, || {
+            self.1.open_readonly(path)
+        })
+    }
+
+    /// See: [`Dir::exists`]
+    pub fn exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        with_paths! { path = self / path }
+        timed("exists", self.path(), path, || self.1.exists(path))
+    }
+
+    /// See: [`Dir::contains`]
+    #[inline]
+    pub fn contains<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.exists(path)
+    }
+
+    /// See: [`Dir::try_exists`]
+    pub fn try_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        with_paths! { path = self / path }
+        timed("try_exists", self.path(), path, || self.1.try_exists(path))
+    }
+
+    /// See: [`Dir::try_contains`]
+    #[inline]
+    pub fn try_contains<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        self.try_exists(path)
+    }
+
+    /// See: [`Dir::metadata`]
+    pub fn metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<F::Metadata> {
+        with_paths! { path = self / path }
+        timed_opaque("metadata", self.path(), path, || self.1.metadata(path))
+    }
+
+    /// See: [`Dir::metadata_opt`]
+    pub fn metadata_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<F::Metadata>> {
+        not_found_to_none(self.metadata(path))
+    }
+
+    /// See: [`Dir::read`]
+    pub fn read<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<u8>> {
+        with_paths! { path = self / path }
+        timed("read", self.path(), path, || self.1.read(path))
+    }
+
+    /// See: [`Dir::read_opt`]
+    pub fn read_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<Vec<u8>>> {
+        not_found_to_none(self.read(path))
+    }
+
+    /// See: [`Dir::read_dir`]
+    pub fn read_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<F::ReadDir> {
+        with_paths! { path = self / path }
+        timed_opaque("read_dir", self.path(), path, || self.1.read_dir(path))
+    }
+
+    /// See: [`Dir::read_to_string`]
+    pub fn read_to_string<P2: AsRef<Path>>(&self, path: P2) -> Result<String> {
+        with_paths! { path = self / path }
+        timed("read_to_string", self.path(), path, || {
+            self.1.read_to_string(path)
+        })
+    }
+
+    /// See: [`Dir::read_to_string_opt`]
+    pub fn read_to_string_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<String>> {
+        not_found_to_none(self.read_to_string(path))
+    }
+
+    /// See: [`Dir::symlink_metadata`]
+    pub fn symlink_metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<F::Metadata> {
+        with_paths! { path = self / path }
+        timed_opaque("symlink_metadata", self.path(), path, || {
+            self.1.symlink_metadata(path)
+        })
+    }
+}
+
+impl<P, F> FsDir<P, F>
+where
+    P: AsRef<Path>,
+    F: FileSystemMut,
+{
+    /// See: [`Dir::create_new`]
+    pub fn create_new<P2: AsRef<Path>>(&self, path: P2) -> Result<F::File> {
+        with_paths! { path = self / path }
+        timed_opaque("create_new", self.path(), path, || self.1.create_new(path))
+    }
+
+    /// See: [`Dir::create_parents`]
+    pub fn create_parents<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("create_parents", self.path(), path, || match path.parent() {
+            Some(parent) => self.1.create_dir_all(parent),
+            None => Ok(()),
+        })
+    }
+
+    /// See: [`Dir::copy`]
+    pub fn copy<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<u64> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("copy", self.path(), from, || self.1.copy(from, to))
+    }
+
+    /// See: [`Dir::create_dir`]
+    pub fn create_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("create_dir", self.path(), path, || self.1.create_dir(path))
+    }
+
+    /// See: [`Dir::create_dir_all`]
+    pub fn create_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("create_dir_all", self.path(), path, || {
+            self.1.create_dir_all(path)
+        })
+    }
+
+    /// See: [`Dir::remove_dir`]
+    pub fn remove_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("remove_dir", self.path(), path, || self.1.remove_dir(path))
+    }
+
+    /// See: [`Dir::remove_dir_all`]
+    pub fn remove_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("remove_dir_all", self.path(), path, || {
+            self.1.remove_dir_all(path)
+        })
+    }
+
+    /// See: [`Dir::remove_file`]
+    pub fn remove_file<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("remove_file", self.path(), path, || self.1.remove_file(path))
+    }
+
+    /// See: [`Dir::rename`]
+    pub fn rename<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<()> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("rename", self.path(), from, || self.1.rename(from, to))
+    }
+
+    /// See: [`Dir::write`]
+    pub fn write<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, contents: C) -> Result<()> {
+        with_paths! { path = self / path }
+        let contents = contents.as_ref();
+        timed("write", self.path(), path, || {
+            self.1.write(path, contents).map(|()| contents.len() as u64)
+        })
+        .map(|_| ())
+    }
+}
+
+impl<P, F> AsRef<Path> for FsDir<P, F>
+where
+    P: AsRef<Path>,
+{
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        self.0.as_ref()
+    }
+}
+
+/// Derefs to the root, as [`Dir`] does
+impl<P, F> Deref for FsDir<P, F>
+where
+    P: AsRef<Path>,
+{
+    type Target = Path;
+
+    #[inline]
+    fn deref(&self) -> &Path {
+        self.0.as_ref()
+    }
+}
+
+impl<P, F, Q> Div<Q> for &FsDir<P, F>
+where
+    P: AsRef<Path>,
+    F: FileSystem,
+    Q: AsRef<Path>,
+{
+    type Output = PathBuf;
+
+    #[inline]
+    fn div(self, rhs: Q) -> Self::Output {
+        self.join(rhs)
+    }
+}
@@ -0,0 +1,396 @@
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+use crate::Dir;
+
+/// A shell-style pattern matched against paths relative to a working
+/// directory, such as `target/**/*.tmp`.
+///
+/// Patterns are made of components separated by `/`, on every platform:
+///
+/// - `*` matches any run of characters within a component, including none
+/// - `?` matches any single character
+/// - `[abc]`, `[a-z]`, and `[!a-z]` match a single character which is, or
+///   isn't, in the set. `[*]` matches a literal `*`.
+/// - `**`, as a whole component, matches any number of components,
+///   including none
+///
+/// Wildcards match names starting with `.` like any others.
+///
+/// ```
+/// use working_dir::Glob;
+///
+/// let glob = Glob::new("target/**/*.tmp")?;
+/// assert!(glob.matches("target/a.tmp"));
+/// assert!(glob.matches("target/debug/build/b.tmp"));
+/// assert!(!glob.matches("src/c.tmp"));
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `**`
+    Recursive,
+    Name(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[...]`, as inclusive ranges
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Parses a pattern.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidInput`]
+    /// if the pattern is absolute, or has an unclosed `[`.
+    pub fn new(pattern: &str) -> Result<Glob> {
+        let invalid = |reason| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid glob {pattern:?}: {reason}"),
+            )
+        };
+        if pattern.starts_with('/') || Path::new(pattern).has_root() {
+            return Err(invalid("patterns are relative to the working directory"));
+        }
+
+        let mut segments = Vec::new();
+        for component in pattern.split('/').filter(|c| !c.is_empty()) {
+            if component == "**" {
+                // Consecutive `**`s match the same paths as one
+                if segments.last() != Some(&Segment::Recursive) {
+                    segments.push(Segment::Recursive);
+                }
+                continue;
+            }
+            let mut tokens = Vec::new();
+            let mut chars = component.chars();
+            while let Some(c) = chars.next() {
+                tokens.push(match c {
+                    '?' => Token::Any,
+                    '*' => Token::Star,
+                    '[' => parse_class(&mut chars).ok_or_else(|| invalid("unclosed `[`"))?,
+                    c => Token::Literal(c),
+                });
+            }
+            segments.push(Segment::Name(tokens));
+        }
+        Ok(Glob {
+            pattern: pattern.to_owned(),
+            segments,
+        })
+    }
+
+    /// Returns true if the relative path `path` matches the pattern. `.`
+    /// components are ignored, and names which aren't valid Unicode are
+    /// converted lossily.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        let mut names = Vec::new();
+        for component in path.as_ref().components() {
+            match component {
+                Component::Normal(name) => names.push(name.to_string_lossy().chars().collect()),
+                Component::CurDir => {}
+                _ => return false,
+            }
+        }
+        match_segments(&self.segments, &names)
+    }
+
+    /// Returns the directory which every match lies beneath: the leading
+    /// components of the pattern which have no wildcards, other than the
+    /// last. This is where a search for matches can start.
+    ///
+    /// ```
+    /// # use working_dir::Glob;
+    /// # use std::path::Path;
+    /// assert_eq!(Glob::new("target/debug/*.d")?.base(), Path::new("target/debug"));
+    /// assert_eq!(Glob::new("**/*.tmp")?.base(), Path::new(""));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn base(&self) -> PathBuf {
+        let mut base = PathBuf::new();
+        let leading = &self.segments[..self.segments.len().saturating_sub(1)];
+        for segment in leading {
+            let Segment::Name(tokens) = segment else {
+                break;
+            };
+            let literal: Option<String> = tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Literal(c) => Some(*c),
+                    _ => None,
+                })
+                .collect();
+            match literal {
+                Some(name) => base.push(name),
+                None => break,
+            }
+        }
+        base
+    }
+
+    /// Returns the pattern as it was written
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+/// Parses the rest of a `[...]` class, after the `[`. Returns `None` if
+/// it's never closed.
+fn parse_class(chars: &mut std::str::Chars) -> Option<Token> {
+    let negated = matches!(chars.clone().next(), Some('!' | '^'));
+    if negated {
+        chars.next();
+    }
+    let mut ranges = Vec::new();
+    // A `]` straight after the opening bracket is part of the set
+    let mut first = true;
+    loop {
+        let c = chars.next()?;
+        if c == ']' && !first {
+            return Some(Token::Class { negated, ranges });
+        }
+        first = false;
+        let mut lookahead = chars.clone();
+        match (lookahead.next(), lookahead.next()) {
+            (Some('-'), Some(end)) if end != ']' => {
+                ranges.push((c, end));
+                *chars = lookahead;
+            }
+            _ => ranges.push((c, c)),
+        }
+    }
+}
+
+fn match_segments(segments: &[Segment], names: &[Vec<char>]) -> bool {
+    match segments.split_first() {
+        None => names.is_empty(),
+        Some((Segment::Recursive, rest)) => {
+            (0..=names.len()).any(|skip| match_segments(rest, &names[skip..]))
+        }
+        Some((Segment::Name(tokens), rest)) => names
+            .split_first()
+            .is_some_and(|(name, names)| match_name(tokens, name) && match_segments(rest, names)),
+    }
+}
+
+fn match_name(tokens: &[Token], name: &[char]) -> bool {
+    match tokens.split_first() {
+        None => name.is_empty(),
+        Some((Token::Star, rest)) => (0..=name.len()).any(|skip| match_name(rest, &name[skip..])),
+        Some((token, rest)) => name.split_first().is_some_and(|(c, name)| {
+            let matched = match token {
+                Token::Literal(literal) => c == literal,
+                Token::Any => true,
+                Token::Class { negated, ranges } => {
+                    ranges.iter().any(|(lo, hi)| (lo..=hi).contains(&c)) != *negated
+                }
+                Token::Star => unreachable!(),
+            };
+            matched && match_name(rest, name)
+        }),
+    }
+}
+
+/// Something which selects paths relative to a working directory, for
+/// [`Dir::move_matching_to`]. This is implemented by [`Glob`], and by
+/// closures taking a `&Path`.
+pub trait PathMatcher {
+    /// Returns true if `path` is selected
+    fn matches(&self, path: &Path) -> bool;
+
+    /// Returns a directory which every selected path lies beneath, so that
+    /// a search for them can start there. The default is the working
+    /// directory itself.
+    fn base(&self) -> PathBuf {
+        PathBuf::new()
+    }
+}
+
+impl PathMatcher for Glob {
+    fn matches(&self, path: &Path) -> bool {
+        Glob::matches(self, path)
+    }
+
+    fn base(&self) -> PathBuf {
+        Glob::base(self)
+    }
+}
+
+impl PathMatcher for &Glob {
+    fn matches(&self, path: &Path) -> bool {
+        Glob::matches(self, path)
+    }
+
+    fn base(&self) -> PathBuf {
+        Glob::base(self)
+    }
+}
+
+impl<F: Fn(&Path) -> bool> PathMatcher for F {
+    fn matches(&self, path: &Path) -> bool {
+        self(path)
+    }
+}
+
+#[derive(Debug, Default)]
+/// The outcome of a call to [`Dir::remove_glob`]
+pub struct RemoveReport {
+    /// The entries which were removed, relative to the working directory
+    pub removed: Vec<PathBuf>,
+    /// The entries which matched, but couldn't be removed, along with the
+    /// reason why
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Removes every file and symbolic link beneath the working directory
+    /// which matches the [`Glob`] `pattern`. Matching directories are left
+    /// alone.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let project = Dir::new("my/project");
+    /// let report = project.remove_glob("target/**/*.tmp")?;
+    /// for (path, err) in &report.failed {
+    ///     eprintln!("couldn't remove {}: {err}", path.display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Failing to remove one entry doesn't stop the others from being
+    /// removed; failures are listed in the report.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the pattern is invalid, or if the
+    /// tree can't be walked.
+    pub fn remove_glob(&self, pattern: &str) -> Result<RemoveReport> {
+        self.remove_glob_with(pattern, false)
+    }
+
+    /// Like [`Dir::remove_glob`], but if `remove_empty_dirs` is true,
+    /// matching directories are removed too, once they're empty. Their
+    /// contents are only removed if they match as well, so `build/**`
+    /// removes everything beneath `build`, but `build/*` only removes the
+    /// files in `build`, and the directories in `build` which are already
+    /// empty. A directory which only contains empty directories is kept,
+    /// since they don't match.
+    pub fn remove_glob_with(&self, pattern: &str, remove_empty_dirs: bool) -> Result<RemoveReport> {
+        let glob = Glob::new(pattern)?;
+        let base = glob.base();
+        let mut report = RemoveReport::default();
+        if self.symlink_metadata_opt(&base)?.is_none() {
+            return Ok(report);
+        }
+
+        let mut dirs = Vec::new();
+        for entry in self.walk(&base) {
+            let entry = entry?;
+            if !glob.matches(entry.path()) {
+                continue;
+            }
+            let path = entry.path().to_owned();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            match self.remove_file(&path) {
+                Ok(()) => report.removed.push(path),
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        if remove_empty_dirs {
+            // Walks yield directories before their contents, so removing
+            // them in reverse empties children before their parents
+            for path in dirs.into_iter().rev() {
+                match self.remove_dir(&path) {
+                    Ok(()) => report.removed.push(path),
+                    Err(err) if err.kind() == ErrorKind::DirectoryNotEmpty => {}
+                    Err(err) => report.failed.push((path, err)),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Moves every file and symbolic link beneath the working directory
+    /// which `matcher` selects to the same relative path beneath
+    /// `new_root`, creating parent directories as necessary, as with
+    /// [`Dir::move_to`]. Returns the paths which were moved, relative to
+    /// both roots.
+    ///
+    /// `matcher` is either a [`Glob`], or a closure:
+    ///
+    /// ```no_run
+    /// # use working_dir::{Dir, Glob};
+    /// # fn main() -> std::io::Result<()> {
+    /// let inbox = Dir::new("scans/inbox");
+    /// inbox.move_matching_to("scans/archive", Glob::new("2023-*/**/*.pdf")?)?;
+    /// inbox.move_matching_to("scans/rejected", |path: &std::path::Path| {
+    ///     path.to_string_lossy().contains("blurry")
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Directories are never moved themselves, so they remain behind,
+    /// possibly empty.
+    ///
+    /// # Errors
+    ///
+    /// This function stops at the first file which can't be moved, and
+    /// returns its error. Files which were moved before then stay moved.
+    pub fn move_matching_to<P2: AsRef<Path>, M: PathMatcher>(
+        &self,
+        new_root: P2,
+        matcher: M,
+    ) -> Result<Vec<PathBuf>> {
+        let base = matcher.base();
+        if self.symlink_metadata_opt(&base)?.is_none() {
+            return Ok(Vec::new());
+        }
+
+        // Gather the matches first, so the walk doesn't see its own moves
+        let mut matched = Vec::new();
+        for entry in self.walk(&base) {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() && matcher.matches(entry.path()) {
+                matched.push(entry.path().to_owned());
+            }
+        }
+        for path in &matched {
+            self.move_to(new_root.as_ref(), path)?;
+        }
+        Ok(matched)
+    }
+}
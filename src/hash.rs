@@ -0,0 +1,254 @@
+use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "hash")]
+use std::io::Write;
+use std::io::{ErrorKind, Read, Result};
+#[cfg(feature = "hash")]
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::crc32::Crc32;
+#[cfg(feature = "hash")]
+use crate::instrument::timed;
+#[cfg(feature = "hash")]
+use crate::{with_paths, Dir};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+/// A hash algorithm used to compute [`Digest`]s
+pub enum HashAlgo {
+    /// CRC-32 (IEEE). Fast and dependency-free, suitable for detecting
+    /// accidental corruption, but not tampering.
+    Crc32,
+    /// SHA-256. Requires the `hash` feature.
+    #[cfg(feature = "hash")]
+    Sha256,
+    /// BLAKE3, with the default 256-bit output. Requires the `hash` feature.
+    #[cfg(feature = "hash")]
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Returns the name of this algorithm, as used in manifests
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Crc32 => "crc32",
+            #[cfg(feature = "hash")]
+            HashAlgo::Sha256 => "sha256",
+            #[cfg(feature = "hash")]
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+}
+
+impl Display for HashAlgo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgo {
+    type Err = std::io::Error;
+
+    fn from_str(name: &str) -> Result<HashAlgo> {
+        match name {
+            "crc32" => Ok(HashAlgo::Crc32),
+            #[cfg(feature = "hash")]
+            "sha256" => Ok(HashAlgo::Sha256),
+            #[cfg(feature = "hash")]
+            "blake3" => Ok(HashAlgo::Blake3),
+            _ => Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown hash algorithm {name:?}"),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// The output of a hash algorithm. Displayed as lowercase hex.
+pub struct Digest(Vec<u8>);
+
+impl Digest {
+    /// Returns the raw bytes of this digest
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns this digest as a lowercase hex string
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Digest {
+    type Err = std::io::Error;
+
+    /// Parses a digest from a hex string
+    fn from_str(hex: &str) -> Result<Digest> {
+        let invalid =
+            || std::io::Error::new(ErrorKind::InvalidData, format!("invalid digest {hex:?}"));
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+            .collect::<Result<_>>()
+            .map(Digest)
+    }
+}
+
+/// An in-progress hash computation
+pub(crate) enum Hasher {
+    Crc32(Crc32),
+    #[cfg(feature = "hash")]
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "hash")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub(crate) fn new(algo: HashAlgo) -> Hasher {
+        match algo {
+            HashAlgo::Crc32 => Hasher::Crc32(Crc32::new()),
+            #[cfg(feature = "hash")]
+            HashAlgo::Sha256 => Hasher::Sha256(sha2::Digest::new()),
+            #[cfg(feature = "hash")]
+            HashAlgo::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Crc32(crc) => crc.update(bytes),
+            #[cfg(feature = "hash")]
+            Hasher::Sha256(sha) => sha2::Digest::update(sha, bytes),
+            #[cfg(feature = "hash")]
+            Hasher::Blake3(blake) => {
+                blake.update(bytes);
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Digest {
+        match self {
+            Hasher::Crc32(crc) => Digest(crc.finish().to_be_bytes().to_vec()),
+            #[cfg(feature = "hash")]
+            Hasher::Sha256(sha) => Digest(sha2::Digest::finalize(sha).to_vec()),
+            #[cfg(feature = "hash")]
+            Hasher::Blake3(blake) => Digest(blake.finalize().as_bytes().to_vec()),
+        }
+    }
+}
+
+/// Hashes everything read from `reader`, in chunks
+pub(crate) fn hash_reader<R: Read>(algo: HashAlgo, mut reader: R) -> Result<(Digest, u64)> {
+    let mut hasher = Hasher::new(algo);
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok((hasher.finish(), total)),
+            Ok(n) => {
+                hasher.update(&buf[..n]);
+                total += n as u64;
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "hash")]
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Computes the digest of the file at `self / path`, streaming it in
+    /// chunks rather than reading it into memory
+    pub fn hash_file<P2: AsRef<Path>>(&self, path: P2, algo: HashAlgo) -> Result<Digest> {
+        let file = self.open_readonly(path)?;
+        hash_reader(algo, file).map(|(digest, _)| digest)
+    }
+
+    /// Copies the file at `from` to `to`, like [`Dir::copy`], hashing the
+    /// data as it's copied. Returns the digest of the contents along with
+    /// the number of bytes copied, so that verifying the copy doesn't need
+    /// to read either file again.
+    ///
+    /// ```no_run
+    /// # use working_dir::{Dir, HashAlgo};
+    /// # fn main() -> std::io::Result<()> {
+    /// let store = Dir::new("/srv/artifacts");
+    /// let (digest, len) = store.copy_with_hash("incoming/build.tar", "build.tar", HashAlgo::Sha256)?;
+    /// println!("stored {len} bytes with sha256 {digest}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The permissions of `from` are copied to `to`, which is overwritten
+    /// if it exists.
+    pub fn copy_with_hash<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        from: P2,
+        to: P3,
+        algo: HashAlgo,
+    ) -> Result<(Digest, u64)> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("copy_with_hash", self.0.as_ref(), from, || {
+            let source = std::fs::File::open(from)?;
+            let permissions = source.metadata()?.permissions();
+            let dest = std::fs::File::create(to)?;
+            let (digest, len) = hash_reader(
+                algo,
+                Tee {
+                    source,
+                    dest: &dest,
+                },
+            )?;
+            dest.set_permissions(permissions)?;
+            Ok((digest, len))
+        })
+    }
+}
+
+#[cfg(feature = "hash")]
+impl crate::counters::Payload for (Digest, u64) {
+    fn bytes(&self) -> u64 {
+        self.1
+    }
+}
+
+/// A reader which writes everything read through it to `dest`
+#[cfg(feature = "hash")]
+struct Tee<R, W> {
+    source: R,
+    dest: W,
+}
+
+#[cfg(feature = "hash")]
+impl<R: Read, W: Write> Read for Tee<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.source.read(buf)?;
+        self.dest.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
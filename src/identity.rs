@@ -0,0 +1,101 @@
+use std::io::Result;
+use std::path::Path;
+
+use crate::Dir;
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns true if both working directories refer to the same directory
+    /// on disk, even if their paths differ, such as through symbolic links,
+    /// bind mounts, or `..` components.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let data = Dir::new("/var/lib/myapp");
+    /// let configured = Dir::new("/srv/link-to-myapp");
+    /// if data.same_dir(&configured)? {
+    ///     println!("the data directory is already in place");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// This function returns an error if either directory doesn't exist
+    pub fn same_dir<Q: AsRef<Path>>(&self, other: &Dir<Q>) -> Result<bool> {
+        same_entry(root_path(self.0.as_ref()), root_path(other.0.as_ref()))
+    }
+
+    /// Returns true if the two paths resolve to the same file, following
+    /// symbolic links. Hard links to the same file are the same file.
+    ///
+    /// On Unix, this compares the device and inode numbers of the files. On
+    /// Windows, it compares their volume serial numbers and file indices.
+    /// Elsewhere, it compares the canonical forms of the paths, which
+    /// doesn't recognize hard links.
+    ///
+    /// # Errors
+    /// This function returns an error if either path doesn't exist
+    pub fn same_file<P2: AsRef<Path>, P3: AsRef<Path>>(&self, a: P2, b: P3) -> Result<bool> {
+        same_entry(&self.join(a), &self.join(b))
+    }
+}
+
+/// The root of a working directory, such that it can be stat'd
+fn root_path(root: &Path) -> &Path {
+    if root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        root
+    }
+}
+
+#[cfg(unix)]
+fn same_entry(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let (a, b) = (a.metadata()?, b.metadata()?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[cfg(windows)]
+fn same_entry(a: &Path, b: &Path) -> Result<bool> {
+    Ok(file_id(a)? == file_id(b)?)
+}
+
+/// Returns the volume serial number and file index of the file at `path`,
+/// which together identify it
+///
+/// See: `GetFileInformationByHandle`
+#[cfg(windows)]
+fn file_id(path: &Path) -> Result<(u32, u64)> {
+    use std::fs::OpenOptions;
+    use std::io::Error;
+    use std::mem::MaybeUninit;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+
+    // No access is needed to query the information, and directories can
+    // only be opened with backup semantics
+    let file = OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    let mut info = MaybeUninit::<BY_HANDLE_FILE_INFORMATION>::uninit();
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), info.as_mut_ptr()) } == 0 {
+        return Err(Error::last_os_error());
+    }
+    let info = unsafe { info.assume_init() };
+    let index = u64::from(info.nFileIndexHigh) << 32 | u64::from(info.nFileIndexLow);
+    Ok((info.dwVolumeSerialNumber, index))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn same_entry(a: &Path, b: &Path) -> Result<bool> {
+    Ok(a.canonicalize()? == b.canonicalize()?)
+}
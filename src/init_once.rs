@@ -0,0 +1,79 @@
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::Dir;
+
+/// The directory, relative to the root, holding the lock and marker files
+/// used by [`Dir::init_once`]
+pub const INIT_DIR: &str = ".init";
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Runs `setup` exactly once for this working directory, even when
+    /// several processes race to initialize it. Returns `true` if this call
+    /// ran `setup`, and `false` if initialization had already completed.
+    ///
+    /// Callers hold an exclusive lock on `.init/<name>.lock` while checking
+    /// for and running the setup, so concurrent callers block until the
+    /// first one finishes. Successful setup is recorded in
+    /// `.init/<name>.done`. Because the lock is held by the operating
+    /// system, it is released even if the process holding it crashes.
+    ///
+    /// If `setup` fails, the error is returned and the next call will try
+    /// again. See [`Dir::init_once_with_cleanup`] to remove the remains of
+    /// a failed attempt before retrying.
+    pub fn init_once<F>(&self, name: &str, setup: F) -> Result<bool>
+    where
+        F: FnOnce(&Dir<P>) -> Result<()>,
+    {
+        self.init_once_with_cleanup(name, |_| Ok(()), setup)
+    }
+
+    /// Like [`Dir::init_once`], but if a previous attempt at setup failed
+    /// or was interrupted, `cleanup` is called to undo any partial setup
+    /// before `setup` is retried.
+    ///
+    /// An attempt is considered to be in progress from the moment `setup`
+    /// starts until it succeeds, tracked by `.init/<name>.started`.
+    pub fn init_once_with_cleanup<F, C>(&self, name: &str, cleanup: C, setup: F) -> Result<bool>
+    where
+        F: FnOnce(&Dir<P>) -> Result<()>,
+        C: FnOnce(&Dir<P>) -> Result<()>,
+    {
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid initialization name {name:?}"),
+            ));
+        }
+        let marker = |suffix: &str| PathBuf::from(INIT_DIR).join(format!("{name}.{suffix}"));
+        let (lock, started, done) = (marker("lock"), marker("started"), marker("done"));
+
+        if self.try_exists(&done)? {
+            return Ok(false);
+        }
+
+        self.create_dir_all(INIT_DIR)?;
+        let lock = self.open(lock, OpenOptions::new().create(true).truncate(false).write(true))?;
+        lock.lock()?;
+
+        // Another process may have finished while we waited for the lock
+        if self.try_exists(&done)? {
+            return Ok(false);
+        }
+        if self.try_exists(&started)? {
+            cleanup(self)?;
+        }
+
+        self.write(&started, std::process::id().to_string())?;
+        setup(self)?;
+        self.write(&done, "")?;
+        self.remove_file(&started)?;
+
+        lock.unlock()?;
+        Ok(true)
+    }
+}
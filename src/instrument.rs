@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::counters::{self, Opaque, Outcome};
+use crate::Dir;
+
+#[derive(Debug, Clone, Copy)]
+/// A filesystem operation which took longer than the configured threshold.
+/// See [`Dir::set_slow_op_threshold`]
+pub struct SlowOp<'a> {
+    /// The name of the operation, such as `"read"` or `"rename"`. This is
+    /// usually the name of the corresponding [`Dir`] method.
+    pub op: &'static str,
+    /// The path the operation acted on, joined onto the working directory.
+    /// For operations on two paths, this is the first (source) path.
+    pub path: &'a Path,
+    /// How long the operation took
+    pub duration: Duration,
+}
+
+type SlowOpHook = Arc<dyn Fn(&SlowOp<'_>) + Send + Sync>;
+
+/// Checked before doing any timing, so that instrumentation costs a single
+/// atomic load when no working directory has a threshold
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static HOOKS: RwLock<Option<HashMap<PathBuf, (Duration, SlowOpHook)>>> = RwLock::new(None);
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Invokes `callback` for every operation performed through this
+    /// working directory, by any `Dir` in the process with the same root,
+    /// which takes longer than `threshold`.
+    ///
+    /// This is meant for catching pathological cases in production, such as
+    /// high network filesystem latency or scans of huge directories, without
+    /// full tracing. Replaces any threshold previously configured for the
+    /// root.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use working_dir::Dir;
+    ///
+    /// let data = Dir::new("/mnt/nfs/data");
+    /// data.set_slow_op_threshold(Duration::from_millis(250), |slow| {
+    ///     eprintln!("slow {} on {:?}: {:?}", slow.op, slow.path, slow.duration)
+    /// });
+    /// ```
+    pub fn set_slow_op_threshold<F>(&self, threshold: Duration, callback: F)
+    where
+        F: Fn(&SlowOp<'_>) + Send + Sync + 'static,
+    {
+        let mut hooks = HOOKS
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        hooks
+            .get_or_insert_with(HashMap::new)
+            .insert(self.0.as_ref().to_owned(), (threshold, Arc::new(callback)));
+        ENABLED.store(true, Ordering::Release);
+    }
+
+    /// Like [`Dir::set_slow_op_threshold`], but logs slow operations to
+    /// stderr
+    pub fn log_slow_ops(&self, threshold: Duration) {
+        self.set_slow_op_threshold(threshold, |slow| {
+            eprintln!(
+                "working_dir: slow {} on {:?} took {:?}",
+                slow.op, slow.path, slow.duration
+            )
+        })
+    }
+
+    /// Stops reporting slow operations performed through this working
+    /// directory
+    pub fn clear_slow_op_threshold(&self) {
+        let mut hooks = HOOKS
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(map) = hooks.as_mut() {
+            map.remove(self.0.as_ref());
+            if map.is_empty() {
+                ENABLED.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+/// Runs a filesystem operation on behalf of the working directory `root`,
+/// counting it, tracing it, and reporting it if it was slow
+#[inline]
+pub(crate) fn timed<T: Outcome>(
+    op: &'static str,
+    root: &Path,
+    path: &Path,
+    f: impl FnOnce() -> T,
+) -> T {
+    let slow = ENABLED.load(Ordering::Acquire);
+    let counting = counters::enabled();
+    let trace = trace::start(op, root, path);
+    if !slow && !counting && trace.is_none() {
+        return f();
+    }
+
+    let start = slow.then(Instant::now);
+    let result = f();
+    if let Some(trace) = trace {
+        trace.finish(&result);
+    }
+    if counting {
+        counters::record(root, op, &result);
+    }
+
+    let Some(start) = start else {
+        return result;
+    };
+    let duration = start.elapsed();
+    let hooks = HOOKS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let hook = match hooks.as_ref().and_then(|hooks| hooks.get(root)) {
+        Some((threshold, hook)) if duration > *threshold => hook.clone(),
+        _ => return result,
+    };
+    // Released first, so that the hook can change thresholds
+    drop(hooks);
+    hook(&SlowOp { op, path, duration });
+    result
+}
+
+/// Like [`timed`], for operations whose results aren't counted in bytes,
+/// such as those producing a [`FileSystem`](crate::FileSystem)'s own types
+#[inline]
+pub(crate) fn timed_opaque<T>(
+    op: &'static str,
+    root: &Path,
+    path: &Path,
+    f: impl FnOnce() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    timed(op, root, path, || f().map(Opaque)).map(|Opaque(value)| value)
+}
+
+/// Emits a `DEBUG` span around every operation, with the target
+/// `working_dir`, and an event within it once the operation finishes.
+///
+/// The span has the fields `op`, `root`, and `path`, which is relative to
+/// `root`. The event has the fields `result` (`"ok"` or `"error"`),
+/// `duration`, and either `bytes` or `error`.
+#[cfg(feature = "tracing")]
+mod trace {
+    use std::path::Path;
+    use std::time::Instant;
+
+    use tracing::span::EnteredSpan;
+
+    use crate::counters::Outcome;
+
+    pub(super) struct Guard {
+        _span: EnteredSpan,
+        start: Instant,
+    }
+
+    #[inline]
+    pub(super) fn start(op: &'static str, root: &Path, path: &Path) -> Option<Guard> {
+        let span = tracing::debug_span!(
+            target: "working_dir",
+            "fs",
+            op,
+            root = %root.display(),
+            path = %path.strip_prefix(root).unwrap_or(path).display(),
+        );
+        if span.is_disabled() {
+            return None;
+        }
+        Some(Guard {
+            _span: span.entered(),
+            start: Instant::now(),
+        })
+    }
+
+    impl Guard {
+        pub(super) fn finish(self, outcome: &impl Outcome) {
+            let duration = self.start.elapsed();
+            match outcome.error() {
+                Some(error) => tracing::debug!(
+                    target: "working_dir",
+                    result = "error",
+                    ?duration,
+                    %error,
+                ),
+                None => tracing::debug!(
+                    target: "working_dir",
+                    result = "ok",
+                    ?duration,
+                    bytes = outcome.bytes(),
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod trace {
+    use std::path::Path;
+
+    use crate::counters::Outcome;
+
+    pub(super) enum Guard {}
+
+    #[inline]
+    pub(super) fn start(_op: &'static str, _root: &Path, _path: &Path) -> Option<Guard> {
+        None
+    }
+
+    impl Guard {
+        pub(super) fn finish(self, _outcome: &impl Outcome) {
+            match self {}
+        }
+    }
+}
@@ -1,9 +1,11 @@
 #![doc = include_str!("../README.md")]
 
 use core::fmt::Debug;
-use std::fs::{File, Metadata, OpenOptions, ReadDir};
-use std::io::Result;
-use std::ops::Div;
+use std::fs::{DirEntry, File, Metadata, OpenOptions, ReadDir};
+use std::io::{ErrorKind, Result};
+use std::ops::{Deref, Div};
+use std::sync::Arc;
+use std::time::SystemTime;
 use std::{
     fs,
     path::{Path, PathBuf},
@@ -11,6 +13,120 @@ use std::{
 
 pub use path_no_alloc::with_paths;
 
+use instrument::timed;
+
+mod allocate;
+#[cfg(target_os = "linux")]
+mod anonymous;
+#[cfg(feature = "asset-cache")]
+mod asset_cache;
+mod backup;
+mod bulk;
+mod chunks;
+#[cfg(feature = "clone-subtree")]
+mod clone_subtree;
+mod compare;
+mod copy;
+mod counters;
+mod crc32;
+mod dedup;
+mod dry_run;
+mod durable;
+mod entry;
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod extract;
+mod filesystem;
+mod fixture;
+mod flush;
+mod framed;
+mod fs_dir;
+mod glob;
+mod hash;
+mod identity;
+mod init_once;
+mod instrument;
+mod link_snapshot;
+mod listing;
+mod lookup;
+mod lru;
+mod maintenance;
+mod manifest;
+mod memory;
+mod merge;
+mod move_set;
+mod open_ext;
+mod overlay;
+mod permissions;
+mod positioned;
+mod progress;
+mod read_only;
+#[cfg(feature = "trash")]
+mod recycle;
+mod reflink;
+mod rename;
+mod rotating;
+mod scratch;
+mod snapshot;
+mod space;
+mod sparse;
+mod sync;
+#[cfg(feature = "tar")]
+mod tar_gz;
+mod temp;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+mod transaction;
+mod tree;
+mod tree_spec;
+mod walk;
+mod walk_stat;
+#[cfg(feature = "zip")]
+mod zip_archive;
+
+#[cfg(target_os = "linux")]
+pub use anonymous::AnonymousFile;
+#[cfg(feature = "asset-cache")]
+pub use asset_cache::AssetCache;
+pub use chunks::ReadChunks;
+#[cfg(feature = "clone-subtree")]
+pub use clone_subtree::CloneMethod;
+pub use compare::dirs_equal;
+pub use copy::{CopyOptions, CopyReport};
+pub use counters::{OpCounter, OpStats};
+pub use dedup::DedupReport;
+pub use dry_run::{DryRun, DryRunOp};
+pub use entry::{Entry, ReadDirMetadata, ReadDirRelative};
+pub use filesystem::{FileSystem, FileSystemMut, StdFs};
+pub use flush::FlushRegistry;
+pub use fs_dir::FsDir;
+pub use glob::{Glob, PathMatcher, RemoveReport};
+pub use hash::{Digest, HashAlgo};
+pub use init_once::INIT_DIR;
+pub use instrument::SlowOp;
+pub use lookup::LookupOptions;
+pub use lru::{EvictionReport, ACCESS_LOG};
+pub use maintenance::{Maintenance, MaintenanceReport, Priority};
+pub use manifest::{Manifest, ManifestEntry, VerifyReport};
+pub use memory::{MemoryEntry, MemoryFile, MemoryFs, MemoryMetadata, MemoryReadDir};
+pub use merge::{ConflictPolicy, MergeReport};
+pub use move_set::MoveSet;
+pub use open_ext::{DirOpenOptions, OpenOptionsDirExt};
+pub use overlay::OverlayDir;
+pub use progress::CancelToken;
+pub use read_only::{ReadOnly, ReadOnlyDir};
+pub use rotating::RotatingWriter;
+pub use scratch::PathScratch;
+pub use snapshot::{FileKind, Snapshot, SnapshotDiff, SnapshotEntry};
+pub use sync::{SyncCompare, SyncOptions, SyncReport};
+pub use temp::{PersistError, TempDir, TempFile};
+pub use transaction::Transaction;
+pub use tree::Tree;
+pub use tree_spec::TreeSpec;
+pub use walk::{SymlinkPolicy, TreeStats, Walk};
+pub use walk_stat::{EntryStat, WalkStat};
+#[cfg(feature = "zip")]
+pub use zip_archive::ZipOptions;
+
 fn create_parents<P: AsRef<Path>>(path: P) -> Result<()> {
     let path = path.as_ref();
     if let Some(parent) = path.parent() {
@@ -20,6 +136,26 @@ fn create_parents<P: AsRef<Path>>(path: P) -> Result<()> {
     }
 }
 
+/// Reads at most `limit` bytes from the start of the file at `path`
+fn read_limited(path: &Path, limit: u64) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let file = File::open(path)?;
+    let hint = (filesystem::len_hint(&file) as u64).min(limit);
+    let mut contents = Vec::with_capacity(hint as usize);
+    file.take(limit).read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Maps a `NotFound` error to `Ok(None)`, passing through any other result
+fn not_found_to_none<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
 impl <P> Dir<P> where P: AsRef<Path>
 {
     #[inline]
@@ -38,28 +174,11 @@ impl <P> Dir<P> where P: AsRef<Path>
         }
     }
 
-    /// Opens a file with the given [`OpenOptions`]
-    pub fn open<P2: AsRef<Path>>(&self, path: P2, opts: &OpenOptions) -> Result<File> {
-        with_paths! { path = self / path => opts.open(path) }
-    }
-
     /// Opens a file in read-only mode
     ///
     /// See: [`std::fs::File::open`]
     pub fn open_readonly<P2: AsRef<Path>>(&self, path: P2) -> Result<File> {
-        with_paths! { path = self / path => File::open(path) }
-    }
-
-    /// Creates any parent directories for a given path. Does nothing
-    /// if the path has no parents.
-    ///
-    /// # Errors
-    /// This function returns an error if the creation of the parent
-    /// directories fails
-    pub fn create_parents<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! {
-            path = self / path => create_parents(path)
-        }
+        self.as_fs_dir().open_readonly(path)
     }
 
     /// Returns true if the path points at an existing entity.
@@ -76,7 +195,7 @@ impl <P> Dir<P> where P: AsRef<Path>
     ///
     /// See: [`std::path::Path::exists`]
     pub fn exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
-        with_paths! { path = self / path => path.exists() }
+        self.as_fs_dir().exists(path)
     }
 
     #[inline]
@@ -102,7 +221,7 @@ impl <P> Dir<P> where P: AsRef<Path>
     ///
     /// See: [`std::path::Path::try_exists`]
     pub fn try_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
-        with_paths! { path = self / path => path.try_exists() }
+        self.as_fs_dir().try_exists(path)
     }
 
     #[inline]
@@ -111,37 +230,107 @@ impl <P> Dir<P> where P: AsRef<Path>
         self.try_exists(path)
     }
 
-    /// Moves a path from this working directory, to another working directory.
+    /// Given a path, query the file system to get information about
+    /// a file, directory, etc.
     ///
-    /// Suppose we have some path `path/to/thing`, corresponding to `<self>/path/to/thing`
-    /// in the current working directory.
+    /// This function will traverse symbolic links to query
+    /// information about the destination file.
     ///
-    /// This function will move it to `<B>/path/to/thing`, in working directory B, creating
-    /// any parent dirs as necessary
+    /// See: [`std::fs::metadata`]
+    pub fn metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<Metadata> {
+        self.as_fs_dir().metadata(path)
+    }
+
+    /// Like [`Dir::metadata`], but returns `Ok(None)` if the path does not exist.
     ///
-    /// # Errors
+    /// Any other error is still returned.
+    pub fn metadata_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<Metadata>> {
+        not_found_to_none(self.metadata(path))
+    }
+
+    /// Read the entire contents of a file into a bytes vector.
     ///
-    /// This function will return an error in the following cases:
+    /// This is a convenience function for using `File::open` and
+    /// `read_to_end` with fewer imports and without an intermediate variable.
+    /// The buffer is sized up front from the file's metadata. The read is
+    /// recorded if this working directory tracks accesses, with
+    /// [`Dir::track_access`].
     ///
-    /// - `<working dir>/<path>` does not exist (in which case, there is nothing to rename)
-    /// - The user lacks permission to view the contents of the path.
-    /// - The destination is on a separate filesystem
-    pub fn move_to<P2: AsRef<Path>, P3: AsRef<Path>>(&self, new_root: P2, path: P3) -> Result<()> {
+    /// See: [`std::fs::read`]
+    pub fn read<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<u8>> {
         let path = path.as_ref();
-        with_paths! {
-            old_path = self / path,
-            new_path = new_root / path
-        }
-        create_parents(new_path)?;
-        fs::rename(old_path, new_path)
+        let contents = self.as_fs_dir().read(path)?;
+        self.note_read(path);
+        Ok(contents)
     }
 
-    /// Returns the canonical, absolute form of a path relative to the current working directory,
-    /// with all intermediate components normalized and symbolic links resolved.
+    /// Like [`Dir::read`], but returns `Ok(None)` if the file does not exist.
     ///
-    /// See: [`std::fs::canonicalize`]
-    pub fn canonicalize<P2: AsRef<Path>>(&self, path: P2) -> Result<PathBuf> {
-        with_paths! { path = self / path => fs::canonicalize(path) }
+    /// Any other error is still returned.
+    pub fn read_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<Vec<u8>>> {
+        not_found_to_none(self.read(path))
+    }
+
+    /// Returns an iterator over the entries within a directory.
+    ///
+    /// The iterator will yield instances of `io::Result<DirEntry>`.
+    /// New errors may be encountered after an iterator is initially
+    /// constructed. Entries for the current and parent directories
+    /// (typically `.` and `..`) are skipped.
+    ///
+    /// See: [`std::fs::read_dir`]
+    pub fn read_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<ReadDir> {
+        self.as_fs_dir().read_dir(path)
+    }
+
+    /// Read the entire contents of a file into a string.
+    ///
+    /// This is a convenience function for using File::open
+    /// and read_to_string with fewer imports and without an
+    /// intermediate variable. The buffer is sized up front from the file's
+    /// metadata. The read is recorded if this working directory tracks
+    /// accesses, with [`Dir::track_access`].
+    ///
+    /// See: [`std::fs::read_to_string`]
+    pub fn read_to_string<P2: AsRef<Path>>(&self, path: P2) -> Result<String> {
+        let path = path.as_ref();
+        let contents = self.as_fs_dir().read_to_string(path)?;
+        self.note_read(path);
+        Ok(contents)
+    }
+
+    /// Like [`Dir::read_to_string`], but returns `Ok(None)` if the file does
+    /// not exist.
+    ///
+    /// Any other error is still returned.
+    pub fn read_to_string_opt<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<String>> {
+        not_found_to_none(self.read_to_string(path))
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    ///
+    /// See: [`std::fs::symlink_metadata`]
+    pub fn symlink_metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<Metadata> {
+        self.as_fs_dir().symlink_metadata(path)
+    }
+
+    /// Creates a new file in read-write mode, failing if it already exists.
+    /// The check and the creation happen atomically, so no other process
+    /// can create the file in between.
+    ///
+    /// See: [`std::fs::File::create_new`]
+    pub fn create_new<P2: AsRef<Path>>(&self, path: P2) -> Result<File> {
+        self.as_fs_dir().create_new(path)
+    }
+
+    /// Creates any parent directories for a given path. Does nothing
+    /// if the path has no parents.
+    ///
+    /// # Errors
+    /// This function returns an error if the creation of the parent
+    /// directories fails
+    pub fn create_parents<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.as_fs_dir().create_parents(path)
     }
 
     /// Copies the contents of one file to another. This function
@@ -161,25 +350,153 @@ impl <P> Dir<P> where P: AsRef<Path>
     ///
     /// See: [`std::fs::create_dir`]
     pub fn copy<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<u64> {
-        with_paths! {
-            from = self / from,
-            to = self / to
-        }
-        fs::copy(from, to)
+        self.as_fs_dir().copy(from, to)
     }
 
     /// Creates a new, empty directory at the provided path
     ///
     /// See: [`std::fs::create_dir`]
     pub fn create_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! { path = self / path => fs::create_dir(path) }
+        self.as_fs_dir().create_dir(path)
     }
 
     /// Recursively create a directory and all of its parent components if they are missing.
     ///
     /// See: [`std::fs::create_dir_all`]
     pub fn create_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! { path = self / path => fs::create_dir_all(path) }
+        self.as_fs_dir().create_dir_all(path)
+    }
+
+    /// Removes an empty directory.
+    ///
+    /// See: [`std::fs::remove_dir`]
+    pub fn remove_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.as_fs_dir().remove_dir(path)
+    }
+
+    /// Removes a directory at this path, after removing all
+    /// its contents. Use carefully!
+    ///
+    /// This function does **not** follow symbolic links and it will
+    /// simply remove the symbolic link itself.
+    ///
+    /// See: [`std::fs::remove_dir_all`]
+    pub fn remove_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.as_fs_dir().remove_dir_all(path)
+    }
+
+    /// Removes a file from the filesystem.
+    ///
+    /// Note that there is no guarantee that the file is immediately
+    /// deleted (e.g., depending on platform, other open file
+    /// descriptors may prevent immediate removal).
+    ///
+    /// See: [`std::fs::remove_file`]
+    pub fn remove_file<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.as_fs_dir().remove_file(path)
+    }
+
+    /// Rename a file or directory to a new name, replacing
+    /// the original file if to already exists.
+    ///
+    /// This will not work if the new name is on a different mount point.
+    ///
+    /// See: [`std::fs::rename`]
+    pub fn rename<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<()> {
+        self.as_fs_dir().rename(from, to)
+    }
+
+    /// Write a slice as the entire contents of a file.
+    ///
+    /// This function will create a file if it does not exist, and will
+    /// entirely replace its contents if it does.
+    ///
+    /// Depending on the platform, this function may fail if the full
+    /// directory path does not exist.
+    ///
+    /// This is a convenience function for using File::create and
+    /// write_all with fewer imports.
+    ///
+    /// See: [`std::fs::write`]
+    pub fn write<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, contents: C) -> Result<()> {
+        self.as_fs_dir().write(path, contents)
+    }
+}
+
+impl <P> Dir<P> where P: AsRef<Path>
+{
+    /// Opens a file with the given [`OpenOptions`]
+    pub fn open<P2: AsRef<Path>>(&self, path: P2, opts: &OpenOptions) -> Result<File> {
+        with_paths! { path = self / path => timed("open", self.0.as_ref(), path, || opts.open(path)) }
+    }
+
+    /// Moves a path from this working directory, to another working directory.
+    ///
+    /// Suppose we have some path `path/to/thing`, corresponding to `<self>/path/to/thing`
+    /// in the current working directory.
+    ///
+    /// This function will move it to `<B>/path/to/thing`, in working directory B, creating
+    /// any parent dirs as necessary
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following cases:
+    ///
+    /// - `<working dir>/<path>` does not exist (in which case, there is nothing to rename)
+    /// - The user lacks permission to view the contents of the path.
+    /// - The destination is on a separate filesystem
+    pub fn move_to<P2: AsRef<Path>, P3: AsRef<Path>>(&self, new_root: P2, path: P3) -> Result<()> {
+        let path = path.as_ref();
+        with_paths! {
+            old_path = self / path,
+            new_path = new_root / path
+        }
+        timed("move_to", self.0.as_ref(), old_path, || {
+            create_parents(new_path)?;
+            fs::rename(old_path, new_path)
+        })
+    }
+
+    /// Moves `path` from this working directory to `new_path` in another working directory,
+    /// creating any parent dirs as necessary. Unlike [`Dir::move_to`], the path can change
+    /// during the move, so `<self>/drafts/a.txt` can become `<B>/published/2024/a.txt`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails in the same cases as [`Dir::move_to`]
+    pub fn move_to_as<P2: AsRef<Path>, P3: AsRef<Path>, P4: AsRef<Path>>(&self, new_root: P2, path: P3, new_path: P4) -> Result<()> {
+        with_paths! {
+            old_path = self / path,
+            new_path = new_root / new_path
+        }
+        timed("move_to_as", self.0.as_ref(), old_path, || {
+            create_parents(new_path)?;
+            fs::rename(old_path, new_path)
+        })
+    }
+
+    /// Copies the file at `from` in this working directory to `to` in another working directory,
+    /// creating any parent dirs as necessary. Like [`Dir::copy`], this overwrites `to` if it
+    /// exists, and returns the number of bytes copied.
+    ///
+    /// See: [`std::fs::copy`]
+    pub fn copy_file_to<P2: AsRef<Path>, P3: AsRef<Path>, P4: AsRef<Path>>(&self, other_root: P2, from: P3, to: P4) -> Result<u64> {
+        with_paths! {
+            from = self / from,
+            to = other_root / to
+        }
+        timed("copy_file_to", self.0.as_ref(), from, || {
+            create_parents(to)?;
+            fs::copy(from, to)
+        })
+    }
+
+    /// Returns the canonical, absolute form of a path relative to the current working directory,
+    /// with all intermediate components normalized and symbolic links resolved.
+    ///
+    /// See: [`std::fs::canonicalize`]
+    pub fn canonicalize<P2: AsRef<Path>>(&self, path: P2) -> Result<PathBuf> {
+        with_paths! { path = self / path => timed("canonicalize", self.0.as_ref(), path, || fs::canonicalize(path)) }
     }
 
     /// Creates a new hard link on the filesystem.
@@ -199,133 +516,284 @@ impl <P> Dir<P> where P: AsRef<Path>
             original = self / original,
             link = self / link
         }
-        fs::hard_link(original, link)
+        timed("hard_link", self.0.as_ref(), original, || fs::hard_link(original, link))
     }
 
-    /// Given a path, query the file system to get information about
-    /// a file, directory, etc.
+    /// Creates a hard link at `path` in another working directory, pointing to `path` in this
+    /// one, creating any parent dirs as necessary. Both working directories must be on the same
+    /// filesystem.
     ///
-    /// This function will traverse symbolic links to query
-    /// information about the destination file.
+    /// See: [`Dir::hard_link`]
+    pub fn hard_link_to<P2: AsRef<Path>, P3: AsRef<Path>>(&self, other_root: P2, path: P3) -> Result<()> {
+        let path = path.as_ref();
+        with_paths! {
+            original = self / path,
+            link = other_root / path
+        }
+        timed("hard_link_to", self.0.as_ref(), original, || {
+            create_parents(link)?;
+            fs::hard_link(original, link)
+        })
+    }
+
+    /// Like [`Dir::read`], but sizes the buffer from `hint` rather than
+    /// querying the file's metadata. When the size is already known, such as
+    /// from an [`Entry`] during a walk, this saves a system call per file,
+    /// and a buffer which starts at the right size is never reallocated.
     ///
-    /// See: [`std::fs::metadata`]
-    pub fn metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<Metadata> {
-        with_paths! { path = self / path => fs::metadata(path) }
+    /// The whole file is read even if it's longer than `hint`.
+    pub fn read_with_capacity<P2: AsRef<Path>>(&self, path: P2, hint: usize) -> Result<Vec<u8>> {
+        use std::io::Read;
+
+        let path = path.as_ref();
+        let contents = with_paths! {
+            abs = self / path => timed("read_with_capacity", self.0.as_ref(), abs, || {
+                let mut contents = Vec::with_capacity(hint);
+                File::open(abs)?.read_to_end(&mut contents)?;
+                Ok(contents)
+            })
+        }?;
+        self.note_read(path);
+        Ok(contents)
     }
 
-    /// Read the entire contents of a file into a bytes vector.
+    /// Like [`Dir::read`], but reads at most `limit` bytes, so that a file
+    /// which is unexpectedly large (or controlled by an untrusted user)
+    /// can't exhaust memory.
     ///
-    /// This is a convenience function for using `File::open` and
-    /// `read_to_end` with fewer imports and without an intermediate variable.
+    /// To read the first `limit` bytes of a larger file instead of failing,
+    /// use [`Dir::read_max_truncating`].
     ///
-    /// See: [`std::fs::read`]
-    pub fn read<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<u8>> {
-        with_paths! { path = self / path => fs::read(path) }
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidData`]
+    /// if the file is longer than `limit`. Only `limit + 1` bytes are read
+    /// to find out.
+    pub fn read_max<P2: AsRef<Path>>(&self, path: P2, limit: u64) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let contents = with_paths! {
+            abs = self / path => timed("read_max", self.0.as_ref(), abs, || {
+                let contents = read_limited(abs, limit.saturating_add(1))?;
+                if contents.len() as u64 > limit {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{abs:?} is larger than {limit} bytes"),
+                    ));
+                }
+                Ok(contents)
+            })
+        }?;
+        self.note_read(path);
+        Ok(contents)
+    }
+
+    /// Like [`Dir::read_max`], but returns the first `limit` bytes of a
+    /// file which is longer than `limit`, rather than failing.
+    pub fn read_max_truncating<P2: AsRef<Path>>(&self, path: P2, limit: u64) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let contents = with_paths! {
+            abs = self / path => timed("read_max_truncating", self.0.as_ref(), abs, || read_limited(abs, limit))
+        }?;
+        self.note_read(path);
+        Ok(contents)
     }
 
-    /// Returns an iterator over the entries within a directory.
+    /// Returns the entries within a directory, sorted by file name.
     ///
-    /// The iterator will yield instances of `io::Result<DirEntry>`.
-    /// New errors may be encountered after an iterator is initially
-    /// constructed. Entries for the current and parent directories
-    /// (typically `.` and `..`) are skipped.
+    /// Unlike [`Dir::read_dir`], which yields entries in whatever order the
+    /// operating system provides, the result of this function is
+    /// deterministic. Any error encountered while reading the directory
+    /// is returned.
+    pub fn read_dir_sorted<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<DirEntry>> {
+        let mut entries = self.read_dir(path)?.collect::<Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        Ok(entries)
+    }
+
+    /// Returns the paths of the entries within a directory, sorted by file name.
     ///
-    /// See: [`std::fs::read_dir`]
-    pub fn read_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<ReadDir> {
-        with_paths! { path = self / path => fs::read_dir(path) }
+    /// See: [`Dir::read_dir_sorted`]
+    pub fn read_dir_paths<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<PathBuf>> {
+        let entries = self.read_dir_sorted(path)?;
+        Ok(entries.iter().map(DirEntry::path).collect())
     }
 
     /// Reads a symbolic link, returning the file that the link points to.
     ///
     /// See: [`std::fs::read_link`]
     pub fn read_link<P2: AsRef<Path>>(&self, path: P2) -> Result<PathBuf> {
-        with_paths! { path = self / path => fs::read_link(path) }
+        with_paths! { path = self / path => timed("read_link", self.0.as_ref(), path, || fs::read_link(path)) }
     }
 
-    /// Read the entire contents of a file into a string.
+    /// Returns true if the path exists and is a regular file, following
+    /// symbolic links. Returns false if the metadata can't be read.
     ///
-    /// This is a convenience function for using File::open
-    /// and read_to_string with fewer imports and without an
-    /// intermediate variable.
+    /// See: [`Path::is_file`]
+    pub fn is_file<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.metadata(path).is_ok_and(|meta| meta.is_file())
+    }
+
+    /// Returns true if the path exists and is a directory, following
+    /// symbolic links. Returns false if the metadata can't be read.
     ///
-    /// See: [`std::fs::read_to_string`]
-    pub fn read_to_string<P2: AsRef<Path>>(&self, path: P2) -> Result<String> {
-        with_paths! { path = self / path => fs::read_to_string(path) }
+    /// See: [`Path::is_dir`]
+    pub fn is_dir<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.metadata(path).is_ok_and(|meta| meta.is_dir())
     }
 
-    /// Removes an empty directory.
+    /// Returns true if the path exists and is a symbolic link, without
+    /// following it. Returns false if the metadata can't be read.
     ///
-    /// See: [`std::fs::remove_dir`]
-    pub fn remove_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! { path = self / path => fs::remove_dir(path) }
+    /// See: [`Path::is_symlink`]
+    pub fn is_symlink<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.symlink_metadata(path).is_ok_and(|meta| meta.is_symlink())
     }
 
-    /// Removes a directory at this path, after removing all
-    /// its contents. Use carefully!
+    /// Returns true if the path names a hidden file or directory. On Unix,
+    /// these are dotfiles, whose names start with `.`. On Windows, they're
+    /// entries with the hidden attribute set, and this returns false if the
+    /// metadata can't be read.
+    pub fn is_hidden<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        #[cfg(windows)]
+        {
+            self.symlink_metadata(path).is_ok_and(|meta| walk::has_hidden_attribute(&meta))
+        }
+        #[cfg(not(windows))]
+        {
+            path.as_ref().file_name().is_some_and(walk::is_dotfile)
+        }
+    }
+
+    /// Returns the size of a file in bytes, following symbolic links.
     ///
-    /// This function does **not** follow symbolic links and it will
-    /// simply remove the symbolic link itself.
+    /// See: [`std::fs::Metadata::len`]
+    pub fn file_size<P2: AsRef<Path>>(&self, path: P2) -> Result<u64> {
+        Ok(self.metadata(path)?.len())
+    }
+
+    /// Returns the last modification time of a file, following symbolic
+    /// links. Fails if the platform doesn't record modification times.
     ///
-    /// See: [`std::fs::remove_dir_all`]
-    pub fn remove_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! { path = self / path => fs::remove_dir_all(path) }
+    /// See: [`std::fs::Metadata::modified`]
+    pub fn modified<P2: AsRef<Path>>(&self, path: P2) -> Result<SystemTime> {
+        self.metadata(path)?.modified()
     }
 
-    /// Removes a file from the filesystem.
+    /// Returns true if the path exists and is a regular file, following
+    /// symbolic links. The same as [`Dir::is_file`].
     ///
-    /// Note that there is no guarantee that the file is immediately
-    /// deleted (e.g., depending on platform, other open file
-    /// descriptors may prevent immediate removal).
+    /// See [`Dir::try_file_exists`] to find out why the answer is false.
+    pub fn file_exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.is_file(path)
+    }
+
+    /// Returns true if the path exists and is a directory, following
+    /// symbolic links. The same as [`Dir::is_dir`].
     ///
-    /// See: [`std::fs::remove_file`]
-    pub fn remove_file<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
-        with_paths! { path = self / path => fs::remove_file(path) }
+    /// See [`Dir::try_dir_exists`] to find out why the answer is false.
+    pub fn dir_exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.is_dir(path)
     }
 
-    /// Rename a file or directory to a new name, replacing
-    /// the original file if to already exists.
+    /// Returns `Ok(true)` if the path is a regular file, and `Ok(false)` if
+    /// nothing exists at the path.
     ///
-    /// This will not work if the new name is on a different mount point.
+    /// # Errors
     ///
-    /// See: [`std::fs::rename`]
-    pub fn rename<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<()> {
-        with_paths! {
-            from = self / from,
-            to = self / to
+    /// Returns an error of kind [`ErrorKind::IsADirectory`] if the path is
+    /// a directory, or [`ErrorKind::InvalidInput`] if it's some other kind
+    /// of entry, rather than quietly returning `Ok(false)`. Also returns an
+    /// error if the metadata can't be read, as with [`Dir::try_exists`].
+    pub fn try_file_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        let path = path.as_ref();
+        match not_found_to_none(self.metadata(path))? {
+            None => Ok(false),
+            Some(meta) if meta.is_file() => Ok(true),
+            Some(meta) if meta.is_dir() => Err(std::io::Error::new(
+                ErrorKind::IsADirectory,
+                format!("expected {:?} to be a file, but it's a directory", self.join(path)),
+            )),
+            Some(_) => Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("expected {:?} to be a file, but it's a special file", self.join(path)),
+            )),
         }
-        fs::rename(from, to)
     }
 
-    /// Query the metadata about a file without following symlinks.
+    /// Returns `Ok(true)` if the path is a directory, and `Ok(false)` if
+    /// nothing exists at the path.
     ///
-    /// See: [`std::fs::symlink_metadata`]
-    pub fn symlink_metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<Metadata> {
-        with_paths! { path = self / path => fs::symlink_metadata(path) }
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::NotADirectory`] if something
+    /// other than a directory exists at the path, rather than quietly
+    /// returning `Ok(false)`. Also returns an error if the metadata can't be
+    /// read, as with [`Dir::try_exists`].
+    pub fn try_dir_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        let path = path.as_ref();
+        match not_found_to_none(self.metadata(path))? {
+            None => Ok(false),
+            Some(meta) if meta.is_dir() => Ok(true),
+            Some(_) => Err(std::io::Error::new(
+                ErrorKind::NotADirectory,
+                format!("expected {:?} to be a directory, but it isn't", self.join(path)),
+            )),
+        }
     }
 
-    /// Write a slice as the entire contents of a file.
+    /// Truncates or extends a file to the given length, without creating it.
+    /// If the file is extended, the new bytes read as zeros.
     ///
-    /// This function will create a file if it does not exist, and will
-    /// entirely replace its contents if it does.
+    /// See: [`std::fs::File::set_len`]
+    pub fn set_len<P2: AsRef<Path>>(&self, path: P2, len: u64) -> Result<()> {
+        with_paths! {
+            path = self / path => timed("set_len", self.0.as_ref(), path, || {
+                OpenOptions::new().write(true).open(path)?.set_len(len)
+            })
+        }
+    }
+
+    /// Truncates a file to zero length, without creating it.
     ///
-    /// Depending on the platform, this function may fail if the full
-    /// directory path does not exist.
+    /// See: [`Dir::set_len`]
+    pub fn truncate<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.set_len(path, 0)
+    }
+
+    /// Writes a slice as the entire contents of a file, unless the file
+    /// already has exactly those contents. Returns `true` if the file was
+    /// written, and `false` if it was left untouched.
     ///
-    /// This is a convenience function for using File::create and
-    /// write_all with fewer imports.
+    /// Skipping identical writes leaves the file's modification time alone,
+    /// so build systems watching the working directory won't see a change.
     ///
-    /// See: [`std::fs::write`]
-    pub fn write<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, contents: C) -> Result<()> {
-        with_paths! {
-            path = self / path => fs::write(path, contents)
+    /// See: [`Dir::write`]
+    pub fn write_if_changed<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, contents: C) -> Result<bool> {
+        let path = path.as_ref();
+        let contents = contents.as_ref();
+
+        // Only read the existing file if its length matches
+        let unchanged = match self.metadata_opt(path)? {
+            Some(meta) if meta.is_file() && meta.len() == contents.len() as u64 => {
+                self.read(path)? == contents
+            }
+            _ => false,
+        };
+        if unchanged {
+            return Ok(false);
         }
+        self.write(path, contents)?;
+        Ok(true)
     }
 }
 
 #[repr(transparent)]
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Clone, PartialEq, PartialOrd, Eq, Ord)]
 /// Acts as a Working Directory. Provides a variety of functions
 /// to manipulate and query directories and files in the context of that directory.
+///
+/// Operations are performed on the operating system's filesystem. See
+/// [`FsDir`] for a working directory on some other [`FileSystem`].
 pub struct Dir<P>(pub P)
 where
     P: AsRef<Path>;
@@ -373,6 +841,91 @@ where
     pub fn new(path: P) -> Dir<P> {
         Dir(path)
     }
+
+    /// Returns a Dir borrowing this one's root, so functions can take a `Dir<&Path>` by value as
+    /// a lightweight handle, whatever the caller's root is stored as:
+    ///
+    /// ```
+    /// # use working_dir::Dir;
+    /// # use std::path::{Path, PathBuf};
+    /// fn config_path(dir: Dir<&Path>) -> PathBuf {
+    ///     dir.join("config.toml")
+    /// }
+    ///
+    /// let owned = Dir::new(PathBuf::from("app"));
+    /// let borrowed = Dir::new("app");
+    /// assert_eq!(config_path(owned.as_dir()), config_path(borrowed.as_dir()));
+    /// ```
+    #[inline]
+    pub fn as_dir(&self) -> Dir<&Path> {
+        Dir(self.0.as_ref())
+    }
+
+    /// Returns a view of this working directory as an [`FsDir`] on [`StdFs`], for passing to code
+    /// written against any [`FileSystem`]
+    #[inline]
+    pub fn as_fs_dir(&self) -> FsDir<&Path> {
+        FsDir(self.0.as_ref(), StdFs)
+    }
+
+    /// Creates a working directory from the given path, which performs its operations on the
+    /// filesystem `fs` rather than the operating system's. See [`FsDir`].
+    #[inline]
+    pub fn with_fs<F: FileSystem>(path: P, fs: F) -> FsDir<P, F> {
+        FsDir(path, fs)
+    }
+
+    /// Returns the root of this Dir as a [`Path`]
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.0.as_ref()
+    }
+
+    /// Consumes this Dir, returning its root
+    #[inline]
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+
+    /// Converts this Dir into one whose root is shared. See [`Dir::shared`].
+    pub fn into_shared(self) -> Dir<Arc<Path>> {
+        Dir(Arc::from(self.0.as_ref()))
+    }
+}
+
+impl Dir<PathBuf> {
+    /// Creates a Dir from the canonical, absolute form of the given path,
+    /// resolving it once against the current working directory of the
+    /// process. Any later change to the process's working directory (for
+    /// example, a daemon calling `chdir` after startup) doesn't affect
+    /// where the Dir points.
+    ///
+    /// On Windows, the root is an extended-length path (starting with
+    /// `\\?\`), as returned by [`std::fs::canonicalize`].
+    ///
+    /// # Errors
+    /// This function returns an error if the path doesn't exist, or can't
+    /// be resolved
+    pub fn canonical<P: AsRef<Path>>(path: P) -> Result<Dir<PathBuf>> {
+        fs::canonicalize(path).map(Dir::new)
+    }
+}
+
+impl Dir<Arc<Path>> {
+    /// Creates a Dir whose root is shared, so that clones of it (for example, one for each
+    /// worker thread or task) refer to the same allocation rather than copying the path.
+    ///
+    /// ```
+    /// # use working_dir::Dir;
+    /// let data = Dir::shared("/var/lib/myapp");
+    /// let workers: Vec<_> = (0..4).map(|_| data.clone()).collect();
+    /// assert!(std::sync::Arc::ptr_eq(&data.0, &workers[0].0));
+    /// ```
+    ///
+    /// `Dir<Arc<str>>` isn't supported, since `Arc<str>` doesn't implement `AsRef<Path>`.
+    pub fn shared<P: AsRef<Path>>(path: P) -> Dir<Arc<Path>> {
+        Dir::new(Arc::from(path.as_ref()))
+    }
 }
 
 impl<P> AsRef<Path> for Dir<P>
@@ -385,6 +938,20 @@ where
     }
 }
 
+/// Derefs to the root, so a Dir can be passed wherever a `&Path` is wanted. Methods of the Dir
+/// itself, such as [`Dir::join`] and [`Dir::exists`], take precedence over those of the [`Path`].
+impl<P> Deref for Dir<P>
+where
+    P: AsRef<Path>,
+{
+    type Target = Path;
+
+    #[inline]
+    fn deref(&self) -> &Path {
+        self.0.as_ref()
+    }
+}
+
 impl <P, Q> Div<Q> for &Dir<P> where P: AsRef<Path>, Q: AsRef<Path> {
     type Output = PathBuf;
 
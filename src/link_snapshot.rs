@@ -0,0 +1,79 @@
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::sync::symlink;
+use crate::{Dir, FileKind};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a point-in-time snapshot of the tree at `src` at `dest`, like
+    /// `cp -al`: the directory structure is recreated, and every file is
+    /// hard-linked rather than copied. Symbolic links are copied as links,
+    /// and directory permissions are preserved. Returns the number of files
+    /// linked.
+    ///
+    /// This is nearly instant and uses no space for file contents, which
+    /// suits backup rotation schemes:
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let backups = Dir::new("/srv/backups");
+    /// backups.snapshot_hardlinks("current", "daily/2024-06-01")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Since the files are shared, modifying a file in place changes it in
+    /// both trees. Tools which replace files instead (by writing a new file
+    /// and renaming it over the old one, as rsync does) leave the snapshot
+    /// untouched.
+    ///
+    /// # Errors
+    ///
+    /// `dest` must not already exist, although its parents are created as
+    /// necessary. Both trees must be on the same filesystem.
+    pub fn snapshot_hardlinks<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        src: P2,
+        dest: P3,
+    ) -> Result<u64> {
+        let (src, dest) = (src.as_ref(), dest.as_ref());
+        let base = self.relative_base(src);
+        self.create_parents(dest)?;
+        self.create_dir(dest)?;
+
+        let mut dirs: Vec<(PathBuf, fs::Permissions)> =
+            vec![(dest.to_owned(), self.metadata(src)?.permissions())];
+        let mut linked = 0;
+        for entry in self.walk(src) {
+            let entry = entry?;
+            let to = dest.join(entry.path().strip_prefix(&base).unwrap_or(entry.path()));
+            match FileKind::from(entry.file_type()?) {
+                FileKind::Dir => {
+                    self.create_dir(&to)?;
+                    dirs.push((to, entry.metadata()?.permissions()));
+                }
+                FileKind::File => {
+                    self.hard_link(entry.path(), &to)?;
+                    linked += 1;
+                }
+                FileKind::Symlink => {
+                    let from = entry.abs_path();
+                    symlink(&fs::read_link(&from)?, &self.join(&to), &from)?;
+                }
+                // Sockets, devices, and the like can't be linked
+                FileKind::Other => {}
+            }
+        }
+
+        // Read-only directories can only be made so after they're populated
+        for (dir, permissions) in dirs.into_iter().rev() {
+            fs::set_permissions(self.join(dir), permissions)?;
+        }
+        Ok(linked)
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write as _;
+use std::io::{Result, Write};
+use std::path::{Component, Path};
+use std::time::UNIX_EPOCH;
+
+use crate::{Dir, EntryStat, FileKind};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a JSON listing of the tree at `path`. See
+    /// [`Dir::list_to_writer`] for the format.
+    pub fn list_json<P2: AsRef<Path>>(&self, path: P2) -> Result<String> {
+        let mut out = Vec::new();
+        self.list_to_writer(path, &mut out)?;
+        // Everything written is escaped JSON
+        Ok(String::from_utf8(out).expect("listing is valid UTF-8"))
+    }
+
+    /// Writes a JSON listing of the tree at `path` to `out`, as an array
+    /// with one object per entry, in the order they're walked:
+    ///
+    /// ```json
+    /// [
+    ///   {"path": "src", "type": "dir", "size": 4096, "mtime": 1718000000.5},
+    ///   {"path": "src/lib.rs", "type": "file", "size": 1024, "mtime": 1718000000.25}
+    /// ]
+    /// ```
+    ///
+    /// Paths are relative to `path`, and separated by `/` on every
+    /// platform. `type` is one of `file`, `dir`, `symlink`, or `other`, and
+    /// symbolic links aren't followed. `mtime` is the modification time in
+    /// seconds since the Unix epoch, or `null` if it's unavailable. Paths
+    /// which aren't valid Unicode are converted lossily.
+    ///
+    /// The entries are fetched with [`Dir::walk_stat`], and written as
+    /// they're found, so listing a large tree doesn't hold it in memory.
+    pub fn list_to_writer<P2: AsRef<Path>, W: Write>(&self, path: P2, mut out: W) -> Result<()> {
+        let base = self.relative_base(path.as_ref());
+        let mut line = String::new();
+        let mut first = true;
+        out.write_all(b"[")?;
+        for entry in self.walk_stat(path) {
+            let (entry, stat) = entry?;
+            let relative = entry.path().strip_prefix(&base).unwrap_or(entry.path());
+            line.clear();
+            line.push_str(if first { "\n  " } else { ",\n  " });
+            write_entry(&mut line, relative, &stat);
+            out.write_all(line.as_bytes())?;
+            first = false;
+        }
+        out.write_all(if first { b"]\n" } else { b"\n]\n" })?;
+        out.flush()
+    }
+}
+
+fn write_entry(out: &mut String, path: &Path, stat: &EntryStat) {
+    let kind = match stat.kind {
+        FileKind::File => "file",
+        FileKind::Dir => "dir",
+        FileKind::Symlink => "symlink",
+        FileKind::Other => "other",
+    };
+    out.push_str("{\"path\": ");
+    write_json_str(out, &slash_separated(path));
+    let _ = write!(
+        out,
+        ", \"type\": \"{kind}\", \"size\": {}, \"mtime\": ",
+        stat.size
+    );
+    match stat
+        .modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+    {
+        Some(since) => write_seconds(out, since.as_secs(), since.subsec_nanos()),
+        None => out.push_str("null"),
+    }
+    out.push('}');
+}
+
+/// Joins the components of `path` with `/`, whatever the platform's
+/// separator
+fn slash_separated(path: &Path) -> String {
+    let mut joined = String::new();
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            if !joined.is_empty() {
+                joined.push('/');
+            }
+            joined.push_str(&name.to_string_lossy());
+        }
+    }
+    joined
+}
+
+/// Writes a number of seconds without trailing zeros in the fraction, so
+/// that whole seconds are written as integers
+fn write_seconds(out: &mut String, secs: u64, nanos: u32) {
+    let _ = write!(out, "{secs}");
+    if nanos != 0 {
+        let fraction = format!("{nanos:09}");
+        let _ = write!(out, ".{}", fraction.trim_end_matches('0'));
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
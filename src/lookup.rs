@@ -0,0 +1,169 @@
+use std::ffi::{OsStr, OsString};
+use std::io::{ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+use crate::Dir;
+
+#[derive(Debug, Clone, Default)]
+/// Options for [`Dir::find_with`], which say when a name on disk matches a
+/// component of the path being looked up
+pub struct LookupOptions {
+    ignore_case: bool,
+    #[cfg(feature = "unicode-normalization")]
+    normalize_unicode: bool,
+}
+
+impl LookupOptions {
+    /// Creates the default options, under which names only match exactly
+    pub fn new() -> LookupOptions {
+        LookupOptions::default()
+    }
+
+    /// Sets whether names match ignoring case. See
+    /// [`Dir::find_case_insensitive`].
+    pub fn ignore_case(mut self, ignore: bool) -> Self {
+        self.ignore_case = ignore;
+        self
+    }
+
+    /// Sets whether names match if they're canonically equivalent, such as
+    /// an accented letter written as one code point (NFC) or as a letter
+    /// followed by a combining accent (NFD). See [`Dir::find_normalized`].
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalize_unicode(mut self, normalize: bool) -> Self {
+        self.normalize_unicode = normalize;
+        self
+    }
+
+    /// Returns true if the entry `name` matches the path component
+    /// `component`
+    fn matches(&self, name: &OsStr, component: &OsStr) -> bool {
+        match (name.to_str(), component.to_str()) {
+            (Some(name), Some(component)) => self.key(name) == self.key(component),
+            _ if self.ignore_case => name.eq_ignore_ascii_case(component),
+            _ => name == component,
+        }
+    }
+
+    /// The form of `name` which is compared
+    fn key(&self, name: &str) -> String {
+        #[cfg(feature = "unicode-normalization")]
+        let name = if self.normalize_unicode {
+            use unicode_normalization::UnicodeNormalization;
+            name.nfc().collect()
+        } else {
+            name.to_owned()
+        };
+        if self.ignore_case {
+            name.to_lowercase()
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Finds the entry at `path`, ignoring the case of each component, and
+    /// returns its path with the case it has on disk. Returns `None` if
+    /// there's no such entry.
+    ///
+    /// This is for paths written for case-insensitive filesystems, such as
+    /// those in Windows game mods or asset manifests, which need to work on
+    /// case-sensitive ones:
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let game = Dir::new("/opt/game/data");
+    /// if let Some(path) = game.find_case_insensitive("Textures/Sky.DDS")? {
+    ///     let texture = game.read(path)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Names which aren't valid Unicode are compared ignoring ASCII case
+    /// only. See [`Dir::find_with`] for how components are resolved.
+    pub fn find_case_insensitive<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<PathBuf>> {
+        self.find_with(path, &LookupOptions::new().ignore_case(true))
+    }
+
+    /// Finds the entry at `path`, treating names which differ only in their
+    /// Unicode normalization form as the same, and returns its path as it's
+    /// written on disk. Returns `None` if there's no such entry.
+    ///
+    /// macOS tends to store accented names decomposed (NFD), while most
+    /// other systems compose them (NFC), so a name like `café.txt` copied
+    /// between the two often can't be found by its original spelling.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let photos = Dir::new("/mnt/backup/photos");
+    /// // Typed as NFC, but perhaps stored as NFD
+    /// if let Some(path) = photos.find_normalized("Café/été.jpg")? {
+    ///     let photo = photos.read(path)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn find_normalized<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<PathBuf>> {
+        self.find_with(path, &LookupOptions::new().normalize_unicode(true))
+    }
+
+    /// Finds the entry at `path`, matching each component against the names
+    /// on disk as `options` say, and returns its path as it's written on
+    /// disk. Returns `None` if there's no such entry.
+    ///
+    /// Each component which doesn't exist exactly as given is found by
+    /// scanning its parent directory. If several entries match, the first
+    /// in sorted order is used. `.`, `..`, and root components are kept as
+    /// they are.
+    pub fn find_with<P2: AsRef<Path>>(
+        &self,
+        path: P2,
+        options: &LookupOptions,
+    ) -> Result<Option<PathBuf>> {
+        let mut found = PathBuf::new();
+        for component in path.as_ref().components() {
+            let Component::Normal(name) = component else {
+                found.push(component);
+                continue;
+            };
+            let exact = found.join(name);
+            if missing_to_none(self.symlink_metadata(&exact))?.is_some() {
+                found = exact;
+                continue;
+            }
+
+            let Some(entries) = missing_to_none(self.read_dir(&found))? else {
+                return Ok(None);
+            };
+            let mut candidates: Vec<OsString> = Vec::new();
+            for entry in entries {
+                let entry_name = entry?.file_name();
+                if options.matches(&entry_name, name) {
+                    candidates.push(entry_name);
+                }
+            }
+            match candidates.into_iter().min() {
+                Some(entry_name) => found.push(entry_name),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(found))
+    }
+}
+
+/// Like [`not_found_to_none`](crate::not_found_to_none), but also treats a
+/// path which runs through a file as missing
+fn missing_to_none<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Err(err) if err.kind() == ErrorKind::NotADirectory => Ok(None),
+        result => crate::not_found_to_none(result),
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::OpenOptions;
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::maintenance::unix_secs;
+use crate::Dir;
+
+/// The name of the sidecar file that records accesses to the files in a
+/// directory. See [`Dir::record_access`]
+pub const ACCESS_LOG: &str = ".access-log";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::evict_lru`]
+pub struct EvictionReport {
+    /// The files which were removed, least recently used first, relative
+    /// to the working directory
+    pub removed: Vec<PathBuf>,
+    /// The number of bytes freed by removing files
+    pub bytes_freed: u64,
+    /// The number of bytes still used by the files that remain
+    pub bytes_remaining: u64,
+}
+
+/// Checked before looking up the tracked roots, so that reads cost a single
+/// atomic load when no working directory tracks accesses
+static ANY_TRACKED: AtomicBool = AtomicBool::new(false);
+static TRACKED: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+/// The entries of a sidecar, after merging repeated entries for a file
+struct AccessLog {
+    /// The latest access time of each file, keyed by its escaped name
+    times: HashMap<String, u64>,
+    /// The number of entries in the sidecar
+    entries: usize,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Starts recording every read of a file made through [`Dir::read`] or
+    /// [`Dir::read_to_string`] in the [`ACCESS_LOG`] sidecar of its
+    /// directory, for any `Dir` in the process with the same root. Does
+    /// nothing if the root is already tracked.
+    ///
+    /// Failing to record an access doesn't cause the read to fail.
+    ///
+    /// See: [`Dir::record_access`], [`Dir::evict_lru`]
+    pub fn track_access(&self) {
+        let mut tracked = TRACKED
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let root = self.0.as_ref();
+        if !tracked.iter().any(|tracked| tracked == root) {
+            tracked.push(root.to_owned());
+        }
+        ANY_TRACKED.store(true, Ordering::Release);
+    }
+
+    /// Stops recording reads made through this working directory
+    pub fn untrack_access(&self) {
+        let mut tracked = TRACKED
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        tracked.retain(|tracked| tracked != self.0.as_ref());
+        if tracked.is_empty() {
+            ANY_TRACKED.store(false, Ordering::Release);
+        }
+    }
+
+    /// Records that the file at the given path was just accessed, by
+    /// appending an entry to the [`ACCESS_LOG`] sidecar in its directory.
+    ///
+    /// The sidecar is used by [`Dir::evict_lru`] on filesystems where
+    /// access times are not maintained (such as those mounted with
+    /// `noatime`). It grows with every access, until it's compacted by the
+    /// next call to `evict_lru`.
+    pub fn record_access<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        let path = path.as_ref();
+        let Some(name) = path.file_name() else {
+            return Ok(());
+        };
+        if name == ACCESS_LOG {
+            return Ok(());
+        }
+        let log = path.with_file_name(ACCESS_LOG);
+        let line = format!("{}\t{}\n", unix_secs(SystemTime::now()), escape(name));
+
+        let mut file = self.open(log, OpenOptions::new().create(true).append(true))?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Records a read of `path` if this working directory tracks accesses.
+    /// See [`Dir::track_access`]
+    #[inline]
+    pub(crate) fn note_read(&self, path: &Path) {
+        if !ANY_TRACKED.load(Ordering::Acquire) {
+            return;
+        }
+        let tracked = TRACKED
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if tracked.iter().any(|tracked| tracked == self.0.as_ref()) {
+            drop(tracked);
+            let _ = self.record_access(path);
+        }
+    }
+
+    /// Removes the least recently used files beneath the given path until
+    /// the files that remain use at most `target_bytes`.
+    ///
+    /// The last use of each file is determined by the most recent of:
+    ///
+    /// - the latest entry for it in its directory's [`ACCESS_LOG`] sidecar
+    /// - its access time, unless the filesystem is mounted with `noatime`
+    /// - its modification time
+    ///
+    /// Sidecar files are never evicted. Afterwards, each one is compacted
+    /// to a single entry for each file that remains, so that sidecars stay
+    /// bounded by the number of files in their directory.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered while walking the
+    /// tree or removing files. Files removed before the error remain removed.
+    pub fn evict_lru<P2: AsRef<Path>>(&self, path: P2, target_bytes: u64) -> Result<EvictionReport> {
+        let path = path.as_ref();
+        let use_atime = atime_reliable(&self.join(path));
+
+        // Access times from the sidecars, keyed by directory, then file name
+        let mut logged: HashMap<PathBuf, AccessLog> = HashMap::new();
+        let mut files = Vec::new();
+        let mut total = 0u64;
+
+        for entry in self.walk(path) {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let parent = entry.path().parent().unwrap_or(Path::new("")).to_owned();
+            if entry.file_name() == ACCESS_LOG {
+                logged.insert(parent, self.read_access_log(entry.path())?);
+                continue;
+            }
+
+            let meta = entry.metadata()?;
+            let mut last_used = meta.modified().map(unix_secs).unwrap_or(0);
+            if use_atime {
+                if let Ok(accessed) = meta.accessed() {
+                    last_used = last_used.max(unix_secs(accessed));
+                }
+            }
+            total += meta.len();
+            files.push((last_used, meta.len(), parent, entry.file_name()));
+        }
+
+        for (last_used, _, parent, name) in &mut files {
+            let logged = logged.get(parent).and_then(|log| log.times.get(&escape(name)));
+            if let Some(&time) = logged {
+                *last_used = (*last_used).max(time);
+            }
+        }
+        files.sort_by(|a, b| (a.0, &a.2, &a.3).cmp(&(b.0, &b.2, &b.3)));
+
+        // The entries to keep in each sidecar, for the files that remain
+        let mut compacted: HashMap<PathBuf, HashMap<String, u64>> =
+            logged.keys().map(|parent| (parent.clone(), HashMap::new())).collect();
+        let mut report = EvictionReport::default();
+        for (_, len, parent, name) in files {
+            if total <= target_bytes {
+                let Some(log) = logged.get(&parent) else {
+                    continue;
+                };
+                let name = escape(&name);
+                if let Some(&time) = log.times.get(&name) {
+                    compacted.entry(parent).or_default().insert(name, time);
+                }
+                continue;
+            }
+            let file = parent.join(&name);
+            self.remove_file(&file)?;
+            total -= len;
+            report.bytes_freed += len;
+            report.removed.push(file);
+        }
+        report.bytes_remaining = total;
+
+        for (parent, times) in &compacted {
+            if times.len() != logged[parent].entries {
+                self.write_access_log(&parent.join(ACCESS_LOG), times)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reads a sidecar, returning the latest access time for each file name
+    fn read_access_log(&self, path: &Path) -> Result<AccessLog> {
+        let mut log = AccessLog {
+            times: HashMap::new(),
+            entries: 0,
+        };
+        for line in self.read_to_string(path)?.lines() {
+            log.entries += 1;
+            // Malformed lines (e.g. from a partially-written append) are
+            // skipped, and dropped when the sidecar is compacted
+            let Some((secs, name)) = line.split_once('\t') else {
+                continue;
+            };
+            let Ok(secs) = secs.parse::<u64>() else {
+                continue;
+            };
+            let time = log.times.entry(name.to_owned()).or_insert(secs);
+            *time = (*time).max(secs);
+        }
+        Ok(log)
+    }
+
+    fn write_access_log(&self, path: &Path, log: &HashMap<String, u64>) -> Result<()> {
+        let mut entries: Vec<_> = log.iter().collect();
+        entries.sort();
+        let mut contents = String::new();
+        for (name, secs) in entries {
+            contents.push_str(&format!("{secs}\t{name}\n"));
+        }
+        self.write(path, contents)
+    }
+}
+
+/// Escapes a file name for a sidecar entry, so that names which aren't
+/// valid UTF-8, or which contain tabs or newlines, are stored losslessly.
+/// `%`, control characters, and bytes of invalid UTF-8 are written as `%XX`.
+fn escape(name: &OsStr) -> String {
+    let mut escaped = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c == '%' || c.is_ascii_control() {
+                escaped.push_str(&format!("%{:02X}", c as u8));
+            } else {
+                escaped.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+/// Returns false if the filesystem containing `path` is known not to
+/// update access times
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn atime_reliable(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return false;
+    }
+    let stat = unsafe { stat.assume_init() };
+    stat.f_flag & libc::ST_NOATIME == 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn atime_reliable(_path: &Path) -> bool {
+    true
+}
@@ -0,0 +1,271 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crc32::Crc32;
+use crate::Dir;
+
+/// The default location of the persisted state file, relative to the root
+const DEFAULT_STATE_FILE: &str = ".maintenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The priority of a maintenance task. When more tasks are due than the
+/// scheduler is allowed to run, higher priority tasks run first.
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+type TaskFn<'a, P> = Box<dyn FnMut(&Dir<P>) -> Result<()> + 'a>;
+
+struct Task<'a, P>
+where
+    P: AsRef<Path>,
+{
+    name: String,
+    interval: Duration,
+    priority: Priority,
+    run: TaskFn<'a, P>,
+}
+
+/// A scheduler for housekeeping tasks in a working directory, such as
+/// removing stale temp files, pruning old logs, or verifying checksums.
+///
+/// Each task has a name, an interval, and a priority. When [`Maintenance::run`]
+/// is called, every task whose interval has elapsed since it last ran is
+/// executed, highest priority first. The time each task last ran is
+/// persisted to a state file inside the working directory, so the schedule
+/// survives restarts.
+///
+/// Created with [`Dir::maintenance`].
+pub struct Maintenance<'a, P>
+where
+    P: AsRef<Path>,
+{
+    dir: &'a Dir<P>,
+    state_file: PathBuf,
+    jitter: Duration,
+    max_tasks: Option<usize>,
+    tasks: Vec<Task<'a, P>>,
+}
+
+#[derive(Debug, Default)]
+/// The outcome of a call to [`Maintenance::run`]
+pub struct MaintenanceReport {
+    /// Tasks which ran and completed successfully
+    pub completed: Vec<String>,
+    /// Tasks which ran and returned an error
+    pub failed: Vec<(String, Error)>,
+    /// Tasks which were due, but were deferred because the scheduler
+    /// already ran the maximum number of tasks for this run
+    pub deferred: Vec<String>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a maintenance scheduler for this working directory.
+    ///
+    /// By default, the scheduler persists its state in `.maintenance`,
+    /// applies no jitter, and runs every task that is due.
+    pub fn maintenance(&self) -> Maintenance<'_, P> {
+        Maintenance {
+            dir: self,
+            state_file: PathBuf::from(DEFAULT_STATE_FILE),
+            jitter: Duration::ZERO,
+            max_tasks: None,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl<'a, P> Maintenance<'a, P>
+where
+    P: AsRef<Path>,
+{
+    /// Sets the path of the state file, relative to the working directory
+    pub fn state_file<P2: AsRef<Path>>(mut self, path: P2) -> Self {
+        self.state_file = path.as_ref().to_owned();
+        self
+    }
+
+    /// Delays each task by up to `jitter` past its interval, so that many
+    /// working directories sharing a schedule don't all run at once.
+    ///
+    /// The delay for a given task is stable between calls to
+    /// [`Maintenance::run`], and changes each time the task runs.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Limits the number of tasks executed by a single call to
+    /// [`Maintenance::run`]. Any further due tasks are deferred until the
+    /// next run.
+    pub fn max_tasks_per_run(mut self, max_tasks: usize) -> Self {
+        self.max_tasks = Some(max_tasks);
+        self
+    }
+
+    /// Registers a task which should be run once every `interval`. The
+    /// state file has a line for each task, so `name` can't contain line
+    /// breaks.
+    pub fn task<F>(mut self, name: &str, interval: Duration, priority: Priority, run: F) -> Self
+    where
+        F: FnMut(&Dir<P>) -> Result<()> + 'a,
+    {
+        self.tasks.push(Task {
+            name: name.to_owned(),
+            interval,
+            priority,
+            run: Box::new(run),
+        });
+        self
+    }
+
+    /// Runs every task that is due, highest priority first, then persists
+    /// the time each task ran to the state file.
+    ///
+    /// A task that fails is recorded in the report, and is retried on the
+    /// next run rather than waiting for its full interval.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the state file cannot be read
+    /// or written, or an error of kind [`ErrorKind::InvalidInput`] if the
+    /// name of a task contains a line break. Errors returned by tasks are
+    /// reported in the [`MaintenanceReport`] instead.
+    pub fn run(&mut self) -> Result<MaintenanceReport> {
+        if let Some(task) = self
+            .tasks
+            .iter()
+            .find(|task| task.name.contains(['\n', '\r']))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "maintenance task name {:?} contains a line break",
+                    task.name
+                ),
+            ));
+        }
+        let mut state = self.load_state()?;
+        let now = unix_secs(SystemTime::now());
+
+        let mut due: Vec<&mut Task<'a, P>> = self
+            .tasks
+            .iter_mut()
+            .filter(|task| match state.get(&task.name) {
+                Some(&last) => {
+                    let delay = task.interval + jitter_for(&task.name, last, self.jitter);
+                    now >= last.saturating_add(delay.as_secs())
+                }
+                None => true,
+            })
+            .collect();
+        // Stable sort, so tasks of equal priority run in registration order
+        due.sort_by_key(|task| Reverse(task.priority));
+
+        let limit = self.max_tasks.unwrap_or(usize::MAX);
+        let mut report = MaintenanceReport::default();
+        for (i, task) in due.into_iter().enumerate() {
+            if i >= limit {
+                report.deferred.push(task.name.clone());
+                continue;
+            }
+            match (task.run)(self.dir) {
+                Ok(()) => {
+                    state.insert(task.name.clone(), now);
+                    report.completed.push(task.name.clone());
+                }
+                Err(err) => report.failed.push((task.name.clone(), err)),
+            }
+        }
+
+        self.save_state(&state)?;
+        Ok(report)
+    }
+
+    fn load_state(&self) -> Result<HashMap<String, u64>> {
+        let Some(contents) = self.dir.read_to_string_opt(&self.state_file)? else {
+            return Ok(HashMap::new());
+        };
+
+        let mut state = HashMap::new();
+        for line in contents.lines().filter(|line| !line.is_empty()) {
+            let parsed = line
+                .rsplit_once('\t')
+                .and_then(|(name, secs)| Some((name, secs.parse::<u64>().ok()?)));
+            match parsed {
+                Some((name, secs)) => state.insert(name.to_owned(), secs),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("malformed maintenance state entry: {line:?}"),
+                    ))
+                }
+            };
+        }
+        Ok(state)
+    }
+
+    fn save_state(&self, state: &HashMap<String, u64>) -> Result<()> {
+        let mut entries: Vec<_> = state.iter().collect();
+        entries.sort();
+
+        let mut contents = String::new();
+        for (name, secs) in entries {
+            contents.push_str(&format!("{name}\t{secs}\n"));
+        }
+
+        // Write to a temporary file first, so a crash never leaves a
+        // truncated state file behind
+        let mut tmp = self.state_file.clone().into_os_string();
+        tmp.push(".tmp");
+        self.dir.create_parents(&self.state_file)?;
+        self.dir.write(&tmp, contents)?;
+        self.dir.rename(&tmp, &self.state_file)
+    }
+}
+
+impl<P> Debug for Maintenance<'_, P>
+where
+    P: AsRef<Path>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tasks: Vec<_> = self.tasks.iter().map(|task| &task.name).collect();
+        f.debug_struct("Maintenance")
+            .field("dir", self.dir)
+            .field("state_file", &self.state_file)
+            .field("jitter", &self.jitter)
+            .field("max_tasks", &self.max_tasks)
+            .field("tasks", &tasks)
+            .finish()
+    }
+}
+
+pub(crate) fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Picks a delay in `[0, jitter)` for a task, derived from its name and the
+/// time it last ran. This uses CRC-32 rather than `DefaultHasher`, whose
+/// output may change between Rust releases, so that a task keeps the same
+/// delay across upgrades.
+fn jitter_for(name: &str, last_run: u64, jitter: Duration) -> Duration {
+    let secs = jitter.as_secs();
+    if secs == 0 {
+        return Duration::ZERO;
+    }
+    let mut crc = Crc32::new();
+    crc.update(name.as_bytes());
+    crc.update(&last_run.to_le_bytes());
+    Duration::from_secs(u64::from(crc.finish()) % secs)
+}
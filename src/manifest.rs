@@ -0,0 +1,219 @@
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::hash::hash_reader;
+use crate::{Digest, Dir, HashAlgo};
+
+/// The first line of a manifest in its text form, followed by the algorithm
+const HEADER: &str = "# working_dir manifest";
+/// The second line of a manifest in its text form, followed by the root
+const ROOT: &str = "# root:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A single file recorded in a [`Manifest`]
+pub struct ManifestEntry {
+    /// The path of the file, relative to the working directory
+    pub path: PathBuf,
+    /// The length of the file in bytes
+    pub len: u64,
+    /// The digest of the file's contents
+    pub digest: Digest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A record of the length and digest of every file in a tree, generated by
+/// [`Dir::build_manifest`]. Entries are sorted by path.
+///
+/// Besides `serde` support (with the `serde` feature), a manifest can be
+/// converted to and from a line-oriented text form, similar to the output
+/// of `sha256sum`:
+///
+/// ```text
+/// # working_dir manifest crc32
+/// # root: release
+/// 3610a686 5 release/bin/app
+/// 0d4a1185 11 release/share/readme.txt
+/// ```
+pub struct Manifest {
+    /// The algorithm used to compute the digests
+    pub algo: HashAlgo,
+    /// The path of the tree covered by this manifest, relative to the
+    /// working directory
+    pub root: PathBuf,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Looks up the entry with the given relative path
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&ManifestEntry> {
+        let path = path.as_ref();
+        self.entries
+            .binary_search_by(|entry| entry.path.as_path().cmp(path))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// Renders this manifest in its text form.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidData`] if a path is not
+    /// valid UTF-8, or contains a newline, since it couldn't be parsed back.
+    pub fn to_text(&self) -> Result<String> {
+        let text_path = |path: &Path| {
+            let text = path.to_str().filter(|text| !text.contains('\n'));
+            text.map(str::to_owned).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{path:?} can't be stored in a text manifest"),
+                )
+            })
+        };
+
+        let mut text = format!(
+            "{HEADER} {}\n{ROOT} {}\n",
+            self.algo,
+            text_path(&self.root)?
+        );
+        for entry in &self.entries {
+            let path = text_path(&entry.path)?;
+            writeln!(text, "{} {} {path}", entry.digest, entry.len).unwrap();
+        }
+        Ok(text)
+    }
+
+    /// Parses a manifest from its text form
+    ///
+    /// See: [`Manifest::to_text`]
+    pub fn parse(text: &str) -> Result<Manifest> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let mut lines = text.lines();
+        let algo = lines
+            .next()
+            .and_then(|line| line.strip_prefix(HEADER))
+            .and_then(|rest| rest.strip_prefix(' '))
+            .ok_or_else(|| invalid("missing manifest header".to_owned()))?
+            .parse()?;
+        let root = lines
+            .next()
+            .and_then(|line| line.strip_prefix(ROOT))
+            .map(|rest| PathBuf::from(rest.strip_prefix(' ').unwrap_or(rest)))
+            .ok_or_else(|| invalid("missing manifest root".to_owned()))?;
+
+        let mut entries = Vec::new();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut fields = line.splitn(3, ' ');
+            let (Some(digest), Some(len), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(format!("malformed manifest entry {line:?}")));
+            };
+            entries.push(ManifestEntry {
+                path: PathBuf::from(path),
+                len: len
+                    .parse()
+                    .map_err(|_| invalid(format!("malformed manifest entry {line:?}")))?,
+                digest: digest.parse()?,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Manifest {
+            algo,
+            root,
+            entries,
+        })
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Builds a manifest of every regular file beneath the given path,
+    /// recording its relative path, length, and digest. Files are streamed
+    /// through the hash rather than read into memory.
+    ///
+    /// Symbolic links are not followed, and are not recorded.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered.
+    pub fn build_manifest<P2: AsRef<Path>>(&self, path: P2, algo: HashAlgo) -> Result<Manifest> {
+        let path = path.as_ref();
+        let mut entries = Vec::new();
+        for entry in self.walk(path) {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let (digest, len) = hash_reader(algo, self.open_readonly(entry.path())?)?;
+            entries.push(ManifestEntry {
+                path: entry.path().to_owned(),
+                len,
+                digest,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Manifest {
+            algo,
+            root: self.relative_base(path),
+            entries,
+        })
+    }
+
+    /// Re-hashes the tree covered by a manifest from [`Dir::build_manifest`],
+    /// reporting files which are missing, which are not in the manifest,
+    /// or whose length or digest no longer match.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the tree can't be read. Files which
+    /// fail verification are reported in the [`VerifyReport`] instead.
+    pub fn verify_manifest(&self, manifest: &Manifest) -> Result<VerifyReport> {
+        let current = self.build_manifest(&manifest.root, manifest.algo)?;
+        let mut report = VerifyReport::default();
+
+        for expected in &manifest.entries {
+            match current.get(&expected.path) {
+                None => report.missing.push(expected.path.clone()),
+                Some(actual) if actual.len != expected.len || actual.digest != expected.digest => {
+                    report.corrupted.push(expected.path.clone())
+                }
+                Some(_) => report.verified += 1,
+            }
+        }
+        for actual in &current.entries {
+            if manifest.get(&actual.path).is_none() {
+                report.extra.push(actual.path.clone());
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::verify_manifest`]. Paths are relative to
+/// the working directory.
+pub struct VerifyReport {
+    /// Files listed in the manifest which no longer exist
+    pub missing: Vec<PathBuf>,
+    /// Files which exist, but aren't listed in the manifest
+    pub extra: Vec<PathBuf>,
+    /// Files whose length or digest differ from the manifest
+    pub corrupted: Vec<PathBuf>,
+    /// The number of files which matched the manifest
+    pub verified: usize,
+}
+
+impl VerifyReport {
+    /// Returns true if the tree matches the manifest exactly
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.corrupted.is_empty()
+    }
+}
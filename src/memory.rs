@@ -0,0 +1,570 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::{Dir, FileKind, FileSystem, FileSystemMut, FsDir};
+
+/// A filesystem held entirely in memory, for tests.
+///
+/// Code which takes an [`FsDir`] can be handed an `FsDir<P, MemoryFs>`,
+/// and exercised without touching the disk or cleaning up afterwards.
+/// Behavior is deterministic: directories are listed in sorted order, and
+/// modification times come from a counter which advances on every change,
+/// starting from the Unix epoch. Symbolic links aren't supported.
+///
+/// Cloning a `MemoryFs` is cheap, and the clone shares the same contents,
+/// so a test can keep a handle to inspect what the code under test did.
+///
+/// ```
+/// # use working_dir::Dir;
+/// # fn main() -> std::io::Result<()> {
+/// let dir = Dir::in_memory("/project");
+/// dir.create_dir("src")?;
+/// dir.write("src/main.rs", "fn main() {}")?;
+/// assert_eq!(dir.read_to_string("src/main.rs")?, "fn main() {}");
+/// assert!(!std::path::Path::new("/project/src/main.rs").exists());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs(Arc<Shared>);
+
+#[derive(Debug, Default)]
+struct Shared {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+    clock: Clock,
+}
+
+#[derive(Debug)]
+enum Node {
+    Dir { modified: SystemTime },
+    File(Arc<Mutex<FileData>>),
+}
+
+#[derive(Debug)]
+struct FileData {
+    contents: Vec<u8>,
+    modified: SystemTime,
+}
+
+/// Produces modification times, one second apart
+#[derive(Debug, Default)]
+struct Clock(AtomicU64);
+
+impl Clock {
+    fn tick(&self) -> SystemTime {
+        let ticks = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+        SystemTime::UNIX_EPOCH + Duration::from_secs(ticks)
+    }
+}
+
+/// Information about an entry in a [`MemoryFs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryMetadata {
+    kind: FileKind,
+    len: u64,
+    modified: SystemTime,
+}
+
+impl MemoryMetadata {
+    /// Returns the type of the entry
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// Returns true if the entry is a file
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    /// Returns true if the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    /// Always returns false, since a [`MemoryFs`] has no symbolic links
+    pub fn is_symlink(&self) -> bool {
+        false
+    }
+
+    /// Returns the size of the file in bytes, or zero for a directory
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the entry has a length of zero
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the time the entry was last modified. This never fails, but
+    /// returns a `Result` to match [`std::fs::Metadata::modified`].
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(self.modified)
+    }
+}
+
+/// An entry within a directory of a [`MemoryFs`], as yielded by
+/// [`Dir::read_dir`]
+#[derive(Debug, Clone)]
+pub struct MemoryEntry {
+    path: PathBuf,
+    metadata: MemoryMetadata,
+}
+
+impl MemoryEntry {
+    /// Returns the full path of this entry
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Returns the bare file name of this entry
+    pub fn file_name(&self) -> OsString {
+        self.path.file_name().unwrap_or_default().to_owned()
+    }
+
+    /// Returns the metadata of this entry, as of when the directory was read
+    pub fn metadata(&self) -> Result<MemoryMetadata> {
+        Ok(self.metadata.clone())
+    }
+}
+
+/// The entries of a directory in a [`MemoryFs`], in sorted order
+pub type MemoryReadDir = std::vec::IntoIter<Result<MemoryEntry>>;
+
+/// An open file in a [`MemoryFs`].
+///
+/// Writes are visible through the filesystem immediately. A file opened
+/// with [`Dir::open_readonly`] can't be written to.
+#[derive(Debug)]
+pub struct MemoryFile {
+    data: Arc<Mutex<FileData>>,
+    fs: Arc<Shared>,
+    pos: u64,
+    writable: bool,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a working directory backed by a new, empty [`MemoryFs`], in
+    /// which the working directory (and its parents) already exist
+    pub fn in_memory(path: P) -> FsDir<P, MemoryFs> {
+        let fs = MemoryFs::new();
+        fs.create_dir_all(path.as_ref())
+            .expect("an empty filesystem has room for any directory");
+        Dir::with_fs(path, fs)
+    }
+}
+
+impl MemoryFs {
+    /// Creates an empty filesystem, containing only the root directory
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    fn nodes(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        lock(&self.0.nodes)
+    }
+}
+
+impl FileSystem for MemoryFs {
+    type File = MemoryFile;
+    type Metadata = MemoryMetadata;
+    type ReadDir = MemoryReadDir;
+
+    fn open_readonly(&self, path: &Path) -> Result<MemoryFile> {
+        let path = normalize(path);
+        match self.nodes().get(&path) {
+            Some(Node::File(data)) => Ok(self.handle(data, false)),
+            Some(Node::Dir { .. }) => Err(is_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        let path = normalize(path);
+        Ok(is_root(&path) || self.nodes().contains_key(&path))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<MemoryMetadata> {
+        let path = normalize(path);
+        if is_root(&path) {
+            return Ok(MemoryMetadata {
+                kind: FileKind::Dir,
+                len: 0,
+                modified: SystemTime::UNIX_EPOCH,
+            });
+        }
+        self.nodes()
+            .get(&path)
+            .map(Node::metadata)
+            .ok_or_else(|| not_found(&path))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<MemoryMetadata> {
+        self.metadata(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let path = normalize(path);
+        match self.nodes().get(&path) {
+            Some(Node::File(data)) => Ok(lock(data).contents.clone()),
+            Some(Node::Dir { .. }) => Err(is_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<MemoryReadDir> {
+        let path = normalize(path);
+        let nodes = self.nodes();
+        if !is_root(&path) {
+            match nodes.get(&path) {
+                Some(Node::Dir { .. }) => {}
+                Some(Node::File(_)) => return Err(not_a_directory(&path)),
+                None => return Err(not_found(&path)),
+            }
+        }
+        let entries: Vec<_> = children(&nodes, &path)
+            .map(|(child, node)| {
+                Ok(MemoryEntry {
+                    path: child.clone(),
+                    metadata: node.metadata(),
+                })
+            })
+            .collect();
+        Ok(entries.into_iter())
+    }
+}
+
+impl FileSystemMut for MemoryFs {
+    fn create_new(&self, path: &Path) -> Result<MemoryFile> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        check_parent(&nodes, &path)?;
+        if nodes.contains_key(&path) {
+            return Err(already_exists(&path));
+        }
+        let data = Arc::new(Mutex::new(FileData {
+            contents: Vec::new(),
+            modified: self.0.clock.tick(),
+        }));
+        let file = self.handle(&data, true);
+        nodes.insert(path, Node::File(data));
+        Ok(file)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        check_parent(&nodes, &path)?;
+        let modified = self.0.clock.tick();
+        match nodes.get(&path) {
+            Some(Node::File(data)) => {
+                let mut data = lock(data);
+                data.contents = contents.to_vec();
+                data.modified = modified;
+            }
+            Some(Node::Dir { .. }) => return Err(is_a_directory(&path)),
+            None => {
+                let data = FileData {
+                    contents: contents.to_vec(),
+                    modified,
+                };
+                nodes.insert(path, Node::File(Arc::new(Mutex::new(data))));
+            }
+        }
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+        let contents = self.read(from)?;
+        self.write(to, &contents)?;
+        Ok(contents.len() as u64)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from, to) = (normalize(from), normalize(to));
+        let mut nodes = self.nodes();
+        let from_is_dir = match nodes.get(&from) {
+            Some(node) => matches!(node, Node::Dir { .. }),
+            None => return Err(not_found(&from)),
+        };
+        if from == to {
+            return Ok(());
+        }
+        check_parent(&nodes, &to)?;
+        match nodes.get(&to) {
+            Some(Node::Dir { .. }) if !from_is_dir => return Err(is_a_directory(&to)),
+            Some(Node::File(_)) if from_is_dir => return Err(not_a_directory(&to)),
+            Some(Node::Dir { .. }) if children(&nodes, &to).next().is_some() => {
+                return Err(Error::new(
+                    ErrorKind::DirectoryNotEmpty,
+                    format!("{to:?} is not empty"),
+                ))
+            }
+            _ => {}
+        }
+        if to.starts_with(&from) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("can't move {from:?} inside of itself"),
+            ));
+        }
+
+        let moved: Vec<PathBuf> = nodes
+            .range(from.clone()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(&from))
+            .cloned()
+            .collect();
+        for path in moved {
+            let node = nodes.remove(&path).expect("the path was just listed");
+            let dest = to.join(path.strip_prefix(&from).expect("paths start with from"));
+            nodes.insert(dest, node);
+        }
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        check_parent(&nodes, &path)?;
+        if is_root(&path) || nodes.contains_key(&path) {
+            return Err(already_exists(&path));
+        }
+        let modified = self.0.clock.tick();
+        nodes.insert(path, Node::Dir { modified });
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        let mut dir = PathBuf::new();
+        for component in path.components() {
+            dir.push(component);
+            match nodes.get(&dir) {
+                Some(Node::Dir { .. }) => {}
+                Some(Node::File(_)) => return Err(not_a_directory(&dir)),
+                None if is_root(&dir) => {}
+                None => {
+                    let modified = self.0.clock.tick();
+                    nodes.insert(dir.clone(), Node::Dir { modified });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        match nodes.get(&path) {
+            Some(Node::File(_)) => {
+                nodes.remove(&path);
+                Ok(())
+            }
+            Some(Node::Dir { .. }) => Err(is_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        match nodes.get(&path) {
+            Some(Node::Dir { .. }) if children(&nodes, &path).next().is_some() => Err(Error::new(
+                ErrorKind::DirectoryNotEmpty,
+                format!("{path:?} is not empty"),
+            )),
+            Some(Node::Dir { .. }) => {
+                nodes.remove(&path);
+                Ok(())
+            }
+            Some(Node::File(_)) => Err(not_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes();
+        match nodes.get(&path) {
+            Some(Node::Dir { .. }) => {
+                nodes.retain(|entry, _| !entry.starts_with(&path));
+                Ok(())
+            }
+            Some(Node::File(_)) => Err(not_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+}
+
+impl MemoryFs {
+    fn handle(&self, data: &Arc<Mutex<FileData>>, writable: bool) -> MemoryFile {
+        MemoryFile {
+            data: data.clone(),
+            fs: self.0.clone(),
+            pos: 0,
+            writable,
+        }
+    }
+}
+
+impl Node {
+    fn metadata(&self) -> MemoryMetadata {
+        match self {
+            Node::Dir { modified } => MemoryMetadata {
+                kind: FileKind::Dir,
+                len: 0,
+                modified: *modified,
+            },
+            Node::File(data) => {
+                let data = lock(data);
+                MemoryMetadata {
+                    kind: FileKind::File,
+                    len: data.contents.len() as u64,
+                    modified: data.modified,
+                }
+            }
+        }
+    }
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = lock(&self.data);
+        let start = (self.pos as usize).min(data.contents.len());
+        let n = (&data.contents[start..]).read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if !self.writable {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "the file was opened read-only",
+            ));
+        }
+        let mut data = lock(&self.data);
+        let start = self.pos as usize;
+        let end = start + buf.len();
+        if data.contents.len() < end {
+            data.contents.resize(end, 0);
+        }
+        data.contents[start..end].copy_from_slice(buf);
+        data.modified = self.fs.clock.tick();
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (lock(&self.data).contents.len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Resolves `.` and `..` lexically, since there are no symbolic links
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+/// The root of the filesystem, which always exists: `/` for absolute
+/// paths, or the empty path for relative ones
+fn is_root(path: &Path) -> bool {
+    path.parent().is_none()
+}
+
+/// Lists the direct children of `dir`
+fn children<'a>(
+    nodes: &'a BTreeMap<PathBuf, Node>,
+    dir: &'a Path,
+) -> impl Iterator<Item = (&'a PathBuf, &'a Node)> {
+    nodes
+        .range(dir.to_owned()..)
+        .take_while(move |(path, _)| path.starts_with(dir))
+        .filter(move |(path, _)| path.parent() == Some(dir))
+}
+
+/// Checks that the parent of `path` exists and is a directory
+fn check_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Result<()> {
+    let Some(parent) = path.parent() else {
+        return Ok(());
+    };
+    match nodes.get(parent) {
+        Some(Node::Dir { .. }) => Ok(()),
+        Some(Node::File(_)) => Err(not_a_directory(parent)),
+        None if is_root(parent) => Ok(()),
+        None => Err(not_found(parent)),
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, format!("{path:?} does not exist"))
+}
+
+fn already_exists(path: &Path) -> Error {
+    Error::new(ErrorKind::AlreadyExists, format!("{path:?} already exists"))
+}
+
+fn is_a_directory(path: &Path) -> Error {
+    Error::new(ErrorKind::IsADirectory, format!("{path:?} is a directory"))
+}
+
+fn not_a_directory(path: &Path) -> Error {
+    Error::new(
+        ErrorKind::NotADirectory,
+        format!("{path:?} is not a directory"),
+    )
+}
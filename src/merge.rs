@@ -0,0 +1,271 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::sync::symlink;
+use crate::{Dir, FileKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What [`Dir::merge_from`] and [`CopyOptions`](crate::CopyOptions) do when
+/// an incoming entry already exists
+pub enum ConflictPolicy {
+    /// Keep the existing entry, and skip the incoming one
+    KeepExisting,
+    /// Replace the existing entry with the incoming one
+    Overwrite,
+    /// Keep the existing entry, and store the incoming one next to it under
+    /// a new name: `config.toml` becomes `config-1.toml`, then
+    /// `config-2.toml`, and so on
+    Rename,
+    /// Fail without merging anything
+    Error,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::merge_from`]. Paths are relative to the
+/// working directories.
+pub struct MergeReport {
+    /// Incoming files and symlinks which didn't conflict
+    pub copied: Vec<PathBuf>,
+    /// Incoming entries skipped under [`ConflictPolicy::KeepExisting`]
+    pub skipped: Vec<PathBuf>,
+    /// Existing entries replaced under [`ConflictPolicy::Overwrite`]
+    pub overwritten: Vec<PathBuf>,
+    /// Incoming entries stored under a new name with
+    /// [`ConflictPolicy::Rename`], as `(original, renamed)` pairs
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Merges the tree at `path` in `other` into the tree at `path` in this
+    /// working directory, creating directories as necessary.
+    ///
+    /// Directories present in both trees are merged recursively. Any other
+    /// entry which already exists (including a file where the incoming tree
+    /// has a directory, or vice versa) is a conflict, handled according to
+    /// `policy`.
+    ///
+    /// # Errors
+    ///
+    /// With [`ConflictPolicy::Error`], all conflicts are detected before
+    /// anything is copied, and an error of kind [`ErrorKind::AlreadyExists`]
+    /// is returned if there are any. Otherwise, this function returns the
+    /// first error encountered, and changes made before it are not undone.
+    pub fn merge_from<Q, P2>(
+        &self,
+        other: &Dir<Q>,
+        path: P2,
+        policy: ConflictPolicy,
+    ) -> Result<MergeReport>
+    where
+        Q: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let incoming = other.snapshot(path)?;
+        let entries = incoming
+            .iter()
+            .map(|entry| (entry.path.as_path(), entry.kind));
+        let mut resolver = Resolver::new(self, policy, entries)?;
+
+        self.create_dir_all(path)?;
+        let mut copied = Vec::new();
+        for entry in &incoming {
+            let Some(placement) = resolver.place(&entry.path, entry.kind)? else {
+                continue;
+            };
+            let Placement {
+                target,
+                dest,
+                existing,
+                conflicted,
+            } = placement;
+
+            let source = other.join(&entry.path);
+            match entry.kind {
+                FileKind::Dir => {
+                    if existing != Some(FileKind::Dir) || dest != target {
+                        self.create_dir(&dest)?;
+                    }
+                }
+                FileKind::File => {
+                    fs::copy(&source, self.join(&dest))?;
+                }
+                FileKind::Symlink => {
+                    symlink(&fs::read_link(&source)?, &self.join(&dest), &source)?;
+                }
+                FileKind::Other => continue,
+            }
+            if entry.kind != FileKind::Dir && !conflicted {
+                copied.push(entry.path.clone());
+            }
+        }
+        let Resolver {
+            skipped,
+            overwritten,
+            renamed,
+            ..
+        } = resolver;
+        Ok(MergeReport {
+            copied,
+            skipped,
+            overwritten,
+            renamed,
+        })
+    }
+
+    /// Finds a name for `path` which isn't taken, by appending `-1`, `-2`,
+    /// and so on to its file stem
+    pub(crate) fn unused_name(&self, path: &Path) -> Result<PathBuf> {
+        let stem = path.file_stem().unwrap_or_default();
+        for n in 1.. {
+            let mut name = OsString::from(stem);
+            name.push(format!("-{n}"));
+            if let Some(ext) = path.extension() {
+                name.push(".");
+                name.push(ext);
+            }
+            let candidate = path.with_file_name(name);
+            if self.symlink_metadata_opt(&candidate)?.is_none() {
+                return Ok(candidate);
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Decides where incoming entries go, according to a [`ConflictPolicy`].
+/// This is shared by [`Dir::merge_from`] and
+/// [`Dir::copy_dir_with`], which fill in the
+/// rest of their reports themselves.
+pub(crate) struct Resolver<'a, P>
+where
+    P: AsRef<Path>,
+{
+    dir: &'a Dir<P>,
+    policy: ConflictPolicy,
+    // Conflicting directories which were skipped or renamed, whose
+    // contents are handled along with them
+    handled: Vec<(PathBuf, Option<PathBuf>)>,
+    pub(crate) skipped: Vec<PathBuf>,
+    pub(crate) overwritten: Vec<PathBuf>,
+    pub(crate) renamed: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Where [`Resolver::place`] put an incoming entry
+pub(crate) struct Placement {
+    /// Where the entry would go if nothing conflicted
+    pub(crate) target: PathBuf,
+    /// Where the entry should be created
+    pub(crate) dest: PathBuf,
+    /// What was at `target` beforehand
+    pub(crate) existing: Option<FileKind>,
+    /// Whether the entry conflicted with an existing one
+    pub(crate) conflicted: bool,
+}
+
+impl<'a, P> Resolver<'a, P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a resolver for entries going into `dir`. With
+    /// [`ConflictPolicy::Error`], `entries` are all checked for conflicts
+    /// up-front.
+    pub(crate) fn new<'b>(
+        dir: &'a Dir<P>,
+        policy: ConflictPolicy,
+        entries: impl IntoIterator<Item = (&'b Path, FileKind)>,
+    ) -> Result<Self> {
+        let resolver = Resolver {
+            dir,
+            policy,
+            handled: Vec::new(),
+            skipped: Vec::new(),
+            overwritten: Vec::new(),
+            renamed: Vec::new(),
+        };
+        if policy == ConflictPolicy::Error {
+            for (path, kind) in entries {
+                if is_conflict(kind, resolver.kind_of(path)?) {
+                    return Err(resolver.already_exists(path));
+                }
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Resolves any conflict for the entry at `path`, removing what's there
+    /// under [`ConflictPolicy::Overwrite`]. Entries must be placed parents
+    /// first. Returns `None` if the entry should be skipped.
+    pub(crate) fn place(&mut self, path: &Path, kind: FileKind) -> Result<Option<Placement>> {
+        let handled_by = self.handled.iter().find(|(dir, _)| path.starts_with(dir));
+        let target = match handled_by {
+            Some((_, None)) => return Ok(None),
+            Some((dir, Some(renamed))) => renamed.join(path.strip_prefix(dir).unwrap()),
+            None => path.to_owned(),
+        };
+
+        let existing = self.kind_of(&target)?;
+        let mut dest = target.clone();
+        let conflicted = handled_by.is_none() && is_conflict(kind, existing);
+        if conflicted {
+            match self.policy {
+                ConflictPolicy::KeepExisting => {
+                    self.skipped.push(target);
+                    if kind == FileKind::Dir {
+                        self.handled.push((path.to_owned(), None));
+                    }
+                    return Ok(None);
+                }
+                ConflictPolicy::Overwrite => {
+                    self.dir.remove_any(&target)?;
+                    self.overwritten.push(target.clone());
+                }
+                ConflictPolicy::Rename => {
+                    dest = self.dir.unused_name(&target)?;
+                    self.renamed.push((target.clone(), dest.clone()));
+                    if kind == FileKind::Dir {
+                        self.handled.push((path.to_owned(), Some(dest.clone())));
+                    }
+                }
+                // Conflicts were checked up-front, so something else
+                // created this one since
+                ConflictPolicy::Error => return Err(self.already_exists(path)),
+            }
+        }
+        Ok(Some(Placement {
+            target,
+            dest,
+            existing,
+            conflicted,
+        }))
+    }
+
+    fn kind_of(&self, path: &Path) -> Result<Option<FileKind>> {
+        Ok(self
+            .dir
+            .symlink_metadata_opt(path)?
+            .map(|meta| FileKind::from(meta.file_type())))
+    }
+
+    fn already_exists(&self, path: &Path) -> Error {
+        Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{:?} already exists in {:?}", path, self.dir),
+        )
+    }
+}
+
+/// An incoming entry conflicts with anything except a directory merging
+/// into a directory
+fn is_conflict(kind: FileKind, existing: Option<FileKind>) -> bool {
+    match existing {
+        Some(FileKind::Dir) => kind != FileKind::Dir,
+        Some(_) => true,
+        None => false,
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::Dir;
+
+#[derive(Debug, Clone)]
+struct Move {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+/// A batch of moves between working directories, executed all-or-nothing.
+///
+/// Moves are staged with [`MoveSet::add`], which follows the same
+/// semantics as [`Dir::move_to`]: a path is moved to the same relative
+/// location beneath another working directory. When the set is executed,
+/// every move is validated up-front, and if any move fails, the moves which
+/// already completed are undone.
+///
+/// ```no_run
+/// use working_dir::{Dir, MoveSet};
+///
+/// let staging = Dir("build/staging");
+/// let release = Dir("build/release");
+///
+/// let mut moves = MoveSet::new();
+/// moves.add(&staging, &release, "bin/app");
+/// moves.add(&staging, &release, "lib/libapp.so");
+/// moves.execute()?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MoveSet {
+    moves: Vec<Move>,
+}
+
+impl MoveSet {
+    /// Creates an empty set of moves
+    #[inline]
+    pub fn new() -> MoveSet {
+        MoveSet::default()
+    }
+
+    /// Stages a move of `<from>/<path>` to `<to>/<path>`
+    pub fn add<P, Q, R>(&mut self, from: &Dir<P>, to: &Dir<Q>, path: R) -> &mut Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        R: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        self.moves.push(Move {
+            from: from.join(path),
+            to: to.join(path),
+        });
+        self
+    }
+
+    /// Returns the number of staged moves
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Returns true if no moves have been staged
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Checks that every staged move can be performed: each source exists,
+    /// no destination exists, and no two moves share a source or a
+    /// destination.
+    ///
+    /// This can not prevent the filesystem from changing between validation
+    /// and execution, which is why [`MoveSet::execute`] also rolls back on
+    /// failure.
+    pub fn validate(&self) -> Result<()> {
+        let mut sources = HashSet::new();
+        let mut destinations = HashSet::new();
+
+        for Move { from, to } in &self.moves {
+            fs::symlink_metadata(from).map_err(|err| {
+                Error::new(err.kind(), format!("cannot move {from:?}: {err}"))
+            })?;
+            if fs::symlink_metadata(to).is_ok() {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("cannot move {from:?}: {to:?} already exists"),
+                ));
+            }
+            if !sources.insert(from) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{from:?} is moved more than once"),
+                ));
+            }
+            if !destinations.insert(to) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{to:?} is the destination of more than one move"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates, then performs every staged move, creating parent
+    /// directories in the destinations as necessary.
+    ///
+    /// # Errors
+    ///
+    /// If validation fails, nothing is moved. If a move fails part-way
+    /// through, every completed move is reversed (on a best-effort basis)
+    /// and any parent directories created for them are removed, then the
+    /// original error is returned.
+    pub fn execute(self) -> Result<()> {
+        self.validate()?;
+
+        let mut created_dirs = Vec::new();
+        for (done, Move { from, to }) in self.moves.iter().enumerate() {
+            let result = create_parents_tracked(to, &mut created_dirs)
+                .and_then(|()| fs::rename(from, to));
+            if let Err(err) = result {
+                for Move { from, to } in self.moves[..done].iter().rev() {
+                    let _ = fs::rename(to, from);
+                }
+                for dir in created_dirs.iter().rev() {
+                    let _ = fs::remove_dir(dir);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates the parents of `path`, recording each directory that didn't
+/// already exist, outermost first
+pub(crate) fn create_parents_tracked(path: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
+    let mut missing = Vec::new();
+    let mut parent = path.parent();
+    while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+        if dir.exists() {
+            break;
+        }
+        missing.push(dir);
+        parent = dir.parent();
+    }
+
+    for dir in missing.into_iter().rev() {
+        match fs::create_dir(dir) {
+            Ok(()) => created.push(dir.to_owned()),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use std::fs::{File, OpenOptions};
+use std::io::Result;
+use std::path::Path;
+
+use crate::Dir;
+
+/// Opens files relative to a [`Dir`] directly from [`OpenOptions`].
+///
+/// This makes it natural to combine [`Dir`] with the platform-specific
+/// extensions to [`OpenOptions`], such as
+/// [`custom_flags`](std::os::unix::fs::OpenOptionsExt::custom_flags) and
+/// [`mode`](std::os::unix::fs::OpenOptionsExt::mode) on Unix, or
+/// `share_mode` and `attributes` on Windows, since the options can be built
+/// and used in a single expression.
+///
+/// ```no_run
+/// # #[cfg(unix)]
+/// # fn main() -> std::io::Result<()> {
+/// use std::fs::OpenOptions;
+/// use std::os::unix::fs::OpenOptionsExt;
+/// use working_dir::{Dir, OpenOptionsDirExt};
+///
+/// let dir = Dir::new("/var/lib/myapp");
+/// // Refuse to follow a symlink planted at `state.db`
+/// let file = OpenOptions::new()
+///     .read(true)
+///     .write(true)
+///     .custom_flags(libc::O_NOFOLLOW)
+///     .mode(0o600)
+///     .open_in(&dir, "state.db")?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// ```
+pub trait OpenOptionsDirExt {
+    /// Opens the file at `path` relative to `dir` with these options.
+    ///
+    /// See: [`Dir::open`]
+    fn open_in<P: AsRef<Path>, P2: AsRef<Path>>(&self, dir: &Dir<P>, path: P2) -> Result<File>;
+}
+
+impl OpenOptionsDirExt for OpenOptions {
+    fn open_in<P: AsRef<Path>, P2: AsRef<Path>>(&self, dir: &Dir<P>, path: P2) -> Result<File> {
+        dir.open(path, self)
+    }
+}
+
+/// A builder for opening files in a working directory, like [`OpenOptions`]
+/// but carrying the directory along, so that opening a file takes a single
+/// expression.
+///
+/// ```no_run
+/// # use working_dir::Dir;
+/// # fn main() -> std::io::Result<()> {
+/// let dir = Dir::new("/var/lib/myapp");
+/// let log = dir.options().append(true).create(true).open("log.txt")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Created with [`Dir::options`].
+#[derive(Debug, Clone)]
+pub struct DirOpenOptions<'a, P>
+where
+    P: AsRef<Path>,
+{
+    dir: &'a Dir<P>,
+    options: OpenOptions,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a builder for opening files in this working directory. All
+    /// options are initially false, as with [`OpenOptions::new`].
+    pub fn options(&self) -> DirOpenOptions<'_, P> {
+        DirOpenOptions {
+            dir: self,
+            options: OpenOptions::new(),
+        }
+    }
+}
+
+impl<P> DirOpenOptions<'_, P>
+where
+    P: AsRef<Path>,
+{
+    /// See: [`OpenOptions::read`]
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.options.read(read);
+        self
+    }
+
+    /// See: [`OpenOptions::write`]
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.options.write(write);
+        self
+    }
+
+    /// See: [`OpenOptions::append`]
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.options.append(append);
+        self
+    }
+
+    /// See: [`OpenOptions::truncate`]
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.options.truncate(truncate);
+        self
+    }
+
+    /// See: [`OpenOptions::create`]
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.options.create(create);
+        self
+    }
+
+    /// See: [`OpenOptions::create_new`]
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.options.create_new(create_new);
+        self
+    }
+
+    /// Sets the permissions a newly created file is given, before the
+    /// process's umask is applied.
+    ///
+    /// See: [`std::os::unix::fs::OpenOptionsExt::mode`]
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut self.options, mode);
+        self
+    }
+
+    /// Passes extra flags to `open(2)`, such as `O_NOFOLLOW` or `O_DIRECT`.
+    ///
+    /// See: [`std::os::unix::fs::OpenOptionsExt::custom_flags`]
+    #[cfg(unix)]
+    pub fn custom_flags(&mut self, flags: i32) -> &mut Self {
+        std::os::unix::fs::OpenOptionsExt::custom_flags(&mut self.options, flags);
+        self
+    }
+
+    /// Sets which kinds of access other handles may have to the file while
+    /// it's open.
+    ///
+    /// See: `std::os::windows::fs::OpenOptionsExt::share_mode`
+    #[cfg(windows)]
+    pub fn share_mode(&mut self, share: u32) -> &mut Self {
+        std::os::windows::fs::OpenOptionsExt::share_mode(&mut self.options, share);
+        self
+    }
+
+    /// Sets the file attributes a newly created file is given.
+    ///
+    /// See: `std::os::windows::fs::OpenOptionsExt::attributes`
+    #[cfg(windows)]
+    pub fn attributes(&mut self, attributes: u32) -> &mut Self {
+        std::os::windows::fs::OpenOptionsExt::attributes(&mut self.options, attributes);
+        self
+    }
+
+    /// Passes extra flags to `CreateFileW`.
+    ///
+    /// See: `std::os::windows::fs::OpenOptionsExt::custom_flags`
+    #[cfg(windows)]
+    pub fn custom_flags(&mut self, flags: u32) -> &mut Self {
+        std::os::windows::fs::OpenOptionsExt::custom_flags(&mut self.options, flags);
+        self
+    }
+
+    /// Sets the access rights requested, overriding `read` and `write`.
+    ///
+    /// See: `std::os::windows::fs::OpenOptionsExt::access_mode`
+    #[cfg(windows)]
+    pub fn access_mode(&mut self, access: u32) -> &mut Self {
+        std::os::windows::fs::OpenOptionsExt::access_mode(&mut self.options, access);
+        self
+    }
+
+    /// Opens the file at `path`, relative to the working directory, with
+    /// these options.
+    ///
+    /// See: [`Dir::open`]
+    pub fn open<P2: AsRef<Path>>(&self, path: P2) -> Result<File> {
+        self.dir.open(path, &self.options)
+    }
+
+    /// Returns the underlying [`OpenOptions`]
+    pub fn as_open_options(&self) -> &OpenOptions {
+        &self.options
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs::{File, Metadata};
+use std::io::{ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::{not_found_to_none, Dir};
+
+/// A union of several working directories, layered on top of one another.
+///
+/// Reads resolve to the first layer containing the path, starting with the
+/// upper layer, and writes always go to the upper layer. This suits layouts
+/// such as defaults shipped with an application, overridden by the user's
+/// configuration:
+///
+/// ```no_run
+/// # use working_dir::{Dir, OverlayDir};
+/// # fn main() -> std::io::Result<()> {
+/// let config = OverlayDir::new(Dir::new("/home/me/.config/myapp"))
+///     .with_lower(Dir::new("/usr/share/myapp/defaults"));
+/// let theme = config.read_to_string("theme.toml")?;
+/// config.write("theme.toml", theme.replace("light", "dark"))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Removing a path only removes it from the upper layer, so a copy in a
+/// lower layer becomes visible again.
+#[derive(Debug)]
+pub struct OverlayDir<P: AsRef<Path>> {
+    layers: Vec<Dir<P>>,
+}
+
+impl<P> OverlayDir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates an overlay with a single layer, `upper`, which receives all
+    /// writes
+    pub fn new(upper: Dir<P>) -> Self {
+        OverlayDir {
+            layers: vec![upper],
+        }
+    }
+
+    /// Adds a layer beneath the existing ones, which is only consulted for
+    /// paths that none of the layers above it contain
+    pub fn with_lower(mut self, lower: Dir<P>) -> Self {
+        self.layers.push(lower);
+        self
+    }
+
+    /// Returns the layer which receives writes
+    pub fn upper(&self) -> &Dir<P> {
+        &self.layers[0]
+    }
+
+    /// Returns every layer, from the upper layer down
+    pub fn layers(&self) -> &[Dir<P>] {
+        &self.layers
+    }
+
+    /// Returns the first layer containing `path`, if any. Broken symbolic
+    /// links count as existing, so that they shadow lower layers.
+    pub fn layer_of<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<&Dir<P>>> {
+        let path = path.as_ref();
+        for layer in &self.layers {
+            if layer.symlink_metadata_opt(path)?.is_some() {
+                return Ok(Some(layer));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the full path of `path` in the first layer containing it
+    pub fn resolve<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<PathBuf>> {
+        let path = path.as_ref();
+        Ok(self.layer_of(path)?.map(|layer| layer.join(path)))
+    }
+
+    /// Returns true if any layer contains `path`, as decided by
+    /// [`OverlayDir::layer_of`]. Returns false if a layer can't be checked.
+    pub fn exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.layer_of(path).is_ok_and(|layer| layer.is_some())
+    }
+
+    /// Reads `path` from the first layer containing it.
+    ///
+    /// See: [`Dir::read`]
+    pub fn read<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<u8>> {
+        self.first(path.as_ref(), |layer, path| layer.read(path))
+    }
+
+    /// Reads `path` as a string from the first layer containing it.
+    ///
+    /// See: [`Dir::read_to_string`]
+    pub fn read_to_string<P2: AsRef<Path>>(&self, path: P2) -> Result<String> {
+        self.first(path.as_ref(), |layer, path| layer.read_to_string(path))
+    }
+
+    /// Opens `path` read-only from the first layer containing it.
+    ///
+    /// See: [`Dir::open_readonly`]
+    pub fn open_readonly<P2: AsRef<Path>>(&self, path: P2) -> Result<File> {
+        self.first(path.as_ref(), |layer, path| layer.open_readonly(path))
+    }
+
+    /// Returns the metadata of `path` in the first layer containing it.
+    ///
+    /// See: [`Dir::metadata`]
+    pub fn metadata<P2: AsRef<Path>>(&self, path: P2) -> Result<Metadata> {
+        self.first(path.as_ref(), |layer, path| layer.metadata(path))
+    }
+
+    /// Returns the names of the entries within the directory at `path`,
+    /// merged across every layer, sorted and without duplicates. Layers
+    /// which don't contain the directory are skipped.
+    pub fn read_dir_names<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<OsString>> {
+        let path = path.as_ref();
+        let mut names = BTreeSet::new();
+        let mut found = false;
+        for layer in &self.layers {
+            let Some(entries) = not_found_to_none(layer.read_dir(path))? else {
+                continue;
+            };
+            found = true;
+            for entry in entries {
+                names.insert(entry?.file_name());
+            }
+        }
+        if !found {
+            return Err(not_found(path));
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    /// Writes `contents` to `path` in the upper layer, creating its parent
+    /// directories if necessary.
+    ///
+    /// See: [`Dir::write`]
+    pub fn write<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, contents: C) -> Result<()> {
+        let path = path.as_ref();
+        self.upper().create_parents(path)?;
+        self.upper().write(path, contents)
+    }
+
+    /// Creates a directory and its parents in the upper layer.
+    ///
+    /// See: [`Dir::create_dir_all`]
+    pub fn create_dir_all<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.upper().create_dir_all(path)
+    }
+
+    /// Removes a file from the upper layer.
+    ///
+    /// See: [`Dir::remove_file`]
+    pub fn remove_file<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        self.upper().remove_file(path)
+    }
+
+    /// Copies `path` from the layer it resolves to into the upper layer, so
+    /// that it can be modified in place. Does nothing if the upper layer
+    /// already contains it.
+    pub fn copy_up<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        let path = path.as_ref();
+        let Some(layer) = self.layer_of(path)? else {
+            return Err(not_found(path));
+        };
+        if std::ptr::eq(layer, self.upper()) {
+            return Ok(());
+        }
+        self.upper().create_parents(path)?;
+        std::fs::copy(layer.join(path), self.upper().join(path)).map(|_| ())
+    }
+
+    /// Runs `op` on the first layer containing `path`, without falling
+    /// through to lower layers if it fails
+    fn first<T>(&self, path: &Path, op: impl Fn(&Dir<P>, &Path) -> Result<T>) -> Result<T> {
+        match self.layer_of(path)? {
+            Some(layer) => op(layer, path),
+            None => Err(not_found(path)),
+        }
+    }
+}
+
+fn not_found(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::NotFound,
+        format!("{path:?} isn't in any layer of the overlay"),
+    )
+}
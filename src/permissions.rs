@@ -0,0 +1,191 @@
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a directory at `path` with the given [`DirBuilder`], which
+    /// says whether missing parents are created, and on Unix, the mode the
+    /// new directories are given.
+    ///
+    /// ```no_run
+    /// # #[cfg(unix)]
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::fs::DirBuilder;
+    /// use std::os::unix::fs::DirBuilderExt;
+    /// use working_dir::Dir;
+    ///
+    /// let app = Dir::new("/srv/app");
+    /// app.create_dir_with("shared/uploads", DirBuilder::new().recursive(true).mode(0o2770))?;
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(unix))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// See: [`DirBuilder::create`]
+    pub fn create_dir_with<P2: AsRef<Path>>(&self, path: P2, builder: &DirBuilder) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("create_dir", self.0.as_ref(), path, || builder.create(path))
+    }
+
+    /// Recursively creates a directory and all of its parent components if
+    /// they're missing, giving each directory it creates the permissions
+    /// `mode`, such as `0o750`, before the process's umask is applied.
+    /// Directories which already exist are left as they are.
+    ///
+    /// See: [`std::os::unix::fs::DirBuilderExt::mode`]
+    #[cfg(unix)]
+    pub fn create_dir_all_mode<P2: AsRef<Path>>(&self, path: P2, mode: u32) -> Result<()> {
+        use std::os::unix::fs::DirBuilderExt;
+        with_paths! { path = self / path }
+        timed("create_dir_all", self.0.as_ref(), path, || {
+            DirBuilder::new().recursive(true).mode(mode).create(path)
+        })
+    }
+
+    /// Writes `contents` as the entire contents of the file at `path`,
+    /// which is given the permissions `mode`, such as `0o600`, from the
+    /// moment it's created. Writing with [`Dir::write`] and then changing
+    /// the permissions leaves a window in which a secret is readable by
+    /// anyone the default permissions allow.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let config = Dir::new("/etc/myapp");
+    /// config.write_with_mode("api-token", "s3cr3t", 0o600)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// On Unix, the file ends up with exactly `mode`, whatever the
+    /// process's umask. If it already exists, its permissions are changed
+    /// before any of the new contents are written. On Windows, the file is
+    /// made read-only if `mode` doesn't grant the owner write access, and
+    /// the other bits are ignored.
+    pub fn write_with_mode<P2: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P2,
+        contents: C,
+        mode: u32,
+    ) -> Result<()> {
+        with_paths! { path = self / path }
+        let contents = contents.as_ref();
+        timed("write", self.0.as_ref(), path, || {
+            let mut options = OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            {
+                use std::fs::Permissions;
+                use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+                let mut file = options.mode(mode).open(path)?;
+                // The umask may have cleared some bits, and an existing
+                // file keeps the permissions it had
+                file.set_permissions(Permissions::from_mode(mode))?;
+                file.write_all(contents)?;
+            }
+            #[cfg(not(unix))]
+            {
+                let mut file = options.open(path)?;
+                file.write_all(contents)?;
+                if mode & 0o200 == 0 {
+                    let mut permissions = file.metadata()?.permissions();
+                    permissions.set_readonly(true);
+                    file.set_permissions(permissions)?;
+                }
+            }
+            Ok(contents.len() as u64)
+        })
+        .map(|_| ())
+    }
+
+    /// Creates a directory at `path` which only the current user can
+    /// access, for sockets, credentials, and other runtime state. Missing
+    /// parents are created with the default permissions.
+    ///
+    /// ```no_run
+    /// # #[cfg(unix)]
+    /// # fn main() -> std::io::Result<()> {
+    /// # use working_dir::Dir;
+    /// let app = Dir::new("/srv/app");
+    /// app.create_private_dir("run")?;
+    /// let socket = std::os::unix::net::UnixListener::bind(app.join("run/control.sock"))?;
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(unix))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// On Unix, the directory is created with mode `0o700`, which is set
+    /// again after creation in case the umask cleared any of its bits. If
+    /// `path` already exists, it's accepted as long as it's a directory
+    /// rather than a symbolic link, owned by the current user, with no
+    /// permissions for the group or others. On Windows, the directory is
+    /// created with the permissions it inherits, and not checked.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::AlreadyExists`]
+    /// if `path` exists, but isn't a private directory.
+    pub fn create_private_dir<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("create_dir", self.0.as_ref(), path, || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            match create_private(path) {
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => check_private(path),
+                result => result,
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> Result<()> {
+    use std::fs::Permissions;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    DirBuilder::new().mode(0o700).create(path)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o700))
+}
+
+#[cfg(unix)]
+fn check_private(path: &Path) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::symlink_metadata(path)?;
+    let problem = if !meta.is_dir() {
+        "isn't a directory"
+    } else if meta.uid() != unsafe { libc::geteuid() } {
+        "is owned by another user"
+    } else if meta.mode() & 0o077 != 0 {
+        "is accessible to other users"
+    } else {
+        return Ok(());
+    };
+    Err(Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{path:?} already exists, but {problem}"),
+    ))
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> Result<()> {
+    DirBuilder::new().create(path)
+}
+
+#[cfg(not(unix))]
+fn check_private(path: &Path) -> Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        return Ok(());
+    }
+    Err(Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{path:?} already exists, but isn't a directory"),
+    ))
+}
@@ -0,0 +1,106 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Result};
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Reads up to `len` bytes from the file at `path`, starting at
+    /// `offset`, without reading the rest of the file. Fewer bytes are
+    /// returned if the end of the file is reached first.
+    ///
+    /// See: [`std::os::unix::fs::FileExt::read_at`], or `seek_read` on
+    /// Windows
+    pub fn read_at<P2: AsRef<Path>>(&self, path: P2, offset: u64, len: usize) -> Result<Vec<u8>> {
+        with_paths! { path = self / path }
+        timed("read", self.0.as_ref(), path, || {
+            let file = File::open(path)?;
+            let mut buf = vec![0u8; len];
+            let mut filled = 0;
+            while filled < len {
+                match sys::read_at(&file, &mut buf[filled..], offset + filled as u64) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            buf.truncate(filled);
+            Ok(buf)
+        })
+    }
+
+    /// Writes `bytes` to the file at `path`, starting at `offset`, leaving
+    /// the rest of the file untouched. The file is extended if necessary,
+    /// but isn't created if it doesn't exist.
+    ///
+    /// See: [`std::os::unix::fs::FileExt::write_at`], or `seek_write` on
+    /// Windows
+    pub fn write_at<P2: AsRef<Path>>(&self, path: P2, offset: u64, bytes: &[u8]) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("write", self.0.as_ref(), path, || {
+            let file = OpenOptions::new().write(true).open(path)?;
+            let mut written = 0;
+            while written < bytes.len() {
+                match sys::write_at(&file, &bytes[written..], offset + written as u64) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => written += n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(bytes.len() as u64)
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::fs::File;
+    use std::io::Result;
+    use std::os::unix::fs::FileExt;
+
+    pub(super) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+        file.read_at(buf, offset)
+    }
+
+    pub(super) fn write_at(file: &File, buf: &[u8], offset: u64) -> Result<usize> {
+        file.write_at(buf, offset)
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::fs::File;
+    use std::io::Result;
+    use std::os::windows::fs::FileExt;
+
+    pub(super) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+        file.seek_read(buf, offset)
+    }
+
+    pub(super) fn write_at(file: &File, buf: &[u8], offset: u64) -> Result<usize> {
+        file.seek_write(buf, offset)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use std::fs::File;
+    use std::io::{Read, Result, Seek, SeekFrom, Write};
+
+    pub(super) fn read_at(mut file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(buf)
+    }
+
+    pub(super) fn write_at(mut file: &File, buf: &[u8], offset: u64) -> Result<usize> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.write(buf)
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+#[derive(Debug, Clone, Default)]
+/// A flag which can be set from another thread to stop a copy started with
+/// [`Dir::copy_cancellable`]. Clones share the same flag.
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token which hasn't been cancelled
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Requests that any copies using this token stop as soon as possible
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`CancelToken::cancel`] has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Copies the contents and permissions of one file to another, as with
+    /// [`Dir::copy`], calling `progress(copied, total)` as each chunk is
+    /// written, so that long copies can report their progress.
+    ///
+    /// `total` is the length of `from` when the copy started. `progress` is
+    /// called once before anything is copied, and then after each chunk.
+    ///
+    /// On success, the total number of bytes copied is returned.
+    pub fn copy_with_progress<P2, P3, F>(&self, from: P2, to: P3, progress: F) -> Result<u64>
+    where
+        P2: AsRef<Path>,
+        P3: AsRef<Path>,
+        F: FnMut(u64, u64),
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.copy_chunked(
+            "copy_with_progress",
+            from,
+            to,
+            &CancelToken::new(),
+            progress,
+        )
+    }
+
+    /// Like [`Dir::copy_with_progress`], but checks `cancel` before each
+    /// chunk, and stops if it has been cancelled.
+    ///
+    /// # Errors
+    ///
+    /// If the copy is cancelled, the partially written destination is
+    /// removed, and an error of kind [`ErrorKind::Interrupted`] is returned.
+    pub fn copy_cancellable<P2, P3, F>(
+        &self,
+        from: P2,
+        to: P3,
+        cancel: &CancelToken,
+        progress: F,
+    ) -> Result<u64>
+    where
+        P2: AsRef<Path>,
+        P3: AsRef<Path>,
+        F: FnMut(u64, u64),
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.copy_chunked("copy_cancellable", from, to, cancel, progress)
+    }
+
+    /// Copies a file a chunk at a time, timed as `op`
+    fn copy_chunked<F>(
+        &self,
+        op: &'static str,
+        from: &Path,
+        to: &Path,
+        cancel: &CancelToken,
+        mut progress: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed(op, self.0.as_ref(), from, || {
+            let mut source = File::open(from)?;
+            let meta = source.metadata()?;
+            let total = meta.len();
+            let mut dest = File::create(to)?;
+
+            let mut buf = vec![0u8; 64 * 1024];
+            let mut copied = 0u64;
+            progress(copied, total);
+            loop {
+                if cancel.is_cancelled() {
+                    drop(dest);
+                    let _ = fs::remove_file(to);
+                    return Err(Error::new(ErrorKind::Interrupted, "copy was cancelled"));
+                }
+                let n = match source.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                dest.write_all(&buf[..n])?;
+                copied += n as u64;
+                progress(copied, total);
+            }
+            dest.set_permissions(meta.permissions())?;
+            Ok(copied)
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs::DirEntry;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{Dir, FileSystem, FsDir, LookupOptions, StdFs, Walk};
+
+/// A working directory which can only be read from: an [`FsDir`] on the
+/// operating system's filesystem, with the [`ReadOnly`] capability.
+///
+/// A `ReadOnlyDir` exposes the non-mutating subset of the [`Dir`] API, so a
+/// function which takes one proves, at the type level, that it won't modify
+/// the tree it's handed:
+///
+/// ```no_run
+/// # use working_dir::{Dir, ReadOnlyDir};
+/// fn count_files(dir: ReadOnlyDir<&std::path::Path>) -> std::io::Result<usize> {
+///     Ok(dir.walk("").filter(|entry| entry.is_ok()).count())
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let project = Dir::new("my/project");
+/// count_files(project.read_only())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Created with [`Dir::read_only`] or [`Dir::into_read_only`]. Reads go
+/// through the [`FileSystem`], as with any [`FsDir`], so they aren't
+/// recorded by [`Dir::track_access`].
+pub type ReadOnlyDir<P> = FsDir<P, ReadOnly>;
+
+/// A capability granting only read access to a filesystem.
+///
+/// The access an [`FsDir`] has is part of its type. Its mutating methods,
+/// such as [`FsDir::write`], are only available when its filesystem
+/// implements [`FileSystemMut`](crate::FileSystemMut), which `ReadOnly`
+/// doesn't. A library declares exactly the access it needs from its
+/// callers with the bounds it puts on the filesystem:
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use working_dir::{Dir, FileSystem, FileSystemMut, FsDir, ReadOnly};
+/// fn total_len<P: AsRef<Path>, F: FileSystem>(dir: &FsDir<P, ReadOnly<F>>) -> std::io::Result<usize> {
+///     Ok(dir.read("a")?.len() + dir.read("b")?.len())
+/// }
+///
+/// fn reset<P: AsRef<Path>, F: FileSystemMut>(dir: &FsDir<P, F>) -> std::io::Result<()> {
+///     dir.write("a", "")?;
+///     dir.write("b", "")
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let cwd = Dir::in_memory("/data");
+/// reset(&cwd)?;
+/// total_len(&cwd.into_read_only())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Access can only ever be downgraded, with [`FsDir::into_read_only`] or
+/// [`Dir::into_read_only`]. `ReadOnly` wraps the filesystem it restricts,
+/// so `FsDir<P, ReadOnly<MemoryFs>>` is a read-only in-memory directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReadOnly<F = StdFs>(F);
+
+impl<F: FileSystem> FileSystem for ReadOnly<F> {
+    type File = F::File;
+    type Metadata = F::Metadata;
+    type ReadDir = F::ReadDir;
+
+    fn open_readonly(&self, path: &Path) -> Result<F::File> {
+        self.0.open_readonly(path)
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        self.0.try_exists(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.0.exists(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<F::Metadata> {
+        self.0.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<F::Metadata> {
+        self.0.symlink_metadata(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.0.read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        self.0.read_to_string(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<F::ReadDir> {
+        self.0.read_dir(path)
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a read-only view of this working directory
+    pub fn read_only(&self) -> ReadOnlyDir<&Path> {
+        FsDir(self.0.as_ref(), ReadOnly(StdFs))
+    }
+
+    /// Converts this working directory into one which can only be read
+    /// from. See [`ReadOnly`].
+    pub fn into_read_only(self) -> ReadOnlyDir<P> {
+        FsDir(self.0, ReadOnly(StdFs))
+    }
+}
+
+impl<P, F> FsDir<P, F>
+where
+    P: AsRef<Path>,
+    F: FileSystem,
+{
+    /// Converts this working directory into one which can only be read
+    /// from. See [`ReadOnly`].
+    pub fn into_read_only(self) -> FsDir<P, ReadOnly<F>> {
+        let (path, fs) = self.into_parts();
+        FsDir(path, ReadOnly(fs))
+    }
+}
+
+impl<P> From<Dir<P>> for ReadOnlyDir<P>
+where
+    P: AsRef<Path>,
+{
+    fn from(dir: Dir<P>) -> Self {
+        dir.into_read_only()
+    }
+}
+
+/// The read-only parts of the `Dir` API which depend on the operating
+/// system, rather than going through a [`FileSystem`]
+impl<P> ReadOnlyDir<P>
+where
+    P: AsRef<Path>,
+{
+    fn std(&self) -> Dir<&Path> {
+        Dir(self.0.as_ref())
+    }
+
+    /// See: [`Dir::read_dir_sorted`]
+    pub fn read_dir_sorted<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<DirEntry>> {
+        self.std().read_dir_sorted(path)
+    }
+
+    /// See: [`Dir::read_dir_paths`]
+    pub fn read_dir_paths<P2: AsRef<Path>>(&self, path: P2) -> Result<Vec<PathBuf>> {
+        self.std().read_dir_paths(path)
+    }
+
+    /// See: [`Dir::read_link`]
+    pub fn read_link<P2: AsRef<Path>>(&self, path: P2) -> Result<PathBuf> {
+        self.std().read_link(path)
+    }
+
+    /// See: [`Dir::is_file`]
+    pub fn is_file<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().is_file(path)
+    }
+
+    /// See: [`Dir::is_dir`]
+    pub fn is_dir<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().is_dir(path)
+    }
+
+    /// See: [`Dir::is_symlink`]
+    pub fn is_symlink<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().is_symlink(path)
+    }
+
+    /// See: [`Dir::is_hidden`]
+    pub fn is_hidden<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().is_hidden(path)
+    }
+
+    /// See: [`Dir::file_size`]
+    pub fn file_size<P2: AsRef<Path>>(&self, path: P2) -> Result<u64> {
+        self.std().file_size(path)
+    }
+
+    /// See: [`Dir::modified`]
+    pub fn modified<P2: AsRef<Path>>(&self, path: P2) -> Result<SystemTime> {
+        self.std().modified(path)
+    }
+
+    /// See: [`Dir::file_exists`]
+    pub fn file_exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().file_exists(path)
+    }
+
+    /// See: [`Dir::dir_exists`]
+    pub fn dir_exists<P2: AsRef<Path>>(&self, path: P2) -> bool {
+        self.std().dir_exists(path)
+    }
+
+    /// See: [`Dir::try_file_exists`]
+    pub fn try_file_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        self.std().try_file_exists(path)
+    }
+
+    /// See: [`Dir::try_dir_exists`]
+    pub fn try_dir_exists<P2: AsRef<Path>>(&self, path: P2) -> Result<bool> {
+        self.std().try_dir_exists(path)
+    }
+
+    /// See: [`Dir::canonicalize`]
+    pub fn canonicalize<P2: AsRef<Path>>(&self, path: P2) -> Result<PathBuf> {
+        self.std().canonicalize(path)
+    }
+
+    /// See: [`Dir::find_case_insensitive`]
+    pub fn find_case_insensitive<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<PathBuf>> {
+        self.std().find_case_insensitive(path)
+    }
+
+    /// See: [`Dir::find_normalized`]
+    #[cfg(feature = "unicode-normalization")]
+    pub fn find_normalized<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<PathBuf>> {
+        self.std().find_normalized(path)
+    }
+
+    /// See: [`Dir::find_with`]
+    pub fn find_with<P2: AsRef<Path>>(
+        &self,
+        path: P2,
+        options: &LookupOptions,
+    ) -> Result<Option<PathBuf>> {
+        self.std().find_with(path, options)
+    }
+
+    /// See: [`Dir::walk`]
+    pub fn walk<P2: AsRef<Path>>(&self, path: P2) -> Walk {
+        self.std().walk(path)
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::{Error, Result};
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Moves the file or directory at the provided path to the platform's
+    /// trash (or recycle bin) instead of deleting it, so that it can be
+    /// restored by the user.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind
+    /// [`ErrorKind::NotFound`](std::io::ErrorKind::NotFound) if the path
+    /// doesn't exist, and an error of kind
+    /// [`ErrorKind::Other`](std::io::ErrorKind::Other) if the platform's
+    /// trash can't be used, for example on a headless system without one.
+    ///
+    /// See: [`trash::delete`]
+    pub fn remove_to_trash<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        with_paths! { path = self / path }
+        timed("remove_to_trash", self.0.as_ref(), path, || {
+            // The trash crate doesn't preserve the kind of error, so check
+            // for the common case up front
+            std::fs::symlink_metadata(path)?;
+            trash::delete(path).map_err(Error::other)
+        })
+    }
+}
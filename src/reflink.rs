@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Copies the contents and permissions of one file to another, as with
+    /// [`Dir::copy`], but shares the underlying storage between the two
+    /// files where the filesystem supports it.
+    ///
+    /// A cloned file is copy-on-write: cloning is nearly instant regardless
+    /// of the file's size, and doesn't use any additional space until one of
+    /// the copies is modified. If cloning isn't possible (for example,
+    /// because the filesystem doesn't support it, or because the two paths
+    /// are on different filesystems), this falls back to a regular copy.
+    ///
+    /// Returns `true` if the file was cloned, and `false` if it was copied.
+    ///
+    /// This function will **overwrite** the contents of `to`.
+    ///
+    /// See: `FICLONE` on Linux (Btrfs, XFS, and others), `clonefile(2)` on
+    /// macOS (APFS). On other platforms, this always performs a regular copy.
+    pub fn copy_reflink<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<bool> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("copy_reflink", self.0.as_ref(), from, || {
+            if reflink(from, to).is_ok() {
+                return Ok(true);
+            }
+            fs::copy(from, to).map(|_| false)
+        })
+    }
+}
+
+/// Clones `from` to `to` without falling back to a regular copy, returning
+/// an error if the filesystem can't clone the file
+pub(crate) fn reflink(from: &Path, to: &Path) -> Result<()> {
+    sys::reflink(from, to)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{Error, Result};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    use std::path::Path;
+
+    pub(super) fn reflink(from: &Path, to: &Path) -> Result<()> {
+        let source = File::open(from)?;
+        let perms = source.metadata()?.permissions();
+        let dest = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(perms.mode())
+            .open(to)?;
+        if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        fs::set_permissions(to, perms)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod sys {
+    use std::ffi::CString;
+    use std::fs;
+    use std::io::{Error, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use crate::temp::unique_name;
+
+    pub(super) fn reflink(from: &Path, to: &Path) -> Result<()> {
+        // `clonefile` refuses to replace an existing file, so clone to a
+        // temporary name and rename it over the destination
+        let mut prefix = to.as_os_str().to_owned();
+        prefix.push(".reflink-");
+        let tmp = unique_name(Path::new(&prefix));
+        let source = CString::new(from.as_os_str().as_bytes())?;
+        let dest = CString::new(tmp.as_os_str().as_bytes())?;
+        if unsafe { libc::clonefile(source.as_ptr(), dest.as_ptr(), 0) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        fs::rename(&tmp, to).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+mod sys {
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+
+    pub(super) fn reflink(_: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "cloning files isn't supported on this platform",
+        ))
+    }
+}
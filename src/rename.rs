@@ -0,0 +1,279 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::temp::unique_name;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Renames a file or directory, failing with an error of kind
+    /// [`ErrorKind::AlreadyExists`] if `to` already exists, rather than
+    /// replacing it as [`Dir::rename`] does.
+    ///
+    /// Checking that `to` doesn't exist before renaming leaves a window in
+    /// which another process can create it, only for the rename to clobber
+    /// it. This closes that window wherever the platform allows:
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let uploads = Dir::new("/srv/uploads");
+    /// match uploads.rename_noreplace("incoming/part-7", "report.pdf") {
+    ///     Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+    ///         println!("someone else uploaded report.pdf first");
+    ///     }
+    ///     result => result?,
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// See: `renameat2(2)` with `RENAME_NOREPLACE` on Linux, `renamex_np`
+    /// with `RENAME_EXCL` on macOS, and `MoveFileExW` on Windows. Where
+    /// these aren't available, including on filesystems which don't support
+    /// them, files are renamed by hard linking them to `to` and then
+    /// removing `from`, which is just as safe. Directories, and files on
+    /// filesystems which don't support hard links either, are renamed after
+    /// checking that `to` doesn't exist, which isn't.
+    pub fn rename_noreplace<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        from: P2,
+        to: P3,
+    ) -> Result<()> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("rename_noreplace", self.0.as_ref(), from, || {
+            let result = sys::rename_noreplace(from, to);
+            match result {
+                Err(err) if err.kind() == ErrorKind::Unsupported => fallback_noreplace(from, to),
+                result => result,
+            }
+        })
+    }
+
+    /// Swaps the files or directories at `a` and `b`, which must both
+    /// exist, so that each takes the other's place.
+    ///
+    /// This is how a new version of something replaces the current one
+    /// while keeping the old one around, with no moment at which neither
+    /// is in place:
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let site = Dir::new("/srv/site");
+    /// site.swap("current", "next")?;
+    /// // "next" now holds the previous release, for rolling back
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// See: `renameat2(2)` with `RENAME_EXCHANGE` on Linux, and
+    /// `renamex_np` with `RENAME_SWAP` on macOS, which swap the two
+    /// atomically. Elsewhere, including on filesystems which don't support
+    /// these, the swap is made with three renames through a temporary name
+    /// next to `a`, which other processes can observe. If one of them
+    /// fails, the earlier ones are undone.
+    pub fn swap<P2: AsRef<Path>, P3: AsRef<Path>>(&self, a: P2, b: P3) -> Result<()> {
+        with_paths! {
+            a = self / a,
+            b = self / b
+        }
+        timed("swap", self.0.as_ref(), a, || {
+            let result = sys::exchange(a, b);
+            match result {
+                Err(err) if err.kind() == ErrorKind::Unsupported => fallback_swap(a, b),
+                result => result,
+            }
+        })
+    }
+}
+
+fn fallback_swap(a: &Path, b: &Path) -> Result<()> {
+    let mut prefix = a.as_os_str().to_owned();
+    prefix.push(".swap-");
+    let temp = unique_name(Path::new(&prefix));
+    fs::rename(a, &temp)?;
+    if let Err(err) = fs::rename(b, a) {
+        let _ = fs::rename(&temp, a);
+        return Err(err);
+    }
+    if let Err(err) = fs::rename(&temp, b) {
+        let _ = fs::rename(a, b);
+        let _ = fs::rename(&temp, a);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn fallback_noreplace(from: &Path, to: &Path) -> Result<()> {
+    if !fs::symlink_metadata(from)?.is_dir() {
+        match fs::hard_link(from, to) {
+            Ok(()) => return fs::remove_file(from),
+            Err(err) if !sys::links_unsupported(&err) => return Err(err),
+            Err(_) => {}
+        }
+    }
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{to:?} already exists"),
+        ));
+    }
+    fs::rename(from, to)
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CString;
+    use std::io::{Error, ErrorKind, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub(super) fn rename_noreplace(from: &Path, to: &Path) -> Result<()> {
+        renameat2(from, to, libc::RENAME_NOREPLACE)
+    }
+
+    pub(super) fn exchange(a: &Path, b: &Path) -> Result<()> {
+        renameat2(a, b, libc::RENAME_EXCHANGE)
+    }
+
+    /// Returns true if `err` means that the filesystem doesn't support hard
+    /// links, and false for any other failure to create one
+    pub(super) fn links_unsupported(err: &Error) -> bool {
+        err.kind() == ErrorKind::Unsupported
+            || matches!(
+                err.raw_os_error(),
+                // ENOTSUP is the same as EOPNOTSUPP on Linux
+                Some(libc::EPERM | libc::EOPNOTSUPP)
+            )
+    }
+
+    fn renameat2(from: &Path, to: &Path, flags: libc::c_uint) -> Result<()> {
+        let from = CString::new(from.as_os_str().as_bytes())?;
+        let to = CString::new(to.as_os_str().as_bytes())?;
+        let result = unsafe {
+            libc::renameat2(
+                libc::AT_FDCWD,
+                from.as_ptr(),
+                libc::AT_FDCWD,
+                to.as_ptr(),
+                flags,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            // Old kernels, and filesystems without support for the flag
+            Some(libc::ENOSYS | libc::EINVAL) => Err(Error::new(ErrorKind::Unsupported, err)),
+            _ => Err(err),
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod sys {
+    use std::ffi::CString;
+    use std::io::{Error, ErrorKind, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub(super) fn rename_noreplace(from: &Path, to: &Path) -> Result<()> {
+        renamex_np(from, to, libc::RENAME_EXCL)
+    }
+
+    pub(super) fn exchange(a: &Path, b: &Path) -> Result<()> {
+        renamex_np(a, b, libc::RENAME_SWAP)
+    }
+
+    /// Returns true if `err` means that the filesystem doesn't support hard
+    /// links, and false for any other failure to create one
+    pub(super) fn links_unsupported(err: &Error) -> bool {
+        err.kind() == ErrorKind::Unsupported
+            || matches!(
+                err.raw_os_error(),
+                Some(libc::EPERM | libc::EOPNOTSUPP | libc::ENOTSUP)
+            )
+    }
+
+    fn renamex_np(from: &Path, to: &Path, flags: libc::c_uint) -> Result<()> {
+        let from = CString::new(from.as_os_str().as_bytes())?;
+        let to = CString::new(to.as_os_str().as_bytes())?;
+        if unsafe { libc::renamex_np(from.as_ptr(), to.as_ptr(), flags) } == 0 {
+            return Ok(());
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            // Filesystems without support for the flag
+            Some(libc::ENOTSUP) => Err(Error::new(ErrorKind::Unsupported, err)),
+            _ => Err(err),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::io::{Error, ErrorKind, Result};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::Foundation::{ERROR_INVALID_FUNCTION, ERROR_NOT_SUPPORTED};
+    use windows_sys::Win32::Storage::FileSystem::MoveFileExW;
+
+    pub(super) fn rename_noreplace(from: &Path, to: &Path) -> Result<()> {
+        let from: Vec<u16> = from.as_os_str().encode_wide().chain(Some(0)).collect();
+        let to: Vec<u16> = to.as_os_str().encode_wide().chain(Some(0)).collect();
+        // Without MOVEFILE_REPLACE_EXISTING, the move fails if `to` exists
+        if unsafe { MoveFileExW(from.as_ptr(), to.as_ptr(), 0) } == 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(super) fn exchange(_: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(ErrorKind::Unsupported, "no atomic exchange"))
+    }
+
+    /// Returns true if `err` means that the filesystem doesn't support hard
+    /// links, such as FAT, and false for any other failure to create one
+    pub(super) fn links_unsupported(err: &Error) -> bool {
+        err.kind() == ErrorKind::Unsupported
+            || matches!(
+                err.raw_os_error().map(|code| code as u32),
+                Some(ERROR_INVALID_FUNCTION | ERROR_NOT_SUPPORTED)
+            )
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "ios", windows)))]
+mod sys {
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+
+    pub(super) fn rename_noreplace(_: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "renaming without replacing isn't supported on this platform",
+        ))
+    }
+
+    pub(super) fn exchange(_: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "atomic exchange isn't supported on this platform",
+        ))
+    }
+
+    /// Returns true if `err` means that the filesystem doesn't support hard
+    /// links, and false for any other failure to create one
+    pub(super) fn links_unsupported(err: &Error) -> bool {
+        err.kind() == ErrorKind::Unsupported
+    }
+}
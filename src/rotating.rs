@@ -0,0 +1,134 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::Dir;
+
+/// An append-only writer which starts a new file once the current one
+/// reaches a maximum size, keeping a fixed number of old files.
+///
+/// When `app.log` is full, it's renamed to `app.log.1`, `app.log.1` to
+/// `app.log.2`, and so on, dropping the oldest, and writing continues in a
+/// new `app.log`. Files are only rotated between writes, so a file may
+/// exceed the maximum size if a single write is larger than it.
+///
+/// Created with [`Dir::rotating_writer`].
+#[derive(Debug)]
+pub struct RotatingWriter {
+    path: PathBuf,
+    // Closed while rotating, since Windows can't rename open files, and
+    // reopened by the next write if rotating fails part way
+    file: Option<File>,
+    len: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Opens the file at `path` for appending, creating it and its parent
+    /// directories if necessary, and returns a [`RotatingWriter`] which
+    /// rotates it once it reaches `max_bytes`, keeping at most `max_files`
+    /// old files alongside it.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// use std::io::Write;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = Dir::new("/var/lib/myapp");
+    /// let mut log = dir.rotating_writer("logs/app.log", 1 << 20, 5)?;
+    /// writeln!(log, "started")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rotating_writer<P2: AsRef<Path>>(
+        &self,
+        path: P2,
+        max_bytes: u64,
+        max_files: usize,
+    ) -> Result<RotatingWriter> {
+        let path = path.as_ref();
+        self.create_parents(path)?;
+        let path = self.join(path);
+        let file = open_append(&path)?;
+        Ok(RotatingWriter {
+            len: file.metadata()?.len(),
+            path,
+            file: Some(file),
+            max_bytes,
+            max_files,
+        })
+    }
+}
+
+impl RotatingWriter {
+    /// Returns the path of the file currently being written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rotates the files now, regardless of the current file's size
+    pub fn rotate(&mut self) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let numbered = |n: usize| {
+            let mut name = OsString::from(self.path.as_os_str());
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if self.max_files == 0 {
+            ignore_missing(fs::remove_file(&self.path))?;
+        } else {
+            ignore_missing(fs::remove_file(numbered(self.max_files)))?;
+            for n in (1..self.max_files).rev() {
+                ignore_missing(fs::rename(numbered(n), numbered(n + 1)))?;
+            }
+            fs::rename(&self.path, numbered(1))?;
+        }
+        self.file = Some(open_append(&self.path)?);
+        self.len = 0;
+        Ok(())
+    }
+
+    fn file(&mut self) -> Result<&mut File> {
+        if self.file.is_none() {
+            let file = open_append(&self.path)?;
+            self.len = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+}
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file()?.write(buf)?;
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new().append(true).create(true).open(path)
+}
+
+fn ignore_missing(result: Result<()>) -> Result<()> {
+    match result {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
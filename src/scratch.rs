@@ -0,0 +1,102 @@
+use std::fs::{self, File, Metadata};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::instrument::timed;
+use crate::Dir;
+
+/// A working directory paired with a reusable path buffer.
+///
+/// Each [`Dir`] method joins its path with the working directory, which
+/// allocates whenever the result is too long for the stack. When statting or
+/// reading thousands of files, a `PathScratch` joins every path into the same
+/// buffer instead, so the allocation is made once and reused.
+///
+/// Created with [`Dir::with_buffer`].
+///
+/// ```no_run
+/// # use working_dir::Dir;
+/// # use std::path::PathBuf;
+/// # fn main() -> std::io::Result<()> {
+/// let dir = Dir::new("/var/lib/myapp");
+/// let mut scratch = dir.with_buffer(PathBuf::new());
+/// let mut total = 0;
+/// for name in ["a.bin", "b.bin", "c.bin"] {
+///     total += scratch.metadata(name)?.len();
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PathScratch<'a, P: AsRef<Path>> {
+    dir: &'a Dir<P>,
+    buf: PathBuf,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a [`PathScratch`] which joins paths into `buf`, reusing its
+    /// allocation. Pass `PathBuf::new()` to start with an empty buffer, or a
+    /// buffer returned by [`PathScratch::into_buffer`] to keep reusing it.
+    pub fn with_buffer(&self, buf: PathBuf) -> PathScratch<'_, P> {
+        PathScratch { dir: self, buf }
+    }
+}
+
+impl<P> PathScratch<'_, P>
+where
+    P: AsRef<Path>,
+{
+    /// Joins `path` with the working directory into the buffer, returning
+    /// the result. As with [`Dir::join`], absolute paths are returned as-is.
+    pub fn join<P2: AsRef<Path>>(&mut self, path: P2) -> &Path {
+        self.buf.clear();
+        self.buf.push(self.dir.0.as_ref());
+        self.buf.push(path);
+        &self.buf
+    }
+
+    /// See: [`Dir::metadata`]
+    pub fn metadata<P2: AsRef<Path>>(&mut self, path: P2) -> Result<Metadata> {
+        let root = self.dir.0.as_ref();
+        let path = self.join(path);
+        timed("metadata", root, path, || fs::metadata(path))
+    }
+
+    /// See: [`Dir::symlink_metadata`]
+    pub fn symlink_metadata<P2: AsRef<Path>>(&mut self, path: P2) -> Result<Metadata> {
+        let root = self.dir.0.as_ref();
+        let path = self.join(path);
+        timed("symlink_metadata", root, path, || {
+            fs::symlink_metadata(path)
+        })
+    }
+
+    /// See: [`Dir::exists`]
+    pub fn exists<P2: AsRef<Path>>(&mut self, path: P2) -> bool {
+        let root = self.dir.0.as_ref();
+        let path = self.join(path);
+        timed("exists", root, path, || path.exists())
+    }
+
+    /// See: [`Dir::open_readonly`]
+    pub fn open_readonly<P2: AsRef<Path>>(&mut self, path: P2) -> Result<File> {
+        let root = self.dir.0.as_ref();
+        let path = self.join(path);
+        timed("open_readonly", root, path, || File::open(path))
+    }
+
+    /// See: [`Dir::read`]
+    pub fn read<P2: AsRef<Path>>(&mut self, path: P2) -> Result<Vec<u8>> {
+        let root = self.dir.0.as_ref();
+        let path = self.join(path);
+        timed("read", root, path, || fs::read(path))
+    }
+
+    /// Returns the buffer, so its allocation can be reused elsewhere
+    pub fn into_buffer(self) -> PathBuf {
+        self.buf
+    }
+}
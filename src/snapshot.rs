@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fs::{self, FileType};
+use std::io::{ErrorKind, Read, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::crc32::Crc32;
+use crate::{Dir, SymlinkPolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// The type of an entry in a directory tree
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    /// Anything else, such as a socket, FIFO, or device
+    Other,
+}
+
+impl From<FileType> for FileKind {
+    fn from(file_type: FileType) -> FileKind {
+        if file_type.is_file() {
+            FileKind::File
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A single entry of a [`Snapshot`]
+pub struct SnapshotEntry {
+    /// The path of the entry, relative to the working directory
+    pub path: PathBuf,
+    /// The type of the entry. Symbolic links are not followed.
+    pub kind: FileKind,
+    /// The size of the entry in bytes, as reported by its metadata
+    pub size: u64,
+    /// The modification time of the entry, if the platform supports it
+    pub modified: Option<SystemTime>,
+    /// The CRC-32 of the file's contents, for regular files in snapshots
+    /// taken with [`Dir::snapshot_hashed`]
+    pub crc32: Option<u32>,
+}
+
+impl SnapshotEntry {
+    /// Returns true if both entries appear to have the same contents.
+    ///
+    /// Entries of different kinds or sizes never match. If both entries
+    /// have a checksum, the checksums are compared, otherwise their
+    /// modification times are.
+    pub fn same_contents(&self, other: &SnapshotEntry) -> bool {
+        if self.kind != other.kind || self.size != other.size {
+            return false;
+        }
+        match (self.crc32, other.crc32) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.modified == other.modified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An owned record of a directory tree at a point in time, taken with
+/// [`Dir::snapshot`]. Entries are sorted by path.
+pub struct Snapshot {
+    pub entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The differences between two snapshots. See [`Snapshot::diff`]
+pub struct SnapshotDiff {
+    /// Paths present only in the newer snapshot
+    pub added: Vec<PathBuf>,
+    /// Paths present only in the older snapshot
+    pub removed: Vec<PathBuf>,
+    /// Paths present in both snapshots whose contents differ
+    pub modified: Vec<PathBuf>,
+}
+
+impl SnapshotDiff {
+    /// Returns true if the snapshots were identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl Snapshot {
+    /// Looks up the entry with the given relative path
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&SnapshotEntry> {
+        let path = path.as_ref();
+        self.entries
+            .binary_search_by(|entry| entry.path.as_path().cmp(path))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    /// Returns the entries of this snapshot, sorted by path
+    pub fn iter(&self) -> std::slice::Iter<'_, SnapshotEntry> {
+        self.entries.iter()
+    }
+
+    /// Compares this snapshot against a newer one, reporting which paths
+    /// were added, removed, or modified. Directories are only reported as
+    /// added or removed, never as modified.
+    ///
+    /// See: [`SnapshotEntry::same_contents`]
+    pub fn diff(&self, newer: &Snapshot) -> SnapshotDiff {
+        let old: BTreeMap<_, _> = self.iter().map(|e| (&e.path, e)).collect();
+        let new: BTreeMap<_, _> = newer.iter().map(|e| (&e.path, e)).collect();
+
+        let mut diff = SnapshotDiff::default();
+        for (path, entry) in &new {
+            match old.get(path) {
+                None => diff.added.push(path.to_path_buf()),
+                Some(old) if old.kind == FileKind::Dir && entry.kind == FileKind::Dir => {}
+                Some(old) if !old.same_contents(entry) => diff.modified.push(path.to_path_buf()),
+                Some(_) => {}
+            }
+        }
+        for path in old.keys() {
+            if !new.contains_key(path) {
+                diff.removed.push(path.to_path_buf());
+            }
+        }
+        diff
+    }
+}
+
+impl<'a> IntoIterator for &'a Snapshot {
+    type Item = &'a SnapshotEntry;
+    type IntoIter = std::slice::Iter<'a, SnapshotEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Walks the tree at the given path, recording the relative path, type,
+    /// size, and modification time of every entry beneath it.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered during the walk.
+    pub fn snapshot<P2: AsRef<Path>>(&self, path: P2) -> Result<Snapshot> {
+        self.take_snapshot(path.as_ref(), false)
+    }
+
+    /// Like [`Dir::snapshot`], but also records a CRC-32 of the contents
+    /// of every regular file, so that changes which preserve the size and
+    /// modification time are still detected.
+    pub fn snapshot_hashed<P2: AsRef<Path>>(&self, path: P2) -> Result<Snapshot> {
+        self.take_snapshot(path.as_ref(), true)
+    }
+
+    fn take_snapshot(&self, path: &Path, hash: bool) -> Result<Snapshot> {
+        self.take_snapshot_with(path, hash, SymlinkPolicy::Preserve)
+    }
+
+    /// Takes a snapshot, treating symlinks as `symlinks` says. Followed
+    /// links are recorded as what they point to, except broken links, which
+    /// are recorded as links.
+    pub(crate) fn take_snapshot_with(
+        &self,
+        path: &Path,
+        hash: bool,
+        symlinks: SymlinkPolicy,
+    ) -> Result<Snapshot> {
+        let mut entries = Vec::new();
+        for entry in self.walk(path).symlinks(symlinks) {
+            let entry = entry?;
+            let meta = match symlinks {
+                SymlinkPolicy::Follow => match fs::metadata(entry.abs_path()) {
+                    Err(err) if err.kind() == ErrorKind::NotFound => entry.metadata()?,
+                    meta => meta?,
+                },
+                _ => entry.metadata()?,
+            };
+            let kind = FileKind::from(meta.file_type());
+            let crc32 = match (hash, kind) {
+                (true, FileKind::File) => Some(self.crc32_file(entry.path())?),
+                _ => None,
+            };
+            entries.push(SnapshotEntry {
+                path: entry.path().to_owned(),
+                kind,
+                size: meta.len(),
+                modified: meta.modified().ok(),
+                crc32,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Snapshot { entries })
+    }
+
+    /// Computes the CRC-32 of a file's contents, without reading it into
+    /// memory all at once
+    pub(crate) fn crc32_file(&self, path: &Path) -> Result<u32> {
+        let mut file = self.open_readonly(path)?;
+        let mut crc = Crc32::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match file.read(&mut buf)? {
+                0 => return Ok(crc.finish()),
+                n => crc.update(&buf[..n]),
+            }
+        }
+    }
+}
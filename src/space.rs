@@ -0,0 +1,96 @@
+use std::io::Result;
+use std::path::Path;
+
+use crate::Dir;
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns the number of bytes available to the current user on the
+    /// filesystem containing this working directory.
+    ///
+    /// This may be less than the amount of free space on the filesystem,
+    /// for example if some space is reserved for the superuser, or if the
+    /// user is subject to a quota.
+    ///
+    /// See: `statvfs(3)` on Unix, `GetDiskFreeSpaceExW` on Windows
+    pub fn available_space(&self) -> Result<u64> {
+        sys::space(self.0.as_ref()).map(|space| space.available)
+    }
+
+    /// Returns the total size, in bytes, of the filesystem containing
+    /// this working directory.
+    ///
+    /// See: `statvfs(3)` on Unix, `GetDiskFreeSpaceExW` on Windows
+    pub fn total_space(&self) -> Result<u64> {
+        sys::space(self.0.as_ref()).map(|space| space.total)
+    }
+}
+
+struct Space {
+    available: u64,
+    total: u64,
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::Space;
+    use std::ffi::CString;
+    use std::io::{Error, Result};
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    // The widths of the `statvfs` fields vary between platforms
+    #[allow(clippy::unnecessary_cast)]
+    pub(super) fn space(path: &Path) -> Result<Space> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        let stat = unsafe { stat.assume_init() };
+        let fragment = stat.f_frsize as u64;
+        Ok(Space {
+            available: stat.f_bavail as u64 * fragment,
+            total: stat.f_blocks as u64 * fragment,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::Space;
+    use std::io::{Error, Result};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    pub(super) fn space(path: &Path) -> Result<Space> {
+        let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut available = 0u64;
+        let mut total = 0u64;
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(path.as_ptr(), &mut available, &mut total, std::ptr::null_mut())
+        };
+        if ok == 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Space { available, total })
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::Space;
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+
+    pub(super) fn space(_path: &Path) -> Result<Space> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "querying free space is not supported on this platform",
+        ))
+    }
+}
@@ -0,0 +1,123 @@
+use std::io::Result;
+use std::path::Path;
+
+use crate::instrument::timed;
+use crate::{with_paths, Dir};
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Copies the contents and permissions of one file to another, as with
+    /// [`Dir::copy`], while preserving holes in sparse files.
+    ///
+    /// Only the regions of `from` which contain data are read and written;
+    /// holes are recreated in `to` by extending it without writing zeros, so
+    /// a sparse disk image or database file takes up no more space after
+    /// copying than it did before. Files without holes are copied normally.
+    ///
+    /// On success, the length of the file is returned. Unlike [`Dir::copy`],
+    /// this includes the holes, which weren't copied.
+    ///
+    /// This function will **overwrite** the contents of `to`.
+    ///
+    /// See: `lseek(2)` with `SEEK_DATA` and `SEEK_HOLE`. On platforms
+    /// without them, this performs a regular copy.
+    pub fn copy_sparse<P2: AsRef<Path>, P3: AsRef<Path>>(&self, from: P2, to: P3) -> Result<u64> {
+        with_paths! {
+            from = self / from,
+            to = self / to
+        }
+        timed("copy_sparse", self.0.as_ref(), from, || {
+            sys::copy_sparse(from, to)
+        })
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+mod sys {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{Error, Result};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::{FileExt, OpenOptionsExt, PermissionsExt};
+    use std::path::Path;
+
+    pub(super) fn copy_sparse(from: &Path, to: &Path) -> Result<u64> {
+        let source = File::open(from)?;
+        let meta = source.metadata()?;
+        let len = meta.len();
+        let dest = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(meta.permissions().mode())
+            .open(to)?;
+        // Extending the file leaves a hole, which data regions are written
+        // into
+        dest.set_len(len)?;
+
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut pos = 0;
+        while pos < len {
+            let Some(start) = seek(&source, pos, libc::SEEK_DATA)? else {
+                break;
+            };
+            let end = seek(&source, start, libc::SEEK_HOLE)?
+                .unwrap_or(len)
+                .min(len);
+            let mut offset = start;
+            while offset < end {
+                let want = buf.len().min((end - offset) as usize);
+                let n = source.read_at(&mut buf[..want], offset)?;
+                if n == 0 {
+                    break;
+                }
+                dest.write_all_at(&buf[..n], offset)?;
+                offset += n as u64;
+            }
+            pos = end;
+        }
+        fs::set_permissions(to, meta.permissions())?;
+        Ok(len)
+    }
+
+    /// Finds the next data region or hole at or after `offset`. Returns
+    /// `None` if there's no more data. Filesystems which can't report holes
+    /// treat the whole file as data.
+    fn seek(file: &File, offset: u64, whence: libc::c_int) -> Result<Option<u64>> {
+        let found = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) };
+        if found >= 0 {
+            return Ok(Some(found as u64));
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::ENXIO) => Ok(None),
+            Some(libc::EINVAL) if whence == libc::SEEK_DATA => Ok(Some(offset)),
+            Some(libc::EINVAL) => Ok(None),
+            _ => Err(err),
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+mod sys {
+    use std::fs;
+    use std::io::Result;
+    use std::path::Path;
+
+    pub(super) fn copy_sparse(from: &Path, to: &Path) -> Result<u64> {
+        fs::copy(from, to)
+    }
+}
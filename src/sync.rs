@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::compare::files_equal;
+use crate::{Dir, FileKind, SnapshotEntry, SymlinkPolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// How [`Dir::sync_to`] decides whether a file in the destination is
+/// already up to date
+pub enum SyncCompare {
+    /// Files are up to date if they have the same size and identical
+    /// contents, like `rsync --checksum`. This is exact, but reads every file
+    /// present on both sides.
+    #[default]
+    Contents,
+    /// Files are up to date if they have the same size and modification
+    /// time, like `rsync`'s default. This never reads file contents, so
+    /// repeated syncs of mostly unchanged trees are nearly instant, but it
+    /// relies on modification times being preserved, as they are by
+    /// [`SyncOptions::preserve_times`].
+    SizeAndMtime,
+}
+
+#[derive(Debug, Clone)]
+/// Options for [`Dir::sync_to`]
+pub struct SyncOptions {
+    delete: bool,
+    preserve_permissions: bool,
+    preserve_times: bool,
+    compare: SyncCompare,
+    symlinks: SymlinkPolicy,
+}
+
+impl Default for SyncOptions {
+    fn default() -> SyncOptions {
+        SyncOptions::new()
+    }
+}
+
+impl SyncOptions {
+    /// Creates the default options: extraneous files in the destination
+    /// are kept, permissions and modification times are preserved, files
+    /// are compared by [`SyncCompare::Contents`], and symbolic links are
+    /// recreated with the same target
+    pub fn new() -> SyncOptions {
+        SyncOptions {
+            delete: false,
+            preserve_permissions: true,
+            preserve_times: true,
+            compare: SyncCompare::Contents,
+            symlinks: SymlinkPolicy::Preserve,
+        }
+    }
+
+    /// Sets how files are checked to be up to date
+    pub fn compare(mut self, compare: SyncCompare) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Sets whether copied files are given the same modification time as
+    /// the source
+    pub fn preserve_times(mut self, preserve: bool) -> Self {
+        self.preserve_times = preserve;
+        self
+    }
+
+    /// Sets whether entries in the destination which don't exist in the
+    /// source are deleted
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Sets whether copied files have the same permissions as the source.
+    /// Otherwise, they're created with the default permissions.
+    pub fn preserve_permissions(mut self, preserve: bool) -> Self {
+        self.preserve_permissions = preserve;
+        self
+    }
+
+    /// Sets how symbolic links in the source are synced. Followed links are
+    /// synced as what they point to, and an error is returned if they form a
+    /// cycle. Skipped links are left out of the sync entirely, and links in
+    /// the destination are never deleted.
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The outcome of a call to [`Dir::sync_to`]. Paths are relative to the
+/// working directories.
+pub struct SyncReport {
+    /// Files and symlinks copied because they were missing or differed
+    pub copied: Vec<PathBuf>,
+    /// Directories created in the destination
+    pub created_dirs: Vec<PathBuf>,
+    /// Entries deleted from the destination
+    pub deleted: Vec<PathBuf>,
+    /// The number of files which were already up to date
+    pub unchanged: usize,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Makes the tree at `path` in `dest` match the tree at `path` in this
+    /// working directory, like a local `rsync`.
+    ///
+    /// Files which are missing from the destination, or whose contents
+    /// differ, are copied. Symbolic links are recreated with the same
+    /// target, unless [`SyncOptions::symlinks`] says otherwise. Entries
+    /// whose type differs between the two trees are replaced. With
+    /// [`SyncOptions::delete`], entries in the destination which don't
+    /// exist in the source are removed.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered. Changes made
+    /// before the error are not undone.
+    pub fn sync_to<Q, P2>(
+        &self,
+        dest: &Dir<Q>,
+        path: P2,
+        options: &SyncOptions,
+    ) -> Result<SyncReport>
+    where
+        Q: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let source = self.take_snapshot_with(path, false, options.symlinks)?;
+        let mut report = SyncReport::default();
+
+        dest.create_dir_all(path)?;
+        for entry in &source {
+            let existing = dest
+                .symlink_metadata_opt(&entry.path)?
+                .map(|meta| FileKind::from(meta.file_type()));
+            if existing.is_some_and(|kind| kind != entry.kind) {
+                dest.remove_any(&entry.path)?;
+            }
+            let exists = existing == Some(entry.kind);
+
+            match entry.kind {
+                FileKind::Dir => {
+                    if !exists {
+                        dest.create_dir(&entry.path)?;
+                        report.created_dirs.push(entry.path.clone());
+                    }
+                }
+                FileKind::File => {
+                    if exists && self.up_to_date(dest, entry, options.compare)? {
+                        report.unchanged += 1;
+                    } else {
+                        let modified = entry.modified.filter(|_| options.preserve_times);
+                        let to = dest.join(&entry.path);
+                        copy_file(&self.join(&entry.path), &to, modified, options)?;
+                        report.copied.push(entry.path.clone());
+                    }
+                }
+                FileKind::Symlink => {
+                    let target = self.read_link(&entry.path)?;
+                    if exists && dest.read_link(&entry.path)? == target {
+                        report.unchanged += 1;
+                    } else {
+                        if exists {
+                            dest.remove_file(&entry.path)?;
+                        }
+                        symlink(&target, &dest.join(&entry.path), &self.join(&entry.path))?;
+                        report.copied.push(entry.path.clone());
+                    }
+                }
+                // Sockets, devices, and the like can't be copied
+                FileKind::Other => {}
+            }
+        }
+
+        if options.delete {
+            let keep: HashSet<&Path> = source.iter().map(|entry| entry.path.as_path()).collect();
+            let mut extraneous = Vec::new();
+            for entry in dest.walk(path) {
+                let entry = entry?;
+                if options.symlinks == SymlinkPolicy::Skip && entry.file_type()?.is_symlink() {
+                    continue;
+                }
+                let parent_deleted = extraneous
+                    .iter()
+                    .any(|dir: &PathBuf| entry.path().starts_with(dir));
+                if !keep.contains(entry.path()) && !parent_deleted {
+                    extraneous.push(entry.path().to_owned());
+                }
+            }
+            for path in extraneous {
+                dest.remove_any(&path)?;
+                report.deleted.push(path);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks whether the copy of a source file in `dest` is up to date
+    fn up_to_date<Q: AsRef<Path>>(
+        &self,
+        dest: &Dir<Q>,
+        entry: &SnapshotEntry,
+        compare: SyncCompare,
+    ) -> Result<bool> {
+        let meta = dest.metadata(&entry.path)?;
+        if meta.len() != entry.size {
+            return Ok(false);
+        }
+        match compare {
+            SyncCompare::Contents => files_equal(&self.join(&entry.path), &dest.join(&entry.path)),
+            SyncCompare::SizeAndMtime => {
+                Ok(entry.modified.is_some() && meta.modified().ok() == entry.modified)
+            }
+        }
+    }
+
+    /// Like [`Dir::symlink_metadata`], but returns `Ok(None)` if the path does not exist
+    pub(crate) fn symlink_metadata_opt<P2: AsRef<Path>>(
+        &self,
+        path: P2,
+    ) -> Result<Option<fs::Metadata>> {
+        crate::not_found_to_none(self.symlink_metadata(path))
+    }
+
+    /// Removes a file, symlink, or directory tree
+    pub(crate) fn remove_any<P2: AsRef<Path>>(&self, path: P2) -> Result<()> {
+        let path = path.as_ref();
+        if self.symlink_metadata(path)?.is_dir() {
+            self.remove_dir_all(path)
+        } else {
+            self.remove_file(path)
+        }
+    }
+}
+
+/// Copies a file's contents, then its modification time and permissions if
+/// they're given. The time is set through the handle the contents were
+/// written with, since the permissions may leave the copy read-only.
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    modified: Option<SystemTime>,
+    options: &SyncOptions,
+) -> Result<u64> {
+    let mut source = File::open(from)?;
+    let mut dest = File::create(to)?;
+    let bytes = io::copy(&mut source, &mut dest)?;
+    if let Some(modified) = modified {
+        dest.set_modified(modified)?;
+    }
+    if options.preserve_permissions {
+        dest.set_permissions(source.metadata()?.permissions())?;
+    }
+    Ok(bytes)
+}
+
+/// Creates a symlink at `link` pointing to `target`. On Windows, whether
+/// a file or directory link is created depends on the type of `original`.
+pub(crate) fn symlink(target: &Path, link: &Path, original: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let _ = original;
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        if fs::metadata(original).is_ok_and(|meta| meta.is_dir()) {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (target, link, original);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "symbolic links are not supported on this platform",
+        ))
+    }
+}
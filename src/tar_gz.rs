@@ -0,0 +1,53 @@
+use std::io::{Read, Result, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::extract::unpack_tar;
+use crate::Dir;
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Packs the tree at `path` into a gzip-compressed tar archive, written
+    /// to `writer` as it's produced. Returns the writer once the archive is
+    /// complete.
+    ///
+    /// As with [`Dir::zip`], entries are stored in sorted order with their
+    /// paths relative to the working directory, and symbolic links are
+    /// stored as links.
+    pub fn tar_gz<P2: AsRef<Path>, W: Write>(&self, path: P2, writer: W) -> Result<W> {
+        let mut entries = Vec::new();
+        for entry in self.walk(path) {
+            entries.push(entry?.path().to_owned());
+        }
+        entries.sort();
+
+        let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        tar.follow_symlinks(false);
+        for path in entries {
+            tar.append_path_with_name(self.join(&path), &path)?;
+        }
+        tar.into_inner()?.finish()
+    }
+
+    /// Extracts a gzip-compressed tar archive read from `reader` into the
+    /// directory `dest`, creating it if necessary. Returns the number of
+    /// files and links extracted.
+    ///
+    /// This applies the same checks as [`Dir::extract_tar`], but since the
+    /// archive is streamed, each entry is checked just before it's written,
+    /// so entries preceding an unsafe one will already have been extracted.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind
+    /// [`ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) if the
+    /// archive is malformed or contains an unsafe entry.
+    pub fn untar_gz<R: Read, P2: AsRef<Path>>(&self, reader: R, dest: P2) -> Result<u64> {
+        unpack_tar(GzDecoder::new(reader), &self.join(dest))
+    }
+}
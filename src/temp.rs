@@ -0,0 +1,235 @@
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::hash::BuildHasher;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Dir;
+
+/// A uniquely named file inside a working directory, which is removed when
+/// it's dropped unless it's moved into place with [`TempFile::persist`].
+///
+/// Created with [`Dir::temp_file`].
+#[derive(Debug)]
+pub struct TempFile {
+    file: Option<File>,
+    path: PathBuf,
+    root: PathBuf,
+}
+
+/// The error returned when [`TempFile::persist`] fails, which gives back the
+/// temporary file so that it can be moved somewhere else, or dropped to
+/// remove it. Converts into the underlying [`std::io::Error`].
+#[derive(Debug)]
+pub struct PersistError {
+    /// The error which caused the rename to fail
+    pub error: Error,
+    /// The temporary file, which is still removed on drop
+    pub file: TempFile,
+}
+
+/// A uniquely named directory inside a working directory, usable as a
+/// [`Dir`] through [`Deref`], which is removed along with its contents when
+/// it's dropped, unless [`TempDir::keep`] is called.
+///
+/// Created with [`Dir::temp_dir_in`].
+#[derive(Debug)]
+pub struct TempDir {
+    dir: Option<Dir<PathBuf>>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a new, uniquely named file, open for reading and writing.
+    /// Its name starts with `prefix`, which may include parent directories
+    /// (such as `uploads/part-`), which must already exist.
+    ///
+    /// The file is removed when the returned [`TempFile`] is dropped. Once
+    /// it's completely written, it can be moved to its final location with
+    /// [`TempFile::persist`].
+    pub fn temp_file<P2: AsRef<Path>>(&self, prefix: P2) -> Result<TempFile> {
+        let prefix = prefix.as_ref();
+        loop {
+            let path = self.join(unique_name(prefix));
+            match File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => {
+                    return Ok(TempFile {
+                        file: Some(file),
+                        path,
+                        root: self.0.as_ref().to_owned(),
+                    })
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates a new, uniquely named directory inside `parent`, with a name
+    /// starting with `prefix`, like `mkdtemp(3)`. `parent` must already
+    /// exist.
+    ///
+    /// The directory and everything in it is removed when the returned
+    /// [`TempDir`] is dropped. Use [`TempDir::keep`] to keep it instead.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let jobs = Dir::new("/var/lib/myapp/jobs");
+    /// let scratch = jobs.temp_dir_in("", "job-")?;
+    /// scratch.write("input.json", "{}")?;
+    /// // `scratch` is removed here
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn temp_dir_in<P2: AsRef<Path>, P3: AsRef<Path>>(
+        &self,
+        parent: P2,
+        prefix: P3,
+    ) -> Result<TempDir> {
+        let prefix = parent.as_ref().join(prefix);
+        loop {
+            let path = self.join(unique_name(&prefix));
+            match fs::create_dir(&path) {
+                Ok(()) => {
+                    return Ok(TempDir {
+                        dir: Some(Dir(path)),
+                    })
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl TempDir {
+    /// Keeps the directory instead of removing it on drop, returning it
+    pub fn keep(mut self) -> Dir<PathBuf> {
+        self.dir.take().unwrap()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Dir<PathBuf>;
+
+    fn deref(&self) -> &Dir<PathBuf> {
+        self.dir.as_ref().unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Some(dir) = self.dir.take() {
+            let _ = fs::remove_dir_all(dir.0);
+        }
+    }
+}
+
+impl TempFile {
+    /// Returns the current path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the underlying file
+    pub fn as_file(&self) -> &File {
+        self.file.as_ref().unwrap()
+    }
+
+    /// Returns the underlying file, mutably
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.file.as_mut().unwrap()
+    }
+
+    /// Atomically moves the file to `path`, relative to the working
+    /// directory it was created in, replacing anything already there. The
+    /// file is no longer removed on drop, and is returned, still open.
+    ///
+    /// # Errors
+    ///
+    /// If the rename fails, the temporary file is returned in the
+    /// [`PersistError`], and is still removed on drop.
+    pub fn persist<P: AsRef<Path>>(mut self, path: P) -> std::result::Result<File, PersistError> {
+        let dest = Dir(&self.root).join(path);
+        match fs::rename(&self.path, &dest) {
+            Ok(()) => Ok(self.file.take().unwrap()),
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+}
+
+impl Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to persist {:?}: {}", self.file.path, self.error)
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PersistError> for Error {
+    fn from(err: PersistError) -> Error {
+        err.error
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.as_file_mut().read(buf)
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.as_file_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.as_file_mut().flush()
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.as_file_mut().seek(pos)
+    }
+}
+
+/// Appends a suffix to `prefix` which is unlikely to be taken: the process
+/// id, a counter, and some randomness, so names aren't predictable
+pub(crate) fn unique_name(prefix: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let random = RandomState::new().hash_one(count) as u32;
+    let mut name = OsString::from(prefix.as_os_str());
+    name.push(format!("{}-{count}-{random:08x}", std::process::id()));
+    PathBuf::from(name)
+}
@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{Dir, FileKind};
+
+/// Assertions for tests which build their output into a working directory.
+/// Each panics with a description of what was found instead, such as a
+/// line-by-line diff of a file's contents.
+///
+/// These are only available with the `testing` feature, which is meant to be
+/// enabled for `[dev-dependencies]`:
+///
+/// ```toml
+/// [dev-dependencies]
+/// working_dir = { version = "0.1", features = ["testing"] }
+/// ```
+///
+/// ```no_run
+/// # use working_dir::Dir;
+/// let out = Dir::new("target/test-output");
+/// out.assert_exists("report.txt");
+/// out.assert_content("report.txt", "2 passed\n");
+/// out.assert_tree_matches([
+///     ("report.txt", "2 passed\n"),
+///     ("logs/run.log", "ok\n"),
+/// ]);
+/// ```
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Panics unless there's a file, directory, or symbolic link at `path`
+    #[track_caller]
+    pub fn assert_exists<P2: AsRef<Path>>(&self, path: P2) {
+        let path = path.as_ref();
+        if let Err(err) = self.symlink_metadata(path) {
+            let mut message = format!("expected {path:?} to exist in {:?}: {err}", self.0.as_ref());
+            describe_parent(self, path, &mut message);
+            panic!("{message}");
+        }
+    }
+
+    /// Panics if there's a file, directory, or symbolic link at `path`
+    #[track_caller]
+    pub fn assert_not_exists<P2: AsRef<Path>>(&self, path: P2) {
+        let path = path.as_ref();
+        if let Ok(meta) = self.symlink_metadata(path) {
+            panic!(
+                "expected {path:?} not to exist in {:?}, but found a {}",
+                self.0.as_ref(),
+                describe_kind(FileKind::from(meta.file_type())),
+            );
+        }
+    }
+
+    /// Panics unless the file at `path` contains exactly `expected`. If both
+    /// are text, the message includes a line-by-line diff.
+    #[track_caller]
+    pub fn assert_content<P2: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P2, expected: C) {
+        let path = path.as_ref();
+        let actual = match self.read(path) {
+            Ok(actual) => actual,
+            Err(err) => {
+                let mut message = format!("couldn't read {path:?} in {:?}: {err}", self.0.as_ref());
+                describe_parent(self, path, &mut message);
+                panic!("{message}");
+            }
+        };
+        if let Some(diff) = content_diff(expected.as_ref(), &actual) {
+            panic!("contents of {path:?} don't match (-expected +actual):\n{diff}");
+        }
+    }
+
+    /// Panics unless the files beneath the working directory are exactly
+    /// those in `spec`, with the given contents. Directories are implied by
+    /// the paths of the files in them, so empty directories are ignored.
+    ///
+    /// On failure, the message lists every missing and unexpected file, and
+    /// a diff for every file whose contents don't match.
+    #[track_caller]
+    pub fn assert_tree_matches<I, P2, C>(&self, spec: I)
+    where
+        I: IntoIterator<Item = (P2, C)>,
+        P2: AsRef<Path>,
+        C: AsRef<[u8]>,
+    {
+        let expected: BTreeMap<PathBuf, C> = spec
+            .into_iter()
+            .map(|(path, contents)| (path.as_ref().to_owned(), contents))
+            .collect();
+        let mut actual = BTreeSet::new();
+        for entry in self.walk("") {
+            let entry = entry.unwrap_or_else(|err| {
+                panic!("couldn't walk {:?}: {err}", self.0.as_ref());
+            });
+            if entry.file_type().is_ok_and(|kind| !kind.is_dir()) {
+                actual.insert(entry.path().to_owned());
+            }
+        }
+
+        let mut message = String::new();
+        for path in expected.keys().filter(|path| !actual.contains(*path)) {
+            let _ = writeln!(message, "missing: {}", path.display());
+        }
+        for path in actual.iter().filter(|path| !expected.contains_key(*path)) {
+            let _ = writeln!(message, "unexpected: {}", path.display());
+        }
+        for (path, contents) in &expected {
+            if !actual.contains(path) {
+                continue;
+            }
+            let diff = match self.read(path) {
+                Ok(found) => content_diff(contents.as_ref(), &found),
+                Err(err) => Some(format!("  couldn't read: {err}\n")),
+            };
+            if let Some(diff) = diff {
+                let _ = write!(message, "differs: {}\n{diff}", path.display());
+            }
+        }
+        if !message.is_empty() {
+            panic!(
+                "the tree at {:?} doesn't match (-expected +actual):\n{message}",
+                self.0.as_ref()
+            );
+        }
+    }
+}
+
+/// Appends the entries of the parent directory of `path` to `message`, or
+/// the nearest ancestor which does exist
+fn describe_parent<P: AsRef<Path>>(dir: &Dir<P>, path: &Path, message: &mut String) {
+    let mut parent = path.parent();
+    while let Some(ancestor) = parent {
+        if let Ok(paths) = dir.read_dir_paths(ancestor) {
+            let names: Vec<_> = paths
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy())
+                .collect();
+            let shown = if ancestor.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                ancestor
+            };
+            let _ = write!(
+                message,
+                "\n{} contains: [{}]",
+                shown.display(),
+                names.join(", ")
+            );
+            return;
+        }
+        parent = ancestor.parent();
+    }
+}
+
+fn describe_kind(kind: FileKind) -> &'static str {
+    match kind {
+        FileKind::File => "file",
+        FileKind::Dir => "directory",
+        FileKind::Symlink => "symbolic link",
+        FileKind::Other => "special file",
+    }
+}
+
+/// Describes how `actual` differs from `expected`, returning `None` if they
+/// don't
+fn content_diff(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    match (std::str::from_utf8(expected), std::str::from_utf8(actual)) {
+        (Ok(expected), Ok(actual)) => Some(line_diff(expected, actual)),
+        _ => {
+            let at = expected
+                .iter()
+                .zip(actual)
+                .position(|(a, b)| a != b)
+                .unwrap_or(expected.len().min(actual.len()));
+            Some(format!(
+                "  expected {} bytes, found {} bytes, first differing at byte {at}\n",
+                expected.len(),
+                actual.len()
+            ))
+        }
+    }
+}
+
+/// Diffs two strings line by line, through their longest common
+/// subsequence of lines. Lines are prefixed with `-` if they're only in
+/// `expected`, `+` if they're only in `actual`, and a space if in both.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let (old, new): (Vec<_>, Vec<_>) = (expected.lines().collect(), actual.lines().collect());
+
+    // lcs[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            let _ = writeln!(diff, "   {}", old[i]);
+            (i, j) = (i + 1, j + 1);
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            let _ = writeln!(diff, "  -{}", old[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(diff, "  +{}", new[j]);
+            j += 1;
+        }
+    }
+    if expected.ends_with('\n') != actual.ends_with('\n') {
+        let _ = writeln!(diff, "  (the trailing newlines differ)");
+    }
+    diff
+}
@@ -1,6 +1,14 @@
 
 use crate::Dir;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Creates an empty working directory for a test, outside of the source tree
+fn scratch(name: &str) -> Dir<PathBuf> {
+    let root = std::env::temp_dir().join(format!("working_dir-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    Dir(root)
+}
 
 #[test]
 fn hello_test() -> std::io::Result<()> {
@@ -33,3 +41,2654 @@ fn hello_test() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn space_test() -> std::io::Result<()> {
+    let cwd = Dir(".");
+
+    let available = cwd.available_space()?;
+    let total = cwd.total_space()?;
+
+    assert!(total > 0);
+    assert!(available <= total);
+
+    Ok(())
+}
+
+#[test]
+fn maintenance_test() -> std::io::Result<()> {
+    use crate::Priority;
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    let cwd = scratch("maintenance");
+    let order = RefCell::new(Vec::new());
+    let hour = Duration::from_secs(60 * 60);
+    let record = |name: &'static str| {
+        let order = &order;
+        move |_: &Dir<PathBuf>| {
+            order.borrow_mut().push(name);
+            Ok(())
+        }
+    };
+
+    let report = cwd
+        .maintenance()
+        .max_tasks_per_run(2)
+        .task("prune", hour, Priority::Low, record("prune"))
+        .task("gc", hour, Priority::High, record("gc"))
+        .task("verify", hour, Priority::Normal, record("verify"))
+        .run()?;
+
+    assert_eq!(*order.borrow(), ["gc", "verify"]);
+    assert_eq!(report.deferred, ["prune"]);
+    assert!(cwd.exists(".maintenance"));
+
+    // Only the deferred task is due now
+    let report = cwd
+        .maintenance()
+        .task("prune", hour, Priority::Low, |_| Ok(()))
+        .task("gc", hour, Priority::High, |_| Ok(()))
+        .task("verify", hour, Priority::Normal, |_| Ok(()))
+        .run()?;
+    assert_eq!(report.completed, ["prune"]);
+
+    let err = cwd.maintenance().task("two\nlines", hour, Priority::Low, |_| Ok(())).run().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
+
+#[test]
+fn stats_test() -> std::io::Result<()> {
+    let cwd = scratch("stats");
+    cwd.create_dir_all("tree/a/b")?;
+    cwd.write("tree/one.txt", "1")?;
+    cwd.write("tree/a/two.txt", "22")?;
+    cwd.write("tree/a/b/three.txt", "333")?;
+
+    let stats = cwd.stats("tree")?;
+    assert_eq!(stats.files, 3);
+    assert_eq!(stats.dirs, 2);
+    assert_eq!(stats.symlinks, 0);
+    assert_eq!(stats.total_bytes, 6);
+    assert_eq!(stats.max_depth, 3);
+    assert_eq!(stats.deepest.as_deref(), Some(Path::new("tree/a/b/three.txt")));
+
+    Ok(())
+}
+
+#[test]
+fn read_opt_test() -> std::io::Result<()> {
+    let cwd = scratch("read_opt");
+    cwd.write("present.txt", "here")?;
+
+    assert_eq!(cwd.read_opt("present.txt")?.as_deref(), Some(&b"here"[..]));
+    assert_eq!(cwd.read_to_string_opt("present.txt")?.as_deref(), Some("here"));
+    assert!(cwd.metadata_opt("present.txt")?.is_some());
+
+    assert_eq!(cwd.read_opt("missing.txt")?, None);
+    assert_eq!(cwd.read_to_string_opt("missing.txt")?, None);
+    assert!(cwd.metadata_opt("missing.txt")?.is_none());
+
+    // Errors other than NotFound still propagate
+    assert!(cwd.read_to_string_opt(".").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn largest_newest_test() -> std::io::Result<()> {
+    use std::time::{Duration, SystemTime};
+
+    let cwd = scratch("largest_newest");
+    cwd.create_dir_all("logs/old")?;
+    cwd.write("logs/small.log", "a")?;
+    cwd.write("logs/old/big.log", "aaaaaaaa")?;
+    cwd.write("logs/medium.log", "aaaa")?;
+
+    let largest = cwd.largest_files("logs", 2)?;
+    assert_eq!(
+        largest,
+        [(PathBuf::from("logs/old/big.log"), 8), (PathBuf::from("logs/medium.log"), 4)]
+    );
+
+    let future = SystemTime::now() + Duration::from_secs(60);
+    std::fs::File::options()
+        .write(true)
+        .open(cwd.join("logs/small.log"))?
+        .set_modified(future)?;
+    let newest = cwd.newest_file("logs")?;
+    assert_eq!(newest, Some((PathBuf::from("logs/small.log"), future)));
+
+    Ok(())
+}
+
+#[test]
+fn write_if_changed_test() -> std::io::Result<()> {
+    let cwd = scratch("write_if_changed");
+
+    assert!(cwd.write_if_changed("gen.rs", "fn a() {}")?);
+    assert!(!cwd.write_if_changed("gen.rs", "fn a() {}")?);
+    assert!(cwd.write_if_changed("gen.rs", "fn b() {}")?);
+    assert_eq!(cwd.read_to_string("gen.rs")?, "fn b() {}");
+
+    Ok(())
+}
+
+#[test]
+fn framed_test() -> std::io::Result<()> {
+    use std::io::ErrorKind;
+
+    let cwd = scratch("framed");
+    let chunks: [&[u8]; 3] = [b"first", b"", b"third chunk"];
+    cwd.write_framed("state.bin", chunks)?;
+    assert_eq!(cwd.read_framed("state.bin")?, chunks);
+
+    // Flipping a byte inside a chunk is detected
+    let mut bytes = cwd.read("state.bin")?;
+    bytes[10] ^= 0xFF;
+    cwd.write("corrupt.bin", &bytes)?;
+    assert_eq!(cwd.read_framed("corrupt.bin").unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // So is dropping the end of the file
+    let bytes = cwd.read("state.bin")?;
+    cwd.write("truncated.bin", &bytes[..bytes.len() - 6])?;
+    assert_eq!(cwd.read_framed("truncated.bin").unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // A corrupt length is rejected before anything is allocated for it
+    let mut bytes = b"WDF1".to_vec();
+    bytes.extend_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+    cwd.write("huge.bin", &bytes)?;
+    assert_eq!(cwd.read_framed("huge.bin").unwrap_err().kind(), ErrorKind::InvalidData);
+
+    Ok(())
+}
+
+#[test]
+fn crc32_test() {
+    assert_eq!(crate::crc32::crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn read_dir_sorted_test() -> std::io::Result<()> {
+    let cwd = scratch("read_dir_sorted");
+    for name in ["c", "a", "b"] {
+        cwd.write(name, name)?;
+    }
+
+    let names: Vec<_> = cwd.read_dir_sorted(".")?.iter().map(|e| e.file_name()).collect();
+    assert_eq!(names, ["a", "b", "c"]);
+    assert_eq!(cwd.read_dir_paths(".")?, [cwd.join("./a"), cwd.join("./b"), cwd.join("./c")]);
+
+    Ok(())
+}
+
+#[test]
+fn relative_entries_test() -> std::io::Result<()> {
+    let cwd = scratch("relative_entries");
+    cwd.create_dir_all("src/bin")?;
+    cwd.write("src/lib.rs", "")?;
+    cwd.write("src/bin/main.rs", "")?;
+
+    let mut listed: Vec<_> = cwd
+        .read_dir_relative("./src")?
+        .map(|entry| entry.map(|e| e.path().to_owned()))
+        .collect::<std::io::Result<_>>()?;
+    listed.sort();
+    assert_eq!(listed, [Path::new("src/bin"), Path::new("src/lib.rs")]);
+
+    let mut walked = Vec::new();
+    for entry in cwd.walk("src") {
+        let entry = entry?;
+        assert_eq!(entry.abs_path(), cwd.join(entry.path()));
+        walked.push((entry.path().to_owned(), entry.depth()));
+    }
+    walked.sort();
+    assert_eq!(
+        walked,
+        [
+            (PathBuf::from("src/bin"), 1),
+            (PathBuf::from("src/bin/main.rs"), 2),
+            (PathBuf::from("src/lib.rs"), 1)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn move_set_test() -> std::io::Result<()> {
+    use crate::MoveSet;
+
+    let root = scratch("move_set");
+    let staging = Dir(root.join("staging"));
+    let release = Dir(root.join("release"));
+    staging.create_dir_all("bin")?;
+    staging.write("bin/app", "app")?;
+    staging.write("readme", "readme")?;
+    std::fs::create_dir_all(&release)?;
+    release.write("readme", "old readme")?;
+
+    // The conflicting destination means nothing is moved
+    let mut moves = MoveSet::new();
+    moves.add(&staging, &release, "bin/app").add(&staging, &release, "readme");
+    assert!(moves.execute().is_err());
+    assert!(staging.exists("bin/app"));
+    assert!(!release.exists("bin"));
+
+    release.remove_file("readme")?;
+    let mut moves = MoveSet::new();
+    moves.add(&staging, &release, "bin/app").add(&staging, &release, "readme");
+    moves.execute()?;
+    assert_eq!(release.read_to_string("bin/app")?, "app");
+    assert_eq!(release.read_to_string("readme")?, "readme");
+    assert!(!staging.exists("bin/app"));
+
+    Ok(())
+}
+
+#[test]
+fn evict_lru_test() -> std::io::Result<()> {
+    use std::time::{Duration, SystemTime};
+
+    let cwd = scratch("evict_lru");
+    cwd.create_dir_all("cache/nested")?;
+    let old = SystemTime::now() - Duration::from_secs(60 * 60);
+    for (i, name) in ["cache/a", "cache/nested/b", "cache/c"].into_iter().enumerate() {
+        cwd.write(name, [0u8; 10])?;
+        let time = old + Duration::from_secs(i as u64 * 60);
+        let file = std::fs::File::options().write(true).open(cwd.join(name))?;
+        file.set_times(std::fs::FileTimes::new().set_accessed(time).set_modified(time))?;
+    }
+    // "a" is the oldest, but was recently used per the sidecar
+    cwd.track_access();
+    cwd.read("cache/a")?;
+    cwd.read_to_string("cache/a")?;
+    cwd.read_with_capacity("cache/a", 10)?;
+    cwd.read_max("cache/a", 10)?;
+    cwd.read_max_truncating("cache/a", 5)?;
+    cwd.untrack_access();
+    assert_eq!(cwd.read_to_string("cache/.access-log")?.lines().count(), 5);
+
+    let report = cwd.evict_lru("cache", 15)?;
+    assert_eq!(report.removed, [PathBuf::from("cache/nested/b"), PathBuf::from("cache/c")]);
+    assert_eq!(report.bytes_freed, 20);
+    assert_eq!(report.bytes_remaining, 10);
+    assert!(cwd.exists("cache/a"));
+    // The sidecar is compacted to one entry per remaining file
+    assert_eq!(cwd.read_to_string("cache/.access-log")?.lines().count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn read_dir_with_metadata_test() -> std::io::Result<()> {
+    let cwd = scratch("read_dir_with_metadata");
+    cwd.create_dir("sub")?;
+    cwd.write("file", "12345")?;
+
+    for entry in cwd.read_dir_with_metadata(".")? {
+        let (entry, meta) = entry?;
+        match entry.file_name().to_str() {
+            Some("sub") => assert!(meta.is_dir()),
+            Some("file") => assert_eq!(meta.len(), 5),
+            name => panic!("unexpected entry {name:?}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "asset-cache")]
+#[test]
+fn asset_cache_test() -> std::io::Result<()> {
+    use crate::AssetCache;
+    use std::cell::Cell;
+
+    let cache = AssetCache::new(scratch("asset_cache"), 1 << 20);
+    let fetches = Cell::new(0);
+    let fetch = || {
+        fetches.set(fetches.get() + 1);
+        Ok(vec![7u8; 4096])
+    };
+
+    assert_eq!(cache.get("texture", fetch)?, vec![7u8; 4096]);
+    assert_eq!(cache.get("texture", fetch)?, vec![7u8; 4096]);
+    assert_eq!(fetches.get(), 1);
+
+    // Each hit is logged once, even if the cache's root tracks accesses
+    let logged = || -> std::io::Result<usize> {
+        let mut lines = 0;
+        for entry in cache.dir().walk("") {
+            let entry = entry?;
+            if entry.file_name() == crate::ACCESS_LOG {
+                lines += cache.dir().read_to_string(entry.path())?.lines().count();
+            }
+        }
+        Ok(lines)
+    };
+    assert_eq!(logged()?, 2);
+    cache.dir().track_access();
+    cache.load("texture")?;
+    cache.dir().untrack_access();
+    assert_eq!(logged()?, 3);
+
+    // The asset is stored compressed
+    assert!(cache.dir().stats("")?.total_bytes < 4096);
+
+    assert!(cache.remove("texture")?);
+    assert_eq!(cache.load("texture")?, None);
+
+    // Assets which don't compress are evicted once they exceed the budget
+    let cache = AssetCache::new(scratch("asset_cache_budget"), 6000).compression_level(0);
+    for key in ["a", "b", "c"] {
+        cache.insert(key, &[key.as_bytes()[0]; 2048])?;
+    }
+    let mut stored = 0;
+    for entry in cache.dir().walk("") {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && entry.file_name() != crate::ACCESS_LOG {
+            assert!(!entry.file_name().to_string_lossy().contains(".tmp"));
+            stored += entry.metadata()?.len();
+        }
+    }
+    assert!(stored > 0 && stored <= 6000, "{stored}");
+
+    Ok(())
+}
+
+#[test]
+fn snapshot_test() -> std::io::Result<()> {
+    use crate::FileKind;
+
+    let cwd = scratch("snapshot");
+    cwd.create_dir_all("out/sub")?;
+    cwd.write("out/a.txt", "aaa")?;
+    cwd.write("out/sub/b.txt", "bbb")?;
+
+    let before = cwd.snapshot_hashed("out")?;
+    let paths: Vec<_> = before.iter().map(|e| e.path.as_path()).collect();
+    assert_eq!(paths, ["out/a.txt", "out/sub", "out/sub/b.txt"].map(Path::new));
+    assert_eq!(before.get("out/sub").map(|e| e.kind), Some(FileKind::Dir));
+    assert_eq!(before.get("out/a.txt").and_then(|e| e.crc32), Some(crate::crc32::crc32(b"aaa")));
+
+    cwd.write("out/a.txt", "AAA")?;
+    cwd.remove_file("out/sub/b.txt")?;
+    cwd.write("out/c.txt", "c")?;
+
+    let diff = before.diff(&cwd.snapshot_hashed("out")?);
+    assert_eq!(diff.added, [Path::new("out/c.txt")]);
+    assert_eq!(diff.removed, [Path::new("out/sub/b.txt")]);
+    assert_eq!(diff.modified, [Path::new("out/a.txt")]);
+
+    // Broken links can't be followed, so they're recorded as they are
+    #[cfg(unix)]
+    {
+        use crate::SymlinkPolicy;
+        std::os::unix::fs::symlink("missing", cwd.join("out/broken"))?;
+        let followed = cwd.take_snapshot_with(Path::new("out"), false, SymlinkPolicy::Follow)?;
+        assert_eq!(followed.get("out/broken").map(|e| e.kind), Some(FileKind::Symlink));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn init_once_test() -> std::io::Result<()> {
+    use std::io::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let cwd = scratch("init_once");
+    let runs = AtomicUsize::new(0);
+
+    // A failed attempt leaves partial state behind, which is cleaned up
+    // before the retry
+    let failed = cwd.init_once("setup", |dir| {
+        dir.write("partial", "")?;
+        Err(Error::other("interrupted"))
+    });
+    assert!(failed.is_err());
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                cwd.init_once_with_cleanup(
+                    "setup",
+                    |dir| dir.remove_file("partial"),
+                    |dir| {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        dir.write("config", "ready")
+                    },
+                )
+                .unwrap();
+            });
+        }
+    });
+
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert!(!cwd.exists("partial"));
+    assert_eq!(cwd.read_to_string("config")?, "ready");
+    assert!(!cwd.init_once("setup", |_| unreachable!())?);
+
+    Ok(())
+}
+
+#[test]
+fn display_tree_test() -> std::io::Result<()> {
+    let cwd = scratch("display_tree");
+    cwd.create_dir_all("src/bin")?;
+    cwd.write("src/bin/main.rs", "")?;
+    cwd.write("src/lib.rs", "")?;
+    cwd.write("src/.hidden", "")?;
+
+    let expected = "\
+src
+├── bin
+│   └── main.rs
+└── lib.rs
+
+1 directory, 2 files";
+    assert_eq!(cwd.display_tree("src").to_string(), expected);
+
+    let expected = "\
+src
+├── .hidden
+├── bin
+└── lib.rs
+
+1 directory, 2 files";
+    assert_eq!(
+        cwd.display_tree("src")
+            .max_depth(1)
+            .show_hidden(true)
+            .to_string(),
+        expected
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flush_registry_test() -> std::io::Result<()> {
+    use crate::FlushRegistry;
+    use std::sync::{Arc, Mutex};
+
+    let cwd = scratch("flush_registry");
+    cwd.write("segment", "data")?;
+    cwd.write("index", "segment")?;
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let registry = FlushRegistry::new(cwd);
+    registry.register_path("segment", "segment", &[])?;
+    registry.register_path("index", "index", &["segment", "footer"])?;
+    for name in ["footer", "manifest"] {
+        let order = order.clone();
+        let deps: &[&str] = if name == "footer" { &["segment"] } else { &["index"] };
+        registry.register(name, deps, move || {
+            order.lock().unwrap().push(name);
+            Ok(())
+        })?;
+    }
+    registry.shutdown()?;
+    assert_eq!(*order.lock().unwrap(), ["footer", "manifest"]);
+
+    // Cycles are rejected
+    registry.register("a", &["b"], || Ok(()))?;
+    registry.register("b", &["a"], || Ok(()))?;
+    assert!(registry.flush_all().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn dirs_equal_test() -> std::io::Result<()> {
+    use crate::dirs_equal;
+
+    let root = scratch("dirs_equal");
+    let (a, b) = (Dir(root.join("a")), Dir(root.join("b")));
+    for dir in [&a, &b] {
+        dir.create_dir_all("sub")?;
+        dir.write("sub/file", "same")?;
+        dir.write("top", "same")?;
+    }
+    assert!(dirs_equal(&a, &b)?);
+
+    // Same size, different contents
+    b.write("sub/file", "diff")?;
+    assert!(!dirs_equal(&a, &b)?);
+
+    b.write("sub/file", "same")?;
+    b.write("extra", "")?;
+    assert!(!dirs_equal(&a, &b)?);
+
+    Ok(())
+}
+
+#[test]
+fn slow_op_test() -> std::io::Result<()> {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let cwd = scratch("slow_op");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let recorder = seen.clone();
+    cwd.set_slow_op_threshold(Duration::ZERO, move |slow| {
+        recorder.lock().unwrap().push(slow.op);
+    });
+    cwd.write("file", "contents")?;
+    cwd.read("file")?;
+    // Other working directories aren't reported
+    scratch("slow_op_other").write("file", "contents")?;
+    cwd.clear_slow_op_threshold();
+    cwd.remove_file("file")?;
+
+    assert_eq!(*seen.lock().unwrap(), ["write", "read"]);
+    Ok(())
+}
+
+#[test]
+fn sync_to_test() -> std::io::Result<()> {
+    use crate::{dirs_equal, SyncOptions};
+
+    let root = scratch("sync_to");
+    let (src, dest) = (Dir(root.join("src")), Dir(root.join("dest")));
+    src.create_dir_all("site/css")?;
+    src.write("site/index.html", "<html>")?;
+    src.write("site/css/main.css", "body {}")?;
+    dest.create_dir_all("site/old")?;
+    dest.write("site/index.html", "<HTML>")?;
+    dest.write("site/old/page.html", "")?;
+
+    let report = src.sync_to(&dest, "site", &SyncOptions::new())?;
+    assert_eq!(report.created_dirs, [Path::new("site/css")]);
+    assert_eq!(report.copied.len(), 2);
+    assert!(dest.exists("site/old/page.html"));
+
+    let report = src.sync_to(&dest, "site", &SyncOptions::new().delete(true))?;
+    assert_eq!(report.unchanged, 2);
+    assert_eq!(report.deleted, [Path::new("site/old")]);
+    assert!(dirs_equal(&src, &dest)?);
+
+    Ok(())
+}
+
+#[test]
+fn stats_counters_test() -> std::io::Result<()> {
+    let cwd = scratch("stats_counters");
+    assert_eq!(cwd.stats_counters(), None);
+
+    cwd.track_stats();
+    cwd.write("a", "12345")?;
+    cwd.read("a")?;
+    cwd.read_to_string("a")?;
+    cwd.read_with_capacity("a", 5)?;
+    cwd.read_max("a", 5)?;
+    cwd.read_max_truncating("a", 2)?;
+    cwd.rename("a", "b")?;
+    cwd.rename_noreplace("b", "c")?;
+    cwd.create_dir("d")?;
+    cwd.swap("c", "d")?;
+    cwd.sync_file("d")?;
+    cwd.sync_root()?;
+    cwd.copy_with_progress("d", "e", |_, _| {})?;
+    cwd.copy_cancellable("d", "f", &crate::CancelToken::new(), |_, _| {})?;
+    assert!(cwd.read("a").is_err());
+
+    let stats = cwd.stats_counters().unwrap();
+    assert_eq!(stats.writes.calls, 3);
+    assert_eq!(stats.writes.bytes, 5);
+    assert_eq!(stats.reads.calls, 6);
+    assert_eq!(stats.reads.bytes, 22);
+    assert_eq!(stats.reads.errors, 1);
+    assert_eq!(stats.renames.calls, 3);
+    assert_eq!(stats.copies.calls, 2);
+    assert_eq!(stats.errors(), 1);
+
+    cwd.untrack_stats();
+    assert_eq!(cwd.stats_counters(), None);
+    Ok(())
+}
+
+#[test]
+fn incremental_sync_test() -> std::io::Result<()> {
+    use crate::{SyncCompare, SyncOptions};
+
+    let root = scratch("incremental_sync");
+    let (src, dest) = (Dir(root.join("src")), Dir(root.join("dest")));
+    src.create_dir_all("data")?;
+    src.write("data/a", "aaaa")?;
+    src.write("data/b", "bbbb")?;
+
+    let fast = SyncOptions::new().compare(SyncCompare::SizeAndMtime);
+    assert_eq!(src.sync_to(&dest, "data", &fast)?.copied.len(), 2);
+    assert_eq!(src.sync_to(&dest, "data", &fast)?.unchanged, 2);
+
+    // A same-size edit with a preserved mtime fools the fast mode, but
+    // not a comparison of contents
+    let modified = src.metadata("data/a")?.modified()?;
+    dest.write("data/a", "AAAA")?;
+    std::fs::File::options()
+        .write(true)
+        .open(dest.join("data/a"))?
+        .set_modified(modified)?;
+    assert_eq!(src.sync_to(&dest, "data", &fast)?.unchanged, 2);
+    let report = src.sync_to(&dest, "data", &SyncOptions::new())?;
+    assert_eq!(report.copied, [Path::new("data/a")]);
+    assert_eq!(dest.read_to_string("data/a")?, "aaaa");
+
+    Ok(())
+}
+
+#[test]
+fn sync_read_only_test() -> std::io::Result<()> {
+    use crate::SyncOptions;
+
+    let root = scratch("sync_read_only");
+    let (src, dest) = (Dir(root.join("src")), Dir(root.join("dest")));
+    src.create_dir_all("")?;
+    src.write("locked", "contents")?;
+    let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+    std::fs::File::options()
+        .write(true)
+        .open(src.join("locked"))?
+        .set_modified(modified)?;
+    let mut permissions = src.metadata("locked")?.permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(src.join("locked"), permissions)?;
+
+    src.sync_to(&dest, "", &SyncOptions::new())?;
+    assert_eq!(dest.read_to_string("locked")?, "contents");
+    assert_eq!(dest.metadata("locked")?.modified()?, modified);
+    assert!(dest.metadata("locked")?.permissions().readonly());
+
+    Ok(())
+}
+
+#[test]
+fn merge_from_test() -> std::io::Result<()> {
+    use crate::ConflictPolicy;
+
+    let root = scratch("merge_from");
+    let (plugin, app) = (Dir(root.join("plugin")), Dir(root.join("app")));
+    plugin.create_dir_all("config")?;
+    plugin.write("config/theme.toml", "dark")?;
+    plugin.write("config/plugin.toml", "enabled")?;
+    app.create_dir_all("config")?;
+    app.write("config/theme.toml", "light")?;
+
+    assert!(app.merge_from(&plugin, "config", ConflictPolicy::Error).is_err());
+    assert!(!app.exists("config/plugin.toml"));
+
+    let report = app.merge_from(&plugin, "config", ConflictPolicy::KeepExisting)?;
+    assert_eq!(report.copied, [Path::new("config/plugin.toml")]);
+    assert_eq!(report.skipped, [Path::new("config/theme.toml")]);
+    assert_eq!(app.read_to_string("config/theme.toml")?, "light");
+
+    let report = app.merge_from(&plugin, "config", ConflictPolicy::Rename)?;
+    assert_eq!(report.renamed.len(), 2);
+    assert_eq!(app.read_to_string("config/theme-1.toml")?, "dark");
+
+    app.merge_from(&plugin, "config", ConflictPolicy::Overwrite)?;
+    assert_eq!(app.read_to_string("config/theme.toml")?, "dark");
+
+    Ok(())
+}
+
+#[test]
+fn manifest_test() -> std::io::Result<()> {
+    use crate::{HashAlgo, Manifest};
+
+    let cwd = scratch("manifest");
+    cwd.create_dir_all("release/bin")?;
+    cwd.write("release/bin/app", "hello")?;
+    cwd.write("release/readme", "hello world")?;
+
+    let manifest = cwd.build_manifest("release", HashAlgo::Crc32)?;
+    let app = manifest.get("release/bin/app").unwrap();
+    assert_eq!(app.len, 5);
+    assert_eq!(app.digest.to_hex(), "3610a686");
+    assert_eq!(manifest.entries.len(), 2);
+
+    let text = manifest.to_text()?;
+    assert!(text.starts_with("# working_dir manifest crc32\n# root: release\n"));
+    assert_eq!(Manifest::parse(&text)?, manifest);
+
+    Ok(())
+}
+
+#[test]
+fn verify_manifest_test() -> std::io::Result<()> {
+    use crate::HashAlgo;
+
+    let cwd = scratch("verify_manifest");
+    cwd.create_dir_all("install")?;
+    for name in ["a", "b", "c"] {
+        cwd.write(Path::new("install").join(name), name)?;
+    }
+    let manifest = cwd.build_manifest("install", HashAlgo::Crc32)?;
+    assert!(cwd.verify_manifest(&manifest)?.is_ok());
+
+    cwd.remove_file("install/a")?;
+    cwd.write("install/b", "B")?;
+    cwd.write("install/d", "d")?;
+    let report = cwd.verify_manifest(&manifest)?;
+    assert_eq!(report.missing, [Path::new("install/a")]);
+    assert_eq!(report.corrupted, [Path::new("install/b")]);
+    assert_eq!(report.extra, [Path::new("install/d")]);
+    assert_eq!(report.verified, 1);
+
+    Ok(())
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn hash_file_test() -> std::io::Result<()> {
+    use crate::HashAlgo;
+
+    let cwd = scratch("hash_file");
+    cwd.write("abc", "abc")?;
+    assert_eq!(
+        cwd.hash_file("abc", HashAlgo::Sha256)?.to_hex(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        cwd.hash_file("abc", HashAlgo::Blake3)?.to_hex(),
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+    );
+    assert_eq!(cwd.hash_file("abc", HashAlgo::Crc32)?.to_hex(), "352441c2");
+    assert_eq!("blake3".parse::<HashAlgo>()?, HashAlgo::Blake3);
+
+    Ok(())
+}
+
+#[test]
+fn dedup_hardlink_test() -> std::io::Result<()> {
+    let cwd = scratch("dedup_hardlink");
+    cwd.create_dir_all("cache/nested")?;
+    cwd.write("cache/a", "same contents")?;
+    cwd.write("cache/nested/b", "same contents")?;
+    cwd.write("cache/c", "different!!!!")?;
+    cwd.write("cache/d", "same contents")?;
+
+    let report = cwd.dedup_hardlink("cache")?;
+    assert_eq!(
+        report.linked,
+        [
+            (PathBuf::from("cache/d"), PathBuf::from("cache/a")),
+            (PathBuf::from("cache/nested/b"), PathBuf::from("cache/a")),
+        ]
+    );
+    assert_eq!(report.bytes_saved, 26);
+    assert_eq!(cwd.read_to_string("cache/nested/b")?, "same contents");
+
+    // Everything is already linked
+    assert_eq!(cwd.dedup_hardlink("cache")?.linked, []);
+
+    // A duplicate's data is only freed once all of its links are replaced
+    cwd.create_dir_all("links")?;
+    cwd.write("links/a", "same contents")?;
+    cwd.write("links/b", "same contents")?;
+    cwd.hard_link("links/b", "links/c")?;
+    cwd.write("links/d", "same contents")?;
+    cwd.hard_link("links/d", "outside")?;
+    let report = cwd.dedup_hardlink("links")?;
+    assert_eq!(report.linked.len(), 3);
+    #[cfg(unix)]
+    assert_eq!(report.bytes_saved, 13);
+    assert_eq!(cwd.read_dir_paths("links")?.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn copy_reflink_test() -> std::io::Result<()> {
+    let cwd = scratch("copy_reflink");
+    cwd.write("big.bin", vec![7u8; 100_000])?;
+    cwd.write("copy.bin", "stale")?;
+
+    // Whether or not the filesystem supports cloning, the copy succeeds
+    cwd.copy_reflink("big.bin", "copy.bin")?;
+    assert_eq!(cwd.read("copy.bin")?, vec![7u8; 100_000]);
+
+    cwd.write("big.bin", "changed")?;
+    assert_eq!(cwd.read("copy.bin")?.len(), 100_000);
+
+    Ok(())
+}
+
+#[test]
+fn copy_sparse_test() -> std::io::Result<()> {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    let cwd = scratch("copy_sparse");
+    let mut file = cwd.open("disk.img", OpenOptions::new().write(true).create(true))?;
+    file.write_all(b"head")?;
+    file.seek(SeekFrom::Start(8 << 20))?;
+    file.write_all(b"tail")?;
+    drop(file);
+
+    assert_eq!(cwd.copy_sparse("disk.img", "copy.img")?, (8 << 20) + 4);
+    let copy = cwd.read("copy.img")?;
+    assert_eq!(copy, cwd.read("disk.img")?);
+    assert!(copy.starts_with(b"head") && copy.ends_with(b"tail"));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let blocks = |path| cwd.metadata(path).map(|meta| meta.blocks());
+        assert!(blocks("copy.img")? <= blocks("disk.img")? + 8);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn copy_options_test() -> std::io::Result<()> {
+    use crate::{ConflictPolicy, CopyOptions};
+
+    let cwd = scratch("copy_options");
+    cwd.create_dir_all("src/sub")?;
+    cwd.write("src/a", "new a")?;
+    cwd.write("src/sub/b", "new b")?;
+    cwd.create_dir_all("dst")?;
+    cwd.write("dst/a", "old a")?;
+
+    let skip = CopyOptions::new().skip_existing(true);
+    let report = cwd.copy_dir_with("src", "dst", &skip)?;
+    assert_eq!(report.skipped, [Path::new("dst/a")]);
+    assert_eq!(report.copied, [Path::new("dst/sub/b")]);
+    assert_eq!(cwd.read_to_string("dst/a")?, "old a");
+
+    let error = CopyOptions::new().conflict(ConflictPolicy::Error);
+    assert!(cwd.copy_with("src/a", "dst/a", &error).is_err());
+    assert!(cwd.copy_dir_with("src", "other", &error).is_ok());
+
+    let times = CopyOptions::new().preserve_times(true);
+    let report = cwd.copy_with("src/a", "dst/a", &times)?;
+    assert_eq!(report.overwritten, [Path::new("dst/a")]);
+    assert_eq!(report.bytes, 5);
+    assert_eq!(
+        cwd.metadata("dst/a")?.modified()?,
+        cwd.metadata("src/a")?.modified()?
+    );
+    assert!(cwd.copy_with("src", "dir", &times).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn copy_with_progress_test() -> std::io::Result<()> {
+    use crate::CancelToken;
+    use std::io::ErrorKind;
+
+    let cwd = scratch("copy_with_progress");
+    cwd.write("big.bin", vec![1u8; 200_000])?;
+
+    let mut calls = Vec::new();
+    let copied = cwd.copy_with_progress("big.bin", "copy.bin", |copied, total| {
+        calls.push((copied, total))
+    })?;
+    assert_eq!(copied, 200_000);
+    assert_eq!(calls.first(), Some(&(0, 200_000)));
+    assert_eq!(calls.last(), Some(&(200_000, 200_000)));
+    assert_eq!(cwd.read("copy.bin")?.len(), 200_000);
+
+    let cancel = CancelToken::new();
+    let result = cwd.copy_cancellable("big.bin", "partial.bin", &cancel, |copied, _| {
+        if copied > 0 {
+            cancel.cancel();
+        }
+    });
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Interrupted);
+    assert!(!cwd.exists("partial.bin"));
+
+    Ok(())
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn zip_test() -> std::io::Result<()> {
+    use crate::ZipOptions;
+
+    let cwd = scratch("zip");
+    cwd.create_dir_all("site/empty")?;
+    cwd.create_dir_all("site/.git")?;
+    cwd.write("site/index.html", "<html></html>")?;
+    cwd.write("site/.git/HEAD", "ref: main")?;
+
+    let options = ZipOptions::new()
+        .compression_level(9)
+        .filter(|path| !path.ends_with(".git"));
+    assert_eq!(cwd.zip("site", "site/site.zip", &options)?, 1);
+
+    let archive = zip::ZipArchive::new(cwd.open_readonly("site/site.zip")?)?;
+    let mut names = archive.file_names().collect::<Result<Vec<_>, _>>()?;
+    names.sort();
+    assert_eq!(names, ["site/empty/", "site/index.html"]);
+
+    Ok(())
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn extract_zip_test() -> std::io::Result<()> {
+    use crate::ZipOptions;
+    use std::io::Write;
+
+    let cwd = scratch("extract_zip");
+    cwd.create_dir_all("src/nested")?;
+    cwd.write("src/nested/file.txt", "contents")?;
+    cwd.zip("src", "good.zip", &ZipOptions::new())?;
+    assert_eq!(cwd.extract_zip("good.zip", "out")?, 1);
+    assert_eq!(cwd.read_to_string("out/src/nested/file.txt")?, "contents");
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(cwd.join("evil.zip"))?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("fine.txt", options)?;
+    zip.write_all(b"fine")?;
+    zip.start_file("../escaped.txt", options)?;
+    zip.write_all(b"evil")?;
+    zip.finish()?;
+
+    let err = cwd.extract_zip("evil.zip", "evil").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // Nothing is written, not even the safe entries
+    assert!(!cwd.exists("evil/fine.txt"));
+    assert!(!cwd.exists("escaped.txt"));
+
+    Ok(())
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn extract_tar_test() -> std::io::Result<()> {
+    let cwd = scratch("extract_tar");
+
+    let mut tar = tar::Builder::new(std::fs::File::create(cwd.join("good.tar"))?);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(5);
+    header.set_mode(0o644);
+    tar.append_data(&mut header, "dir/hello.txt", &b"hello"[..])?;
+    tar.into_inner()?;
+    assert_eq!(cwd.extract_tar("good.tar", "out")?, 1);
+    assert_eq!(cwd.read_to_string("out/dir/hello.txt")?, "hello");
+
+    let mut tar = tar::Builder::new(std::fs::File::create(cwd.join("evil.tar"))?);
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    tar.append_link(&mut header, "link", "../../etc")?;
+    tar.into_inner()?;
+    let err = cwd.extract_tar("evil.tar", "evil").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(!cwd.exists("evil"));
+
+    Ok(())
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn extract_zip_symlink_chain_test() -> std::io::Result<()> {
+    let cwd = scratch("extract_zip_symlink_chain");
+
+    // Each `deep` leads back to the destination, so the three `..` climb
+    // out of it, even though the target looks like it stays beneath it
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(cwd.join("evil.zip"))?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip.add_symlink("deep", ".", options)?;
+    zip.add_symlink("x", "deep/deep/deep/../../..", options)?;
+    zip.finish()?;
+
+    let err = cwd.extract_zip("evil.zip", "evil").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(!cwd.exists("evil/deep"));
+
+    Ok(())
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn extract_tar_symlink_chain_test() -> std::io::Result<()> {
+    let cwd = scratch("extract_tar_symlink_chain");
+
+    // The same escape, with the link it goes through extracted second
+    let mut tar = tar::Builder::new(std::fs::File::create(cwd.join("evil.tar"))?);
+    for (link, target) in [("x", "deep/deep/deep/../../.."), ("deep", ".")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        tar.append_link(&mut header, link, target)?;
+    }
+    tar.into_inner()?;
+
+    let err = cwd.extract_tar("evil.tar", "evil").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(!cwd.exists("evil"));
+
+    Ok(())
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn tar_gz_test() -> std::io::Result<()> {
+    let cwd = scratch("tar_gz");
+    cwd.create_dir_all("data/sub")?;
+    cwd.write("data/a.txt", "a")?;
+    cwd.write("data/sub/b.txt", "b")?;
+
+    let archive = cwd.tar_gz("data", Vec::new())?;
+    assert_eq!(&archive[..2], [0x1f, 0x8b]);
+
+    assert_eq!(cwd.untar_gz(&archive[..], "restored")?, 2);
+    assert_eq!(cwd.read_to_string("restored/data/a.txt")?, "a");
+    assert_eq!(cwd.read_to_string("restored/data/sub/b.txt")?, "b");
+
+    Ok(())
+}
+
+#[test]
+fn backup_test() -> std::io::Result<()> {
+    let cwd = scratch("backup");
+    assert_eq!(cwd.backup("config", 2)?, None);
+
+    for version in ["v1", "v2", "v3", "v4"] {
+        cwd.backup("config", 2)?;
+        cwd.write("config", version)?;
+    }
+    assert_eq!(cwd.read_to_string("config")?, "v4");
+    assert_eq!(cwd.read_to_string("config.1")?, "v3");
+    assert_eq!(cwd.read_to_string("config.2")?, "v2");
+    assert!(!cwd.exists("config.3"));
+
+    Ok(())
+}
+
+#[test]
+fn rotating_writer_test() -> std::io::Result<()> {
+    use std::io::Write;
+
+    let cwd = scratch("rotating_writer");
+    let mut log = cwd.rotating_writer("logs/app.log", 10, 2)?;
+    for line in ["one\n", "two\n", "three\n", "four\n", "five\n", "six\n"] {
+        log.write_all(line.as_bytes())?;
+    }
+    log.flush()?;
+
+    assert_eq!(cwd.read_to_string("logs/app.log")?, "six\n");
+    assert_eq!(cwd.read_to_string("logs/app.log.1")?, "four\nfive\n");
+    assert_eq!(cwd.read_to_string("logs/app.log.2")?, "three\n");
+    assert!(!cwd.exists("logs/app.log.3"));
+
+    Ok(())
+}
+
+#[cfg(feature = "trash")]
+#[test]
+fn remove_to_trash_test() {
+    // Trashing real files would touch the user's trash, so only the error
+    // path is exercised here
+    let cwd = scratch("remove_to_trash");
+    let err = cwd.remove_to_trash("missing").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn create_new_test() -> std::io::Result<()> {
+    use std::io::Write;
+
+    let cwd = scratch("create_new");
+    cwd.create_new("lockfile")?.write_all(b"1234")?;
+    let err = cwd.create_new("lockfile").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(cwd.read_to_string("lockfile")?, "1234");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn open_in_test() -> std::io::Result<()> {
+    use crate::OpenOptionsDirExt;
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let cwd = scratch("open_in");
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .mode(0o600)
+        .open_in(&cwd, "secret")?;
+    assert_eq!(cwd.metadata("secret")?.permissions().mode() & 0o777, 0o600);
+
+    std::os::unix::fs::symlink("secret", cwd.join("link"))?;
+    let nofollow = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open_in(&cwd, "link");
+    assert!(nofollow.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn options_test() -> std::io::Result<()> {
+    use std::io::Write;
+
+    let cwd = scratch("options");
+    for line in ["a\n", "b\n"] {
+        let mut log = cwd.options().append(true).create(true).open("log.txt")?;
+        log.write_all(line.as_bytes())?;
+    }
+    assert_eq!(cwd.read_to_string("log.txt")?, "a\nb\n");
+    assert!(cwd.options().read(true).open("missing.txt").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn set_len_test() -> std::io::Result<()> {
+    let cwd = scratch("set_len");
+    cwd.write("log", "0123456789")?;
+    cwd.set_len("log", 4)?;
+    assert_eq!(cwd.read_to_string("log")?, "0123");
+    cwd.set_len("log", 6)?;
+    assert_eq!(cwd.read("log")?, b"0123\0\0");
+    cwd.truncate("log")?;
+    assert_eq!(cwd.metadata("log")?.len(), 0);
+    assert!(cwd.truncate("missing").is_err());
+    assert!(!cwd.exists("missing"));
+
+    Ok(())
+}
+
+#[test]
+fn allocate_test() -> std::io::Result<()> {
+    let cwd = scratch("allocate");
+    cwd.allocate("download.part", 1 << 20)?;
+    assert_eq!(cwd.metadata("download.part")?.len(), 1 << 20);
+
+    // Existing contents are kept, and files are never shrunk
+    cwd.write("small", "abc")?;
+    cwd.allocate("small", 2)?;
+    assert_eq!(cwd.read_to_string("small")?, "abc");
+    cwd.allocate("small", 8)?;
+    assert_eq!(cwd.read("small")?, b"abc\0\0\0\0\0");
+
+    Ok(())
+}
+
+#[test]
+fn sync_test() -> std::io::Result<()> {
+    let cwd = scratch("sync");
+    cwd.write("state", "1")?;
+    cwd.sync_file("state")?;
+    cwd.sync_root()?;
+    assert!(cwd.sync_file("missing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn write_atomic_test() -> std::io::Result<()> {
+    let cwd = scratch("write_atomic");
+    cwd.write_atomic("config", "v1")?;
+    cwd.write_atomic_durable("config", "v2")?;
+    assert_eq!(cwd.read_to_string("config")?, "v2");
+    // No temporary files are left behind
+    assert_eq!(cwd.read_dir_paths("")?.len(), 1);
+    assert!(cwd.write_atomic("missing/config", "v1").is_err());
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn create_anonymous_test() -> std::io::Result<()> {
+    use std::io::Write;
+
+    let cwd = scratch("create_anonymous");
+    cwd.create_dir_all("out")?;
+    let mut file = match cwd.create_anonymous("out") {
+        Ok(file) => file,
+        // Not every filesystem supports O_TMPFILE
+        Err(_) => return Ok(()),
+    };
+    file.write_all(b"complete")?;
+    assert!(cwd.read_dir_paths("out")?.is_empty());
+    file.persist("result")?;
+    assert_eq!(cwd.read_to_string("out/result")?, "complete");
+
+    let err = cwd.create_anonymous("out")?.persist("result").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(cwd.read_dir_paths("out")?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn temp_file_test() -> std::io::Result<()> {
+    use std::io::Write;
+
+    let cwd = scratch("temp_file");
+    let mut discarded = cwd.temp_file("upload-")?;
+    discarded.write_all(b"partial")?;
+    assert!(discarded.path().starts_with(&cwd));
+    let path = discarded.path().to_owned();
+    drop(discarded);
+    assert!(!path.exists());
+
+    let mut kept = cwd.temp_file("upload-")?;
+    kept.write_all(b"complete")?;
+    // A failed persist gives the file back
+    let err = kept.persist("missing/final.bin").unwrap_err();
+    assert_eq!(err.error.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.file.path().exists());
+    err.file.persist("final.bin")?;
+    assert_eq!(cwd.read_to_string("final.bin")?, "complete");
+    assert_eq!(cwd.read_dir_paths("")?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn temp_dir_in_test() -> std::io::Result<()> {
+    let cwd = scratch("temp_dir_in");
+    cwd.create_dir_all("jobs")?;
+
+    let job = cwd.temp_dir_in("jobs", "job-")?;
+    job.write("input", "data")?;
+    let path = job.join("");
+    assert!(path.starts_with(cwd.join("jobs")));
+    drop(job);
+    assert!(!path.exists());
+
+    let kept = cwd.temp_dir_in("jobs", "job-")?.keep();
+    assert!(kept.exists(""));
+
+    Ok(())
+}
+
+#[test]
+fn read_at_test() -> std::io::Result<()> {
+    let cwd = scratch("read_at");
+    cwd.write("records", "aaaabbbbcccc")?;
+    assert_eq!(cwd.read_at("records", 4, 4)?, b"bbbb");
+    assert_eq!(cwd.read_at("records", 10, 4)?, b"cc");
+
+    cwd.write_at("records", 4, b"BBBB")?;
+    cwd.write_at("records", 12, b"dddd")?;
+    assert_eq!(cwd.read_to_string("records")?, "aaaaBBBBccccdddd");
+    assert!(cwd.write_at("missing", 0, b"x").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn read_chunks_test() -> std::io::Result<()> {
+    let cwd = scratch("read_chunks");
+    cwd.write("data", "0123456789")?;
+
+    let chunks = cwd
+        .read_chunks("data", 4)?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    assert_eq!(chunks, [&b"0123"[..], b"4567", b"89"]);
+
+    let mut reader = cwd.read_chunks("data", 4)?;
+    let mut buf = [0u8; 6];
+    assert_eq!(reader.read_into(&mut buf)?, 6);
+    assert_eq!(reader.read_into(&mut buf)?, 4);
+    assert_eq!(reader.read_into(&mut buf)?, 0);
+    assert!(cwd.read_chunks("data", 0).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn read_max_test() -> std::io::Result<()> {
+    let cwd = scratch("read_max");
+    cwd.write("small", "12345")?;
+    assert_eq!(cwd.read_max("small", 5)?, b"12345");
+    let err = cwd.read_max("small", 4).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(cwd.read_max_truncating("small", 5)?, b"12345");
+    assert_eq!(cwd.read_max_truncating("small", 4)?, b"1234");
+    assert_eq!(cwd.read_max_truncating("small", 0)?, b"");
+
+    Ok(())
+}
+
+#[test]
+fn read_with_capacity_test() -> std::io::Result<()> {
+    let cwd = scratch("read_with_capacity");
+    cwd.write("data", "0123456789")?;
+    for entry in cwd.walk("") {
+        let entry = entry?;
+        let len = entry.metadata()?.len() as usize;
+        let contents = cwd.read_with_capacity(entry.path(), len)?;
+        assert_eq!(contents, b"0123456789");
+        assert_eq!(contents.capacity(), len);
+    }
+    // A hint which is too small still reads the whole file
+    assert_eq!(cwd.read_with_capacity("data", 2)?.len(), 10);
+    // Without a hint, the buffers are sized from the metadata
+    assert_eq!(cwd.read("data")?.capacity(), 10);
+    assert_eq!(cwd.read_to_string("data")?.capacity(), 10);
+
+    Ok(())
+}
+
+#[test]
+fn path_scratch_test() -> std::io::Result<()> {
+    let cwd = scratch("path_scratch");
+    cwd.write("a.txt", "aaa")?;
+    cwd.create_dir("sub")?;
+    cwd.write("sub/b.txt", "bb")?;
+
+    let mut scratch = cwd.with_buffer(PathBuf::new());
+    assert_eq!(scratch.join("sub/b.txt"), cwd.join("sub/b.txt"));
+    assert_eq!(scratch.metadata("a.txt")?.len(), 3);
+    assert_eq!(scratch.read("sub/b.txt")?, b"bb");
+    assert!(scratch.exists("sub"));
+    assert!(!scratch.exists("missing"));
+
+    // The allocation survives being handed back and reused
+    let buf = scratch.into_buffer();
+    let capacity = buf.capacity();
+    let mut scratch = cwd.with_buffer(buf);
+    assert!(scratch.symlink_metadata("a.txt")?.is_file());
+    assert_eq!(scratch.into_buffer().capacity(), capacity);
+
+    Ok(())
+}
+
+#[test]
+fn walk_stat_test() -> std::io::Result<()> {
+    let cwd = scratch("walk_stat");
+    cwd.create_dir("sub")?;
+    cwd.write("sub/data", "0123456789")?;
+
+    let mut stats = Vec::new();
+    for entry in cwd.walk_stat("") {
+        let (entry, stat) = entry?;
+        let meta = entry.metadata()?;
+        assert_eq!(stat.modified, meta.modified().ok());
+        stats.push((entry.path().to_owned(), stat.kind, stat.size));
+    }
+    stats.sort();
+    assert_eq!(stats[0].0, Path::new("sub"));
+    assert_eq!(stats[0].1, crate::FileKind::Dir);
+    assert_eq!(
+        stats[1],
+        (PathBuf::from("sub/data"), crate::FileKind::File, 10)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bulk_test() -> std::io::Result<()> {
+    let cwd = scratch("bulk");
+    cwd.create_dir("src")?;
+    let names: Vec<String> = (0..300).map(|i| format!("src/{i}.txt")).collect();
+    cwd.write_many(names.iter().map(|name| (name, name.repeat(3))))?;
+    cwd.write("src/empty", "")?;
+
+    let contents = cwd.read_many(&names)?;
+    assert_eq!(contents.len(), 300);
+    assert_eq!(contents[7], b"src/7.txt".repeat(3));
+    assert_eq!(cwd.read_many(["src/empty"])?, vec![Vec::new()]);
+
+    let bytes = cwd.copy_dir_batched("src", "dest")?;
+    let expected: usize = names.iter().map(|name| name.len() * 3).sum();
+    assert_eq!(bytes, expected as u64);
+    assert_eq!(cwd.read_to_string("dest/299.txt")?, "src/299.txt".repeat(3));
+    assert!(cwd.exists("dest/empty"));
+
+    assert!(cwd.read_many(["src/0.txt", "missing"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn custom_filesystem_test() -> std::io::Result<()> {
+    use crate::{FileSystem, FileSystemMut, StdFs};
+    use std::cell::RefCell;
+    use std::io::Result;
+
+    /// Records the paths which were written, and otherwise defers to std
+    #[derive(Default)]
+    struct Recording(RefCell<Vec<PathBuf>>);
+
+    impl FileSystem for Recording {
+        type File = std::fs::File;
+        type Metadata = std::fs::Metadata;
+        type ReadDir = std::fs::ReadDir;
+
+        fn open_readonly(&self, path: &Path) -> Result<Self::File> {
+            StdFs.open_readonly(path)
+        }
+        fn try_exists(&self, path: &Path) -> Result<bool> {
+            StdFs.try_exists(path)
+        }
+        fn metadata(&self, path: &Path) -> Result<Self::Metadata> {
+            StdFs.metadata(path)
+        }
+        fn symlink_metadata(&self, path: &Path) -> Result<Self::Metadata> {
+            StdFs.symlink_metadata(path)
+        }
+        fn read(&self, path: &Path) -> Result<Vec<u8>> {
+            StdFs.read(path)
+        }
+        fn read_to_string(&self, path: &Path) -> Result<String> {
+            StdFs.read_to_string(path)
+        }
+        fn read_dir(&self, path: &Path) -> Result<Self::ReadDir> {
+            StdFs.read_dir(path)
+        }
+    }
+
+    impl FileSystemMut for Recording {
+        fn create_new(&self, path: &Path) -> Result<Self::File> {
+            StdFs.create_new(path)
+        }
+        fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+            self.0.borrow_mut().push(path.to_owned());
+            StdFs.write(path, contents)
+        }
+        fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+            StdFs.copy(from, to)
+        }
+        fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+            StdFs.rename(from, to)
+        }
+        fn create_dir(&self, path: &Path) -> Result<()> {
+            StdFs.create_dir(path)
+        }
+        fn create_dir_all(&self, path: &Path) -> Result<()> {
+            StdFs.create_dir_all(path)
+        }
+        fn remove_file(&self, path: &Path) -> Result<()> {
+            StdFs.remove_file(path)
+        }
+        fn remove_dir(&self, path: &Path) -> Result<()> {
+            StdFs.remove_dir(path)
+        }
+        fn remove_dir_all(&self, path: &Path) -> Result<()> {
+            StdFs.remove_dir_all(path)
+        }
+    }
+
+    let root = scratch("custom_filesystem").0;
+    let cwd = Dir::with_fs(&root, Recording::default());
+    cwd.create_parents("a/b.txt")?;
+    cwd.write("a/b.txt", "hello")?;
+    assert_eq!(cwd.read_to_string("a/b.txt")?, "hello");
+    assert!(cwd.metadata("a")?.is_dir());
+    assert!(cwd.exists("a/b.txt"));
+    assert_eq!(*cwd.fs().0.borrow(), [root.join("a/b.txt")]);
+
+    Ok(())
+}
+
+#[test]
+fn memory_fs_test() -> std::io::Result<()> {
+    use crate::MemoryFs;
+    use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+    let fs = MemoryFs::new();
+    let cwd = Dir::with_fs("/project", fs.clone());
+    assert_eq!(
+        cwd.write("a.txt", "x").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    cwd.create_dir_all("")?;
+    cwd.create_dir_all("src/bin")?;
+    cwd.write("src/main.rs", "fn main() {}")?;
+    cwd.write("src/lib.rs", "")?;
+    assert_eq!(cwd.read_to_string("src/main.rs")?, "fn main() {}");
+    assert_eq!(cwd.metadata("src/main.rs")?.len(), 12);
+    assert!(cwd.metadata("src")?.is_dir());
+    assert!(cwd.exists("src/../src/lib.rs"));
+    assert!(!std::path::Path::new("/project/src/main.rs").exists());
+
+    // Listings are sorted, and modification times advance on each change
+    let names: Vec<_> = cwd
+        .read_dir("src")?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    assert_eq!(names, ["bin", "lib.rs", "main.rs"]);
+    let before = cwd.metadata("src/lib.rs")?.modified()?;
+    cwd.write("src/lib.rs", "pub mod x;")?;
+    assert!(cwd.metadata("src/lib.rs")?.modified()? > before);
+
+    // Open files are views of the same contents
+    let mut file = cwd.create_new("src/bin/tool.rs")?;
+    file.write_all(b"hello world")?;
+    assert_eq!(
+        cwd.create_new("src/bin/tool.rs").unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+    let mut file = cwd.open_readonly("src/bin/tool.rs")?;
+    file.seek(SeekFrom::Start(6))?;
+    let mut rest = String::new();
+    file.read_to_string(&mut rest)?;
+    assert_eq!(rest, "world");
+    assert!(file.write(b"!").is_err());
+
+    cwd.rename("src", "lib")?;
+    assert!(!cwd.exists("src"));
+    assert_eq!(cwd.read("lib/bin/tool.rs")?, b"hello world");
+    assert_eq!(cwd.copy("lib/main.rs", "main.rs")?, 12);
+    assert_eq!(
+        cwd.remove_dir("lib").unwrap_err().kind(),
+        ErrorKind::DirectoryNotEmpty
+    );
+    cwd.remove_dir_all("lib")?;
+    assert!(!cwd.exists("lib/main.rs"));
+
+    // The clone shares the same contents
+    let other = Dir::with_fs("/project", fs);
+    assert_eq!(other.read_to_string("main.rs")?, "fn main() {}");
+    assert!(Dir::in_memory("/elsewhere").metadata("")?.is_dir());
+
+    Ok(())
+}
+
+#[test]
+fn overlay_test() -> std::io::Result<()> {
+    use crate::OverlayDir;
+
+    let root = scratch("overlay");
+    root.create_dir_all("defaults/themes")?;
+    root.create_dir("user")?;
+    root.write("defaults/app.toml", "default")?;
+    root.write("defaults/themes/light.toml", "light")?;
+    root.write("user/app.toml", "custom")?;
+
+    let config =
+        OverlayDir::new(Dir::new(root.join("user"))).with_lower(Dir::new(root.join("defaults")));
+    assert_eq!(config.read_to_string("app.toml")?, "custom");
+    assert_eq!(config.read_to_string("themes/light.toml")?, "light");
+    assert_eq!(
+        config.resolve("themes/light.toml")?,
+        Some(root.join("defaults/themes/light.toml"))
+    );
+    assert!(config.read("missing").is_err());
+    assert_eq!(config.read_dir_names("")?, ["app.toml", "themes"]);
+
+    config.write("themes/dark.toml", "dark")?;
+    assert_eq!(root.read_to_string("user/themes/dark.toml")?, "dark");
+    assert_eq!(
+        config.read_dir_names("themes")?,
+        ["dark.toml", "light.toml"]
+    );
+
+    config.copy_up("themes/light.toml")?;
+    assert!(root.exists("user/themes/light.toml"));
+
+    // Removing from the upper layer reveals the lower one again
+    config.remove_file("app.toml")?;
+    assert_eq!(config.read_to_string("app.toml")?, "default");
+
+    // A broken link in the upper layer shadows the lower one
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("missing", root.join("user/app.toml"))?;
+        assert!(config.exists("app.toml"));
+        assert_eq!(config.read("app.toml").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert!(config.metadata("app.toml").is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn read_only_test() -> std::io::Result<()> {
+    use crate::ReadOnlyDir;
+
+    fn describe<P: AsRef<Path>>(dir: &ReadOnlyDir<P>) -> std::io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        for entry in dir.read_dir_sorted("")? {
+            let name = entry.file_name();
+            let len = dir.metadata(&name)?.len();
+            lines.push(format!("{} {len}", name.to_string_lossy()));
+        }
+        Ok(lines)
+    }
+
+    let cwd = scratch("read_only");
+    cwd.write("a.txt", "aaa")?;
+    cwd.write("b.txt", "b")?;
+    assert_eq!(describe(&cwd.read_only())?, ["a.txt 3", "b.txt 1"]);
+
+    let view = cwd.into_read_only();
+    assert_eq!(view.read_to_string("a.txt")?, "aaa");
+    assert_eq!(view.read_opt("missing")?, None);
+    assert_eq!(view.walk("").count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn capability_test() -> std::io::Result<()> {
+    use crate::{FileSystem, FileSystemMut, FsDir, ReadOnly};
+
+    fn total_len<P: AsRef<Path>, F: FileSystem>(
+        dir: &FsDir<P, ReadOnly<F>>,
+        names: &[&str],
+    ) -> std::io::Result<usize> {
+        let mut total = 0;
+        for name in names {
+            total += dir.read(name)?.len();
+        }
+        Ok(total)
+    }
+
+    fn fill<P: AsRef<Path>, F: FileSystemMut>(dir: &FsDir<P, F>) -> std::io::Result<()> {
+        dir.write("a", "aaa")?;
+        dir.write("b", "bb")
+    }
+
+    let cwd = Dir::in_memory("/data");
+    fill(&cwd)?;
+    let view = cwd.into_read_only();
+    assert_eq!(total_len(&view, &["a", "b"])?, 5);
+    assert_eq!(view.read_to_string("a")?, "aaa");
+
+    let disk = scratch("capability");
+    fill(&disk.as_fs_dir())?;
+    assert_eq!(total_len(&disk.read_only(), &["a", "b"])?, 5);
+    assert_eq!(disk.read_only().read_dir_paths("")?, [disk.join("a"), disk.join("b")]);
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_test() -> std::io::Result<()> {
+    use crate::DryRunOp;
+
+    let cwd = scratch("dry_run");
+    cwd.write("keep.txt", "keep")?;
+
+    let dry = cwd.dry_run();
+    dry.write("new.txt", "hello")?;
+    assert_eq!(dry.copy("keep.txt", "copy.txt")?, 4);
+    dry.rename("keep.txt", "moved.txt")?;
+    dry.remove_file("keep.txt")?;
+    dry.create_dir_all("a/b")?;
+    assert!(dry.copy("missing", "elsewhere").is_err());
+
+    assert!(!cwd.exists("new.txt"));
+    assert!(!cwd.exists("copy.txt"));
+    assert!(!cwd.exists("a"));
+    assert_eq!(cwd.read_to_string("keep.txt")?, "keep");
+    assert_eq!(dry.read_to_string("keep.txt")?, "keep");
+
+    let ops = dry.fs().take_ops();
+    assert_eq!(ops.len(), 5);
+    assert_eq!(
+        ops[0],
+        DryRunOp::Write {
+            path: cwd.join("new.txt"),
+            len: 5
+        }
+    );
+    assert_eq!(
+        ops[2].to_string(),
+        format!(
+            "rename {:?} to {:?}",
+            cwd.join("keep.txt"),
+            cwd.join("moved.txt")
+        )
+    );
+    assert!(dry.fs().ops().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn transaction_test() -> std::io::Result<()> {
+    let cwd = scratch("transaction");
+    cwd.write("a.txt", "old a")?;
+    cwd.write("b.txt", "b")?;
+    cwd.write("c.txt", "c")?;
+
+    let staged = cwd.transaction(|tx| {
+        tx.write("a.txt", "new a")?;
+        tx.write("new/d.txt", "d")?;
+        tx.rename("b.txt", "moved/b.txt")?;
+        tx.remove_file("c.txt")?;
+        assert_eq!(cwd.read_to_string("a.txt")?, "old a");
+        assert!(cwd.exists("c.txt"));
+        Ok(tx.len())
+    })?;
+    assert_eq!(staged, 4);
+    assert_eq!(cwd.read_to_string("a.txt")?, "new a");
+    assert_eq!(cwd.read_to_string("new/d.txt")?, "d");
+    assert_eq!(cwd.read_to_string("moved/b.txt")?, "b");
+    assert!(!cwd.exists("b.txt"));
+    assert!(!cwd.exists("c.txt"));
+    let names = |path| -> std::io::Result<Vec<String>> {
+        cwd.read_dir_paths(path).map(|paths| {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        })
+    };
+    assert_eq!(names("")?, ["a.txt", "moved", "new"]);
+
+    // A failing change undoes the ones before it
+    let result = cwd.transaction(|tx| {
+        tx.write("a.txt", "newer a")?;
+        tx.write("other/e.txt", "e")?;
+        tx.remove_file("missing.txt")
+    });
+    assert!(result.is_err());
+    assert_eq!(cwd.read_to_string("a.txt")?, "new a");
+    assert_eq!(names("")?, ["a.txt", "moved", "new"]);
+
+    // So does an error from the closure itself
+    let result: std::io::Result<()> = cwd.transaction(|tx| {
+        tx.write("a.txt", "discarded")?;
+        Err(std::io::Error::other("changed my mind"))
+    });
+    assert!(result.is_err());
+    assert_eq!(cwd.read_to_string("a.txt")?, "new a");
+    assert_eq!(names("")?, ["a.txt", "moved", "new"]);
+
+    Ok(())
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_test() -> std::io::Result<()> {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Default)]
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={value:?}", field.name()));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push(format!("{}={value}", field.name()));
+        }
+    }
+
+    #[derive(Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Subscriber for Collect {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            let line = fields
+                .0
+                .into_iter()
+                .filter(|field| !field.starts_with("root="))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut lines = self.0.lock().unwrap();
+            lines.push(line);
+            Id::from_u64(lines.len() as u64)
+        }
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let line = fields
+                .0
+                .into_iter()
+                .filter(|field| !field.starts_with("duration="))
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let cwd = scratch("tracing");
+    let collect = Collect::default();
+    let lines = collect.0.clone();
+    tracing::subscriber::with_default(collect, || -> std::io::Result<()> {
+        cwd.write("a.txt", "hello")?;
+        cwd.read("a.txt")?;
+        assert!(cwd.read("missing").is_err());
+        Ok(())
+    })?;
+
+    let lines = lines.lock().unwrap();
+    assert_eq!(lines[0], "op=write path=a.txt");
+    assert_eq!(lines[1], "result=ok bytes=5");
+    assert_eq!(lines[2], "op=read path=a.txt");
+    assert_eq!(lines[3], "result=ok bytes=5");
+    assert_eq!(lines[4], "op=read path=missing");
+    assert!(lines[5].starts_with("result=error error="), "{}", lines[5]);
+
+    Ok(())
+}
+
+#[test]
+fn snapshot_hardlinks_test() -> std::io::Result<()> {
+    let cwd = scratch("snapshot_hardlinks");
+    cwd.create_dir_all("current/sub/empty")?;
+    cwd.write("current/a.txt", "a")?;
+    cwd.write("current/sub/b.txt", "b")?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("a.txt", cwd.join("current/link"))?;
+
+    assert_eq!(cwd.snapshot_hardlinks("current", "daily/1")?, 2);
+    assert_eq!(cwd.read_to_string("daily/1/a.txt")?, "a");
+    assert_eq!(cwd.read_to_string("daily/1/sub/b.txt")?, "b");
+    assert!(cwd.metadata("daily/1/sub/empty")?.is_dir());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(cwd.read_link("daily/1/link")?, Path::new("a.txt"));
+        let (orig, snap) = (
+            cwd.metadata("current/a.txt")?,
+            cwd.metadata("daily/1/a.txt")?,
+        );
+        assert_eq!(orig.ino(), snap.ino());
+        assert_eq!(orig.nlink(), 2);
+    }
+
+    // Replacing a file leaves the snapshot alone
+    cwd.write_atomic("current/a.txt", "new a")?;
+    assert_eq!(cwd.read_to_string("daily/1/a.txt")?, "a");
+
+    assert!(cwd.snapshot_hardlinks("current", "daily/1").is_err());
+    Ok(())
+}
+
+#[cfg(feature = "clone-subtree")]
+#[test]
+fn clone_subtree_test() -> std::io::Result<()> {
+    use crate::dirs_equal;
+
+    let cwd = scratch("clone_subtree");
+    cwd.create_dir_all("latest/sub/empty")?;
+    cwd.write("latest/a.txt", "a")?;
+    cwd.write("latest/sub/b.txt", "b")?;
+
+    cwd.clone_subtree("latest", "builds/candidate")?;
+    assert!(dirs_equal(
+        &Dir::new(cwd.join("latest")),
+        &Dir::new(cwd.join("builds/candidate"))
+    )?);
+
+    // The clone doesn't share changes with the original
+    cwd.write("builds/candidate/a.txt", "changed")?;
+    assert_eq!(cwd.read_to_string("latest/a.txt")?, "a");
+
+    assert!(cwd.clone_subtree("latest", "builds/candidate").is_err());
+    assert!(cwd.clone_subtree("missing", "elsewhere").is_err());
+
+    // A plain directory is never snapshotted, which would copy its whole
+    // subvolume, so only its own contents are cloned
+    #[cfg(target_os = "linux")]
+    {
+        use crate::CloneMethod;
+        let method = cwd.clone_subtree("latest/sub", "plain")?;
+        assert_ne!(method, CloneMethod::Snapshot);
+        assert_eq!(cwd.read_dir_paths("plain")?, [cwd.join("plain/b.txt"), cwd.join("plain/empty")]);
+    }
+    assert!(!cwd.exists("elsewhere"));
+    Ok(())
+}
+
+#[test]
+fn assertions_test() -> std::io::Result<()> {
+    fn panic_message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let err = std::panic::catch_unwind(f).expect_err("should have panicked");
+        err.downcast_ref::<String>().cloned().unwrap_or_default()
+    }
+
+    let cwd = scratch("assertions");
+    cwd.create_dir_all("logs")?;
+    cwd.write("report.txt", "one\ntwo\nthree\n")?;
+    cwd.write("logs/run.log", "ok\n")?;
+
+    cwd.assert_exists("logs/run.log");
+    cwd.assert_not_exists("missing.txt");
+    cwd.assert_content("report.txt", "one\ntwo\nthree\n");
+    cwd.assert_tree_matches([
+        ("report.txt", "one\ntwo\nthree\n"),
+        ("logs/run.log", "ok\n"),
+    ]);
+
+    let message = panic_message(|| cwd.assert_exists("logs/other.log"));
+    assert!(message.ends_with("logs contains: [run.log]"), "{message}");
+
+    let message = panic_message(|| cwd.assert_content("report.txt", "one\n2\nthree\n"));
+    assert!(
+        message.ends_with("   one\n  -2\n  +two\n   three\n"),
+        "{message}"
+    );
+
+    let message = panic_message(|| {
+        cwd.assert_tree_matches([("report.txt", "one\ntwo\nthree\n"), ("out.bin", "")])
+    });
+    assert!(message.contains("missing: out.bin\n"), "{message}");
+    assert!(message.contains("unexpected: logs/run.log\n"), "{message}");
+
+    Ok(())
+}
+
+#[test]
+fn fixture_test() -> std::io::Result<()> {
+    let root = crate::manifest_dir!();
+    assert!(root.exists("Cargo.toml"));
+    assert_eq!(Dir::manifest_dir()?.0, root.0);
+
+    let src = crate::fixture!("src");
+    assert!(src.exists("lib.rs"));
+    assert!(Dir::fixture("src")?.exists("lib.rs"));
+    assert!(Dir::fixture("Cargo.toml").is_err());
+    assert!(Dir::fixture("no/such/dir").is_err());
+    Ok(())
+}
+
+#[test]
+fn build_tree_test() -> std::io::Result<()> {
+    use crate::tree;
+
+    let spec = tree! {
+        "a/b.txt" => "hi",
+        "c/" => {},
+        "d" => {
+            "e.txt" => String::from("e"),
+            "f" => { "g.txt" => b"g" },
+        },
+    };
+    assert_eq!(spec.len(), 6);
+
+    let cwd = scratch("build_tree");
+    cwd.build_tree(&spec)?;
+    cwd.assert_tree_matches([("a/b.txt", "hi"), ("d/e.txt", "e"), ("d/f/g.txt", "g")]);
+    assert!(cwd.metadata("c")?.is_dir());
+
+    let memory = Dir::in_memory("/fixture");
+    memory.build_tree(&tree! { "x/y.txt" => "y" })?;
+    assert_eq!(memory.read_to_string("x/y.txt")?, "y");
+
+    assert!(tree! {}.is_empty());
+    Ok(())
+}
+
+#[test]
+fn list_json_test() -> std::io::Result<()> {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let cwd = scratch("list_json");
+    assert_eq!(cwd.list_json("")?, "[]\n");
+
+    // Windows doesn't allow quotes in file names
+    let (name, escaped) = if cfg!(windows) {
+        ("say hi.txt", "say hi.txt")
+    } else {
+        ("say \"hi\".txt", r#"say \"hi\".txt"#)
+    };
+    cwd.create_dir_all("tree/sub")?;
+    cwd.write(Path::new("tree/sub").join(name), "hello")?;
+    let file = std::fs::File::options()
+        .write(true)
+        .open(cwd.join("tree/sub").join(name))?;
+    file.set_modified(UNIX_EPOCH + Duration::from_millis(1_500))?;
+
+    let json = cwd.list_json("tree")?;
+    let lines: Vec<&str> = json.lines().collect();
+    assert_eq!(lines.len(), 4, "{json}");
+    assert_eq!(lines[0], "[");
+    assert!(lines[1].starts_with(r#"  {"path": "sub", "type": "dir", "size": "#));
+    assert_eq!(
+        lines[2],
+        format!(r#"  {{"path": "sub/{escaped}", "type": "file", "size": 5, "mtime": 1.5}}"#)
+    );
+    assert_eq!(lines[3], "]");
+    Ok(())
+}
+
+#[test]
+fn entry_type_test() -> std::io::Result<()> {
+    let cwd = scratch("entry_type");
+    cwd.create_dir("sub")?;
+    cwd.write("file.txt", "")?;
+
+    assert!(cwd.is_file("file.txt") && !cwd.is_dir("file.txt"));
+    assert!(cwd.is_dir("sub") && !cwd.is_file("sub"));
+    assert!(!cwd.is_file("missing") && !cwd.is_dir("missing") && !cwd.is_symlink("missing"));
+    assert!(!cwd.is_symlink("file.txt"));
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("sub", cwd.join("link"))?;
+        assert!(cwd.is_symlink("link") && cwd.is_dir("link"));
+        assert!(cwd.read_only().is_symlink("link"));
+    }
+    Ok(())
+}
+
+#[test]
+fn metadata_shortcuts_test() -> std::io::Result<()> {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let cwd = scratch("metadata_shortcuts");
+    cwd.write("file.txt", "12345")?;
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(cwd.join("file.txt"))?
+        .set_modified(time)?;
+
+    assert_eq!(cwd.file_size("file.txt")?, 5);
+    assert_eq!(cwd.modified("file.txt")?, time);
+    assert_eq!(cwd.read_only().file_size("file.txt")?, 5);
+    assert!(cwd.file_size("missing").is_err());
+    assert!(cwd.modified("missing").is_err());
+    Ok(())
+}
+
+#[test]
+fn typed_exists_test() -> std::io::Result<()> {
+    use std::io::ErrorKind;
+
+    let cwd = scratch("typed_exists");
+    cwd.create_dir("sub")?;
+    cwd.write("file.txt", "")?;
+
+    assert!(cwd.file_exists("file.txt") && !cwd.file_exists("sub"));
+    assert!(cwd.dir_exists("sub") && !cwd.dir_exists("file.txt"));
+    assert!(cwd.try_file_exists("file.txt")?);
+    assert!(cwd.try_dir_exists("sub")?);
+    assert!(!cwd.try_file_exists("missing")?);
+    assert!(!cwd.try_dir_exists("missing")?);
+
+    let err = cwd.try_file_exists("sub").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IsADirectory);
+    let err = cwd.try_dir_exists("file.txt").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotADirectory);
+    assert!(cwd.read_only().try_dir_exists("sub")?);
+    Ok(())
+}
+
+#[test]
+fn canonical_test() -> std::io::Result<()> {
+    let cwd = scratch("canonical");
+    cwd.create_dir_all("a/b")?;
+    cwd.write("a/file.txt", "hi")?;
+
+    let dir = Dir::canonical(cwd.join("a/b/.."))?;
+    assert!(dir.0.is_absolute());
+    assert_eq!(dir.0, std::fs::canonicalize(cwd.join("a"))?);
+    assert_eq!(dir.read_to_string("file.txt")?, "hi");
+    assert!(Dir::canonical(cwd.join("missing")).is_err());
+    Ok(())
+}
+
+#[test]
+fn identity_test() -> std::io::Result<()> {
+    let cwd = scratch("identity");
+    cwd.create_dir_all("a/b")?;
+    cwd.write("a/file.txt", "hi")?;
+    cwd.write("a/other.txt", "hi")?;
+
+    let a = Dir::new(cwd.join("a"));
+    assert!(a.same_dir(&Dir::new(cwd.join("a/b/..")))?);
+    assert!(!a.same_dir(&Dir::new(cwd.join("a/b")))?);
+    assert!(a.same_dir(&Dir::new(cwd.join("missing"))).is_err());
+
+    assert!(cwd.same_file("a/file.txt", "a/b/../file.txt")?);
+    assert!(!cwd.same_file("a/file.txt", "a/other.txt")?);
+    #[cfg(unix)]
+    {
+        cwd.hard_link("a/file.txt", "hard.txt")?;
+        std::os::unix::fs::symlink("a", cwd.join("link"))?;
+        assert!(cwd.same_file("a/file.txt", "hard.txt")?);
+        assert!(cwd.same_file("link/file.txt", "a/file.txt")?);
+        assert!(a.same_dir(&Dir::new(cwd.join("link")))?);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_preserve_hard_links_test() -> std::io::Result<()> {
+    use crate::CopyOptions;
+    use std::os::unix::fs::MetadataExt;
+
+    let cwd = scratch("copy_preserve_hard_links");
+    cwd.create_dir_all("store/pkgs")?;
+    cwd.write("store/pkgs/lib.js", "shared")?;
+    cwd.hard_link("store/pkgs/lib.js", "store/a.js")?;
+    cwd.hard_link("store/pkgs/lib.js", "store/b.js")?;
+    cwd.write("store/alone.js", "alone")?;
+
+    let options = CopyOptions::new().preserve_hard_links(true);
+    let report = cwd.copy_dir_with("store", "copy", &options)?;
+    assert_eq!(report.copied.len(), 4);
+    assert_eq!(report.linked.len(), 2);
+    assert_eq!(report.bytes, ("shared".len() + "alone".len()) as u64);
+    let ino = |path| cwd.metadata(path).map(|meta| meta.ino());
+    assert_eq!(ino("copy/a.js")?, ino("copy/pkgs/lib.js")?);
+    assert_eq!(ino("copy/b.js")?, ino("copy/pkgs/lib.js")?);
+    assert_ne!(ino("copy/a.js")?, ino("store/a.js")?);
+    assert_eq!(cwd.metadata("copy/alone.js")?.nlink(), 1);
+
+    let report = cwd.copy_dir_with("store", "plain", &CopyOptions::new())?;
+    assert!(report.linked.is_empty());
+    assert_eq!(cwd.metadata("plain/a.js")?.nlink(), 1);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_policy_test() -> std::io::Result<()> {
+    use crate::{CopyOptions, SymlinkPolicy, SyncOptions};
+    use std::os::unix::fs::symlink;
+
+    let cwd = scratch("symlink_policy");
+    cwd.create_dir_all("src/real")?;
+    cwd.write("src/real/file.txt", "real")?;
+    symlink("real", cwd.join("src/dir_link"))?;
+    symlink("real/file.txt", cwd.join("src/file_link"))?;
+
+    let walked = |policy| -> std::io::Result<Vec<String>> {
+        let mut paths = cwd
+            .walk("src")
+            .symlinks(policy)
+            .map(|entry| Ok(entry?.path().to_string_lossy().into_owned()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+        Ok(paths)
+    };
+    assert_eq!(
+        walked(SymlinkPolicy::Preserve)?,
+        [
+            "src/dir_link",
+            "src/file_link",
+            "src/real",
+            "src/real/file.txt"
+        ]
+    );
+    assert_eq!(
+        walked(SymlinkPolicy::Skip)?,
+        ["src/real", "src/real/file.txt"]
+    );
+    assert_eq!(
+        walked(SymlinkPolicy::Follow)?,
+        [
+            "src/dir_link",
+            "src/dir_link/file.txt",
+            "src/file_link",
+            "src/real",
+            "src/real/file.txt"
+        ]
+    );
+
+    let options = CopyOptions::new().symlinks(SymlinkPolicy::Skip);
+    let report = cwd.copy_dir_with("src", "skipped", &options)?;
+    assert_eq!(report.copied, [Path::new("skipped/real/file.txt")]);
+    assert!(!cwd.is_symlink("skipped/dir_link"));
+
+    let options = SyncOptions::new().symlinks(SymlinkPolicy::Follow);
+    cwd.sync_to(&Dir::new(cwd.join("followed")), "src", &options)?;
+    assert!(!cwd.is_symlink("followed/src/dir_link"));
+    assert_eq!(
+        cwd.read_to_string("followed/src/dir_link/file.txt")?,
+        "real"
+    );
+    assert_eq!(cwd.read_to_string("followed/src/file_link")?, "real");
+
+    // Links are never deleted from the destination when they're skipped
+    symlink("gone", cwd.join("followed/src/extra_link"))?;
+    let options = SyncOptions::new()
+        .symlinks(SymlinkPolicy::Skip)
+        .delete(true);
+    let report = cwd.sync_to(&Dir::new(cwd.join("followed")), "src", &options)?;
+    assert!(cwd.is_symlink("followed/src/extra_link"));
+    assert_eq!(report.deleted.len(), 2, "{report:?}");
+
+    // A link to an ancestor is a cycle
+    symlink("..", cwd.join("src/real/up"))?;
+    let err = walked(SymlinkPolicy::Follow).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn find_case_insensitive_test() -> std::io::Result<()> {
+    let cwd = scratch("find_case_insensitive");
+    cwd.create_dir_all("Data/Textures")?;
+    cwd.write("Data/Textures/Sky.dds", "sky")?;
+
+    let found = cwd.find_case_insensitive("data/TEXTURES/sky.DDS")?;
+    assert_eq!(found, Some(PathBuf::from("Data/Textures/Sky.dds")));
+    assert_eq!(cwd.read_to_string(found.unwrap())?, "sky");
+    // Exact paths are returned as they are
+    let exact = cwd.find_case_insensitive("Data/Textures")?;
+    assert_eq!(exact, Some(PathBuf::from("Data/Textures")));
+    assert_eq!(cwd.find_case_insensitive("data/missing.dds")?, None);
+    assert_eq!(cwd.find_case_insensitive("nowhere/sky.dds")?, None);
+    // Paths which run through a file don't exist either
+    assert_eq!(cwd.find_case_insensitive("Data/Textures/Sky.dds/inner")?, None);
+    assert_eq!(cwd.find_case_insensitive("data/textures/sky.dds/inner")?, None);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "unicode-normalization")]
+fn find_normalized_test() -> std::io::Result<()> {
+    let cwd = scratch("find_normalized");
+    let (nfc, nfd) = ("caf\u{e9}", "cafe\u{301}");
+    cwd.create_dir_all(nfd)?;
+    cwd.write(Path::new(nfd).join("menu.txt"), "croissant")?;
+
+    let found = cwd.find_normalized(Path::new(nfc).join("menu.txt"))?;
+    // Some filesystems normalize names themselves, so the exact lookup
+    // may succeed
+    if cwd.symlink_metadata(nfc).is_err() {
+        assert_eq!(found, Some(Path::new(nfd).join("menu.txt")));
+    }
+    assert_eq!(cwd.read_to_string(found.unwrap())?, "croissant");
+
+    let both = crate::LookupOptions::new()
+        .ignore_case(true)
+        .normalize_unicode(true);
+    let found = cwd.find_with("CAF\u{c9}/Menu.TXT", &both)?;
+    assert_eq!(cwd.read_to_string(found.unwrap())?, "croissant");
+    Ok(())
+}
+
+#[test]
+fn include_hidden_test() -> std::io::Result<()> {
+    let cwd = scratch("include_hidden");
+    cwd.create_dir_all("src/.cache")?;
+    cwd.write("src/.cache/blob", "")?;
+    cwd.write("src/lib.rs", "")?;
+    cwd.write(".env", "")?;
+
+    let walked = |include| -> std::io::Result<Vec<PathBuf>> {
+        let mut paths = cwd
+            .walk("")
+            .include_hidden(include)
+            .map(|entry| Ok(entry?.path().to_owned()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+        Ok(paths)
+    };
+    assert_eq!(walked(true)?.len(), 5);
+    if cfg!(unix) {
+        assert!(cwd.is_hidden(".env"));
+        assert!(cwd.is_hidden("src/.cache"));
+        assert!(!cwd.is_hidden("src/lib.rs"));
+        assert_eq!(
+            walked(false)?,
+            [PathBuf::from("src"), PathBuf::from("src/lib.rs")]
+        );
+        let stats = cwd.walk_stat("").include_hidden(false).count();
+        assert_eq!(stats, 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn files_with_extension_test() -> std::io::Result<()> {
+    let cwd = scratch("files_with_extension");
+    cwd.create_dir_all("src/bin")?;
+    cwd.create_dir_all("src/dir.rs")?;
+    for file in [
+        "src/lib.rs",
+        "src/bin/main.rs",
+        "src/notes.md",
+        "Cargo.toml",
+        "src/old.RS",
+    ] {
+        cwd.write(file, "")?;
+    }
+
+    assert_eq!(
+        cwd.files_with_extension("src", "rs")?,
+        [
+            PathBuf::from("src/bin/main.rs"),
+            PathBuf::from("src/lib.rs")
+        ]
+    );
+    assert_eq!(
+        cwd.files_with_extensions("", ["md", "toml"])?,
+        [PathBuf::from("Cargo.toml"), PathBuf::from("src/notes.md")]
+    );
+    assert!(cwd.files_with_extension("src", "png")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn glob_test() -> std::io::Result<()> {
+    use crate::Glob;
+
+    let glob = Glob::new("src/**/[a-c]?.r[!x]")?;
+    assert!(glob.matches("src/ab.rs"));
+    assert!(glob.matches("src/x/y/cd.rt"));
+    assert!(!glob.matches("src/ab.rx"));
+    assert!(!glob.matches("src/db.rs"));
+    assert!(!glob.matches("ab.rs"));
+    assert!(Glob::new("*")?.matches(".hidden"));
+    assert!(!Glob::new("*")?.matches("a/b"));
+    assert!(Glob::new("a/**")?.matches("a"));
+    assert!(Glob::new("[]x]")?.matches("]"));
+    assert_eq!(
+        Glob::new("a/[b").unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert!(Glob::new("/etc/*").is_err());
+    Ok(())
+}
+
+#[test]
+fn remove_glob_test() -> std::io::Result<()> {
+    let cwd = scratch("remove_glob");
+    cwd.create_dir_all("target/debug/empty.tmp")?;
+    for file in [
+        "target/a.tmp",
+        "target/debug/b.tmp",
+        "target/debug/keep.rs",
+        "c.tmp",
+    ] {
+        cwd.write(file, "")?;
+    }
+
+    let report = cwd.remove_glob("target/**/*.tmp")?;
+    let mut removed = report.removed;
+    removed.sort();
+    assert_eq!(
+        removed,
+        [Path::new("target/a.tmp"), Path::new("target/debug/b.tmp")]
+    );
+    assert!(report.failed.is_empty());
+    assert!(cwd.exists("c.tmp"));
+    assert!(cwd.exists("target/debug/empty.tmp"));
+    assert!(cwd.exists("target/debug/keep.rs"));
+
+    let report = cwd.remove_glob_with("target/*/*", true)?;
+    assert_eq!(report.removed.len(), 2);
+    assert!(!cwd.exists("target/debug/empty.tmp"));
+    assert!(cwd.exists("target/debug"));
+    assert!(cwd.remove_glob("missing/*")?.removed.is_empty());
+    Ok(())
+}
+
+#[test]
+fn move_matching_to_test() -> std::io::Result<()> {
+    use crate::Glob;
+
+    let cwd = scratch("move_matching_to");
+    let inbox = Dir::new(cwd.join("inbox"));
+    let archive = cwd.join("archive");
+    inbox.create_dir_all("2023/jan")?;
+    for file in ["2023/jan/a.pdf", "2023/b.pdf", "2023/jan/c.txt", "d.pdf"] {
+        inbox.write(file, file)?;
+    }
+
+    let mut moved = inbox.move_matching_to(&archive, Glob::new("2023/**/*.pdf")?)?;
+    moved.sort();
+    assert_eq!(
+        moved,
+        [Path::new("2023/b.pdf"), Path::new("2023/jan/a.pdf")]
+    );
+    assert_eq!(
+        cwd.read_to_string("archive/2023/jan/a.pdf")?,
+        "2023/jan/a.pdf"
+    );
+    assert!(!inbox.exists("2023/b.pdf"));
+    assert!(inbox.exists("2023/jan/c.txt"));
+
+    let moved = inbox.move_matching_to(&archive, |path: &Path| path.ends_with("c.txt"))?;
+    assert_eq!(moved, [Path::new("2023/jan/c.txt")]);
+    assert!(cwd.exists("archive/2023/jan/c.txt"));
+    assert!(inbox.exists("d.pdf"));
+    Ok(())
+}
+
+#[test]
+fn move_to_as_test() -> std::io::Result<()> {
+    let cwd = scratch("move_to_as");
+    let drafts = Dir::new(cwd.join("drafts"));
+    drafts.create_dir_all("")?;
+    drafts.write("a.txt", "hello")?;
+
+    drafts.move_to_as(cwd.join("published"), "a.txt", "2024/b.txt")?;
+    assert!(!drafts.exists("a.txt"));
+    assert_eq!(cwd.read_to_string("published/2024/b.txt")?, "hello");
+    assert!(drafts
+        .move_to_as(cwd.join("published"), "a.txt", "c.txt")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn rename_noreplace_test() -> std::io::Result<()> {
+    let cwd = scratch("rename_noreplace");
+    cwd.write("a.txt", "a")?;
+    cwd.write("b.txt", "b")?;
+
+    let err = cwd.rename_noreplace("a.txt", "b.txt").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(cwd.read_to_string("b.txt")?, "b");
+
+    cwd.rename_noreplace("a.txt", "c.txt")?;
+    assert!(!cwd.exists("a.txt"));
+    assert_eq!(cwd.read_to_string("c.txt")?, "a");
+    cwd.create_dir_all("d")?;
+    cwd.rename_noreplace("d", "e")?;
+    assert!(cwd.is_dir("e"));
+    Ok(())
+}
+
+#[test]
+fn swap_test() -> std::io::Result<()> {
+    let cwd = scratch("swap");
+    cwd.create_dir_all("current")?;
+    cwd.write("current/index.html", "v1")?;
+    cwd.write("next", "v2")?;
+
+    cwd.swap("current", "next")?;
+    assert_eq!(cwd.read_to_string("current")?, "v2");
+    assert_eq!(cwd.read_to_string("next/index.html")?, "v1");
+    assert!(cwd.swap("current", "missing").is_err());
+    assert_eq!(cwd.read_to_string("current")?, "v2");
+    assert_eq!(cwd.read_dir_paths("")?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn copy_file_to_test() -> std::io::Result<()> {
+    let cwd = scratch("copy_file_to");
+    let source = Dir::new(cwd.join("source"));
+    source.create_dir_all("")?;
+    source.write("a.txt", "hello")?;
+
+    assert_eq!(
+        source.copy_file_to(cwd.join("dest"), "a.txt", "nested/b.txt")?,
+        5
+    );
+    assert_eq!(source.read_to_string("a.txt")?, "hello");
+    assert_eq!(cwd.read_to_string("dest/nested/b.txt")?, "hello");
+    assert!(source
+        .copy_file_to(cwd.join("dest"), "missing", "c.txt")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn hard_link_to_test() -> std::io::Result<()> {
+    let cwd = scratch("hard_link_to");
+    let store = Dir::new(cwd.join("store"));
+    store.create_dir_all("ab")?;
+    store.write("ab/cdef", "blob")?;
+
+    store.hard_link_to(cwd.join("checkout"), "ab/cdef")?;
+    assert!(cwd.same_file("store/ab/cdef", "checkout/ab/cdef")?);
+    let err = store
+        .hard_link_to(cwd.join("checkout"), "ab/cdef")
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    Ok(())
+}
+
+#[test]
+fn shared_test() -> std::io::Result<()> {
+    use std::sync::Arc;
+
+    let cwd = scratch("shared");
+    cwd.write("a.txt", "hello")?;
+    let shared = Dir::new(cwd.join("")).into_shared();
+    let clone = shared.clone();
+    assert!(Arc::ptr_eq(&shared.0, &clone.0));
+    assert_eq!(Dir::shared(cwd.join("")), shared);
+
+    let handle = std::thread::spawn(move || clone.read_to_string("a.txt"));
+    assert_eq!(handle.join().unwrap()?, "hello");
+    Ok(())
+}
+
+#[test]
+fn as_dir_test() -> std::io::Result<()> {
+    fn count(dir: Dir<&Path>) -> std::io::Result<usize> {
+        Ok(dir.read_dir_paths("")?.len())
+    }
+
+    let cwd = scratch("as_dir");
+    cwd.write("a.txt", "")?;
+    let borrowed = cwd.as_dir();
+    assert_eq!(borrowed.0, cwd.0.as_path());
+    assert_eq!(count(borrowed.clone())?, 1);
+    assert_eq!(count(borrowed)?, 1);
+    assert_eq!(count(Dir::new(cwd.0.to_str().unwrap()).as_dir())?, 1);
+    Ok(())
+}
+
+#[test]
+fn into_inner_test() {
+    fn takes_path(path: &Path) -> usize {
+        path.components().count()
+    }
+
+    let cwd = Dir::new(PathBuf::from("my/root"));
+    assert_eq!(cwd.path(), Path::new("my/root"));
+    assert_eq!(takes_path(&cwd), 2);
+    assert_eq!(cwd.file_name().unwrap(), "root");
+    // Dir's own methods take precedence over Path's
+    assert_eq!(cwd.join("a"), Path::new("my/root/a"));
+    assert_eq!(cwd.into_inner(), PathBuf::from("my/root"));
+}
+
+#[test]
+fn create_dir_with_test() -> std::io::Result<()> {
+    let cwd = scratch("create_dir_with");
+    cwd.create_dir_with("a/b", std::fs::DirBuilder::new().recursive(true))?;
+    assert!(cwd.is_dir("a/b"));
+    assert!(cwd
+        .create_dir_with("a/b", &std::fs::DirBuilder::new())
+        .is_err());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        cwd.create_dir_all_mode("c/d", 0o700)?;
+        for dir in ["c", "c/d"] {
+            assert_eq!(cwd.metadata(dir)?.permissions().mode() & 0o777, 0o700);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn write_with_mode_test() -> std::io::Result<()> {
+    let cwd = scratch("write_with_mode");
+    cwd.write_with_mode("token", "secret", 0o600)?;
+    assert_eq!(cwd.read_to_string("token")?, "secret");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path| Ok::<_, std::io::Error>(cwd.metadata(path)?.permissions().mode() & 0o777);
+        assert_eq!(mode("token")?, 0o600);
+
+        // Existing files have their permissions tightened too
+        cwd.write("loose", "old")?;
+        std::fs::set_permissions(cwd.join("loose"), std::fs::Permissions::from_mode(0o644))?;
+        cwd.write_with_mode("loose", "new", 0o640)?;
+        assert_eq!(mode("loose")?, 0o640);
+        assert_eq!(cwd.read_to_string("loose")?, "new");
+    }
+    #[cfg(not(unix))]
+    {
+        cwd.write_with_mode("frozen", "data", 0o444)?;
+        assert!(cwd.metadata("frozen")?.permissions().readonly());
+    }
+    Ok(())
+}
+
+#[test]
+fn create_private_dir_test() -> std::io::Result<()> {
+    let cwd = scratch("create_private_dir");
+    cwd.create_private_dir("run/state")?;
+    assert!(cwd.is_dir("run/state"));
+    // Creating it again is fine
+    cwd.create_private_dir("run/state")?;
+
+    cwd.write("file", "")?;
+    let err = cwd.create_private_dir("file").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = cwd.metadata("run/state")?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        cwd.create_dir_all_mode("shared", 0o755)?;
+        let err = cwd.create_private_dir("shared").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn copy_with_hash_test() -> std::io::Result<()> {
+    use crate::HashAlgo;
+
+    let cwd = scratch("copy_with_hash");
+    cwd.write("abc", "abc")?;
+    cwd.write("dest", "longer old contents")?;
+
+    let (digest, len) = cwd.copy_with_hash("abc", "dest", HashAlgo::Sha256)?;
+    assert_eq!(len, 3);
+    assert_eq!(
+        digest.to_hex(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(cwd.read_to_string("dest")?, "abc");
+    assert_eq!(digest, cwd.hash_file("dest", HashAlgo::Sha256)?);
+    assert!(cwd
+        .copy_with_hash("missing", "dest2", HashAlgo::Blake3)
+        .is_err());
+    Ok(())
+}
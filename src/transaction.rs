@@ -0,0 +1,272 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::move_set::create_parents_tracked;
+use crate::temp::unique_name;
+use crate::Dir;
+
+#[derive(Debug)]
+enum Staged {
+    /// Contents already written to `temp`, to be renamed over `to`
+    Write { temp: PathBuf, to: PathBuf },
+    /// Deferred until commit, replacing anything at `to`
+    Rename { from: PathBuf, to: PathBuf },
+    /// Deferred until commit, and only unlinked once every change is applied
+    Remove { path: PathBuf },
+}
+
+/// A change which has been applied during [`Transaction`] commit, and how to
+/// undo it
+#[derive(Debug)]
+enum Applied {
+    /// `to` was put in place from `from`, displacing whatever was at `to`
+    /// into `backup`
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+        backup: Option<PathBuf>,
+    },
+    /// `path` was moved aside into `backup`
+    Removed { path: PathBuf, backup: PathBuf },
+}
+
+/// A set of changes to a working directory which are applied together, or
+/// not at all.
+///
+/// Created by [`Dir::transaction`]. Writes are staged by writing their
+/// contents to temporary files right away, while renames and removals are
+/// deferred until the transaction commits. Nothing beneath the working
+/// directory changes until then, apart from the temporary files and any
+/// parent directories they need.
+#[derive(Debug)]
+pub struct Transaction<'a, P: AsRef<Path>> {
+    dir: &'a Dir<P>,
+    staged: Vec<Staged>,
+    created_dirs: Vec<PathBuf>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Runs `f` with a [`Transaction`], then commits the changes it staged.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let site = Dir::new("public");
+    /// site.transaction(|tx| {
+    ///     tx.write("index.html", "<h1>Hello</h1>")?;
+    ///     tx.rename("drafts/about.html", "about.html")?;
+    ///     tx.remove_file("old.html")
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Each change is applied with a rename, so every file is replaced
+    /// atomically, and files which are overwritten or removed are moved
+    /// aside until every change has been applied.
+    ///
+    /// # Errors
+    ///
+    /// If `f` returns an error, the staged changes are discarded and the
+    /// error is returned. If a change fails to apply, the changes which were
+    /// already applied are undone (on a best-effort basis), and the original
+    /// error is returned. Like [`MoveSet`](crate::MoveSet), this can't
+    /// protect against a crash part-way through a commit.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Transaction<'_, P>) -> Result<T>,
+    {
+        let mut tx = Transaction {
+            dir: self,
+            staged: Vec::new(),
+            created_dirs: Vec::new(),
+        };
+        let value = f(&mut tx)?;
+        tx.commit()?;
+        Ok(value)
+    }
+}
+
+impl<'a, P> Transaction<'a, P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns the working directory the transaction applies to
+    pub fn dir(&self) -> &'a Dir<P> {
+        self.dir
+    }
+
+    /// Stages writing `contents` to `path`, replacing anything already
+    /// there. Parent directories are created as necessary, and removed again
+    /// if the transaction doesn't commit.
+    pub fn write<P2: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P2, contents: C) -> Result<()> {
+        let to = self.dir.join(path);
+        create_parents_tracked(&to, &mut self.created_dirs)?;
+        let temp = loop {
+            let temp = sibling(&to, "tx");
+            match fs::File::create_new(&temp) {
+                Ok(_) => break temp,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
+        self.staged.push(Staged::Write {
+            temp: temp.clone(),
+            to,
+        });
+        fs::write(temp, contents)
+    }
+
+    /// Stages renaming `from` to `to`, replacing anything already at `to`.
+    /// Parent directories of `to` are created as necessary.
+    pub fn rename<P2: AsRef<Path>, P3: AsRef<Path>>(&mut self, from: P2, to: P3) -> Result<()> {
+        let to = self.dir.join(to);
+        create_parents_tracked(&to, &mut self.created_dirs)?;
+        self.staged.push(Staged::Rename {
+            from: self.dir.join(from),
+            to,
+        });
+        Ok(())
+    }
+
+    /// Stages removing the file at `path`
+    pub fn remove_file<P2: AsRef<Path>>(&mut self, path: P2) -> Result<()> {
+        self.staged.push(Staged::Remove {
+            path: self.dir.join(path),
+        });
+        Ok(())
+    }
+
+    /// Returns the number of staged changes
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// Returns true if no changes have been staged
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    fn commit(mut self) -> Result<()> {
+        let mut applied = Vec::new();
+        for staged in &self.staged {
+            let result = match staged {
+                Staged::Write { temp, to } => move_into_place(temp, to),
+                Staged::Rename { from, to } => move_into_place(from, to),
+                Staged::Remove { path } => move_aside(path).map(|backup| Applied::Removed {
+                    path: path.clone(),
+                    backup,
+                }),
+            };
+            match result {
+                Ok(change) => applied.push(change),
+                Err(err) => {
+                    // Undone writes return to their temporary files, which
+                    // are removed along with the rest when `self` is dropped
+                    for change in applied.into_iter().rev() {
+                        change.undo();
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        self.staged.clear();
+        self.created_dirs.clear();
+        for change in applied {
+            if let Some(backup) = change.backup() {
+                let _ = Dir::new(backup).remove_any("");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P> Drop for Transaction<'_, P>
+where
+    P: AsRef<Path>,
+{
+    fn drop(&mut self) {
+        for staged in &self.staged {
+            if let Staged::Write { temp, .. } = staged {
+                let _ = fs::remove_file(temp);
+            }
+        }
+        for dir in self.created_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+impl Applied {
+    fn backup(&self) -> Option<&Path> {
+        match self {
+            Applied::Moved { backup, .. } => backup.as_deref(),
+            Applied::Removed { backup, .. } => Some(backup),
+        }
+    }
+
+    fn undo(self) {
+        match self {
+            Applied::Moved { from, to, backup } => {
+                let _ = fs::rename(&to, from);
+                if let Some(backup) = backup {
+                    let _ = fs::rename(backup, to);
+                }
+            }
+            Applied::Removed { path, backup } => {
+                let _ = fs::rename(backup, path);
+            }
+        }
+    }
+}
+
+/// Renames `from` to `to`, first moving anything at `to` aside
+fn move_into_place(from: &Path, to: &Path) -> Result<Applied> {
+    fs::symlink_metadata(from)?;
+    let backup = match fs::symlink_metadata(to) {
+        Ok(_) => Some(move_aside(to)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    if let Err(err) = fs::rename(from, to) {
+        if let Some(backup) = backup {
+            let _ = fs::rename(backup, to);
+        }
+        return Err(err);
+    }
+    Ok(Applied::Moved {
+        from: from.to_owned(),
+        to: to.to_owned(),
+        backup,
+    })
+}
+
+/// Renames `path` to an unused name beside it, returning the new name
+fn move_aside(path: &Path) -> Result<PathBuf> {
+    loop {
+        let backup = sibling(path, "bak");
+        match fs::symlink_metadata(&backup) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                fs::rename(path, &backup)?;
+                return Ok(backup);
+            }
+            Err(err) => return Err(err),
+            Ok(_) => continue,
+        }
+    }
+}
+
+/// Returns a unique hidden name in the same directory as `path`, such as
+/// `.name.tx-<suffix>`, so that renaming between them is atomic
+fn sibling(path: &Path, tag: &str) -> PathBuf {
+    let mut prefix = OsString::from(".");
+    prefix.push(path.file_name().unwrap_or_default());
+    prefix.push(format!(".{tag}-"));
+    path.with_file_name(unique_name(Path::new(&prefix)))
+}
@@ -0,0 +1,150 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::walk::is_hidden;
+use crate::Dir;
+
+/// Renders a directory tree in the style of the Unix `tree` command.
+///
+/// ```text
+/// src
+/// ├── bin
+/// │   └── main.rs
+/// └── lib.rs
+///
+/// 1 directory, 2 files
+/// ```
+///
+/// Entries are sorted by name. Directories which can't be read are shown
+/// with an error message rather than failing the whole rendering.
+///
+/// Created with [`Dir::display_tree`].
+#[derive(Debug, Clone)]
+pub struct Tree {
+    label: PathBuf,
+    root: PathBuf,
+    max_depth: Option<usize>,
+    show_hidden: bool,
+}
+
+#[derive(Default)]
+struct Counts {
+    dirs: usize,
+    files: usize,
+}
+
+impl Tree {
+    /// Limits how many levels beneath the root are shown
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets whether hidden entries, as decided by [`Dir::is_hidden`], are
+    /// shown. Hidden entries are not shown by default.
+    pub fn show_hidden(mut self, show: bool) -> Self {
+        self.show_hidden = show;
+        self
+    }
+
+    fn render(
+        &self,
+        f: &mut Formatter<'_>,
+        dir: &Path,
+        prefix: &str,
+        depth: usize,
+        counts: &mut Counts,
+    ) -> fmt::Result {
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            return Ok(());
+        }
+
+        let mut entries = match fs::read_dir(dir).and_then(|dir| dir.collect::<Result<Vec<_>, _>>())
+        {
+            Ok(entries) => entries,
+            Err(err) => return writeln!(f, "{prefix}└── [error reading directory: {err}]"),
+        };
+        entries.retain(|entry| self.show_hidden || !is_hidden(entry));
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let count = entries.len();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    counts.dirs += 1;
+                    writeln!(f, "{prefix}{branch}{name}")?;
+                    let prefix = format!("{prefix}{indent}");
+                    self.render(f, &entry.path(), &prefix, depth + 1, counts)?;
+                }
+                Ok(file_type) if file_type.is_symlink() => {
+                    counts.files += 1;
+                    match fs::read_link(entry.path()) {
+                        Ok(target) => {
+                            writeln!(f, "{prefix}{branch}{name} -> {}", target.display())?
+                        }
+                        Err(_) => writeln!(f, "{prefix}{branch}{name}")?,
+                    }
+                }
+                _ => {
+                    counts.files += 1;
+                    writeln!(f, "{prefix}{branch}{name}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Tree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.label.display())?;
+        let mut counts = Counts::default();
+        self.render(f, &self.root, "", 0, &mut counts)?;
+
+        let plural =
+            |n: usize, one: &'static str, many: &'static str| if n == 1 { one } else { many };
+        write!(
+            f,
+            "\n{} {}, {} {}",
+            counts.dirs,
+            plural(counts.dirs, "directory", "directories"),
+            counts.files,
+            plural(counts.files, "file", "files"),
+        )
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Returns a [`Display`] adapter which renders the tree at the given
+    /// path like the Unix `tree` command. The tree is read when it is
+    /// displayed, not when this method is called.
+    ///
+    /// ```no_run
+    /// use working_dir::Dir;
+    ///
+    /// let project = Dir("my/project");
+    /// println!("{}", project.display_tree("src").max_depth(2));
+    /// ```
+    pub fn display_tree<P2: AsRef<Path>>(&self, path: P2) -> Tree {
+        let path = path.as_ref();
+        Tree {
+            label: path.to_owned(),
+            root: self.join(path),
+            max_depth: None,
+            show_hidden: false,
+        }
+    }
+}
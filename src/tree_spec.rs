@@ -0,0 +1,164 @@
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::{Dir, FileSystemMut, FsDir};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    File(Vec<u8>),
+    Dir(TreeSpec),
+}
+
+/// A description of a directory structure, which [`Dir::build_tree`]
+/// creates in one go.
+///
+/// Specs are usually written with the [`tree!`](crate::tree) macro, but can
+/// also be built up with [`TreeSpec::file`] and [`TreeSpec::dir`]:
+///
+/// ```
+/// use working_dir::{tree, TreeSpec};
+///
+/// let spec = tree! {
+///     "Cargo.toml" => "[package]\n",
+///     "src" => {
+///         "main.rs" => "fn main() {}\n",
+///     },
+///     "tests/" => {},
+/// };
+/// let built = TreeSpec::new()
+///     .file("Cargo.toml", "[package]\n")
+///     .dir("src", TreeSpec::new().file("main.rs", "fn main() {}\n"))
+///     .dir("tests/", TreeSpec::new());
+/// assert_eq!(spec, built);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeSpec {
+    entries: Vec<(PathBuf, Node)>,
+}
+
+impl TreeSpec {
+    /// Creates an empty spec, which describes an empty directory
+    #[inline]
+    pub fn new() -> TreeSpec {
+        TreeSpec::default()
+    }
+
+    /// Adds a file at `path` with the given contents. Any directories in
+    /// `path` are created as necessary.
+    pub fn file<P: AsRef<Path>, C: AsRef<[u8]>>(mut self, path: P, contents: C) -> Self {
+        let node = Node::File(contents.as_ref().to_vec());
+        self.entries.push((path.as_ref().to_owned(), node));
+        self
+    }
+
+    /// Adds a directory at `path`, containing everything in `contents`
+    pub fn dir<P: AsRef<Path>>(mut self, path: P, contents: TreeSpec) -> Self {
+        self.entries
+            .push((path.as_ref().to_owned(), Node::Dir(contents)));
+        self
+    }
+
+    /// Returns the number of files and directories in the spec, including
+    /// those in nested directories
+    pub fn len(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(_, node)| match node {
+                Node::File(_) => 1,
+                Node::Dir(spec) => 1 + spec.len(),
+            })
+            .sum()
+    }
+
+    /// Returns true if the spec describes an empty directory
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Creates the files and directories described by `spec` beneath the
+    /// working directory, creating parent directories as necessary and
+    /// overwriting any files which already exist.
+    ///
+    /// ```no_run
+    /// # use working_dir::{tree, Dir};
+    /// # fn main() -> std::io::Result<()> {
+    /// let fixture = Dir::new("target/fixture");
+    /// fixture.build_tree(&tree! {
+    ///     "a/b.txt" => "hi",
+    ///     "c/" => {},
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_tree(&self, spec: &TreeSpec) -> Result<()> {
+        self.as_fs_dir().build_tree(spec)
+    }
+}
+
+impl<P, F> FsDir<P, F>
+where
+    P: AsRef<Path>,
+    F: FileSystemMut,
+{
+    /// See: [`Dir::build_tree`]
+    pub fn build_tree(&self, spec: &TreeSpec) -> Result<()> {
+        self.build_tree_in(Path::new(""), spec)
+    }
+
+    fn build_tree_in(&self, base: &Path, spec: &TreeSpec) -> Result<()> {
+        for (path, node) in &spec.entries {
+            let path = base.join(path);
+            match node {
+                Node::File(contents) => {
+                    self.create_parents(&path)?;
+                    self.write(&path, contents)?;
+                }
+                Node::Dir(contents) => {
+                    self.create_dir_all(&path)?;
+                    self.build_tree_in(&path, contents)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`TreeSpec`] from a list of `path => contents` entries, where
+/// the contents are either an expression giving a file's contents, or a
+/// nested list in braces giving a directory's. `{}` is an empty directory.
+///
+/// ```
+/// let spec = working_dir::tree! {
+///     "README.md" => "# Example\n",
+///     "assets" => {
+///         "logo.png" => [0x89, b'P', b'N', b'G'],
+///     },
+///     "cache/" => {},
+/// };
+/// assert_eq!(spec.len(), 4);
+/// ```
+///
+/// See [`Dir::build_tree`].
+#[macro_export]
+macro_rules! tree {
+    (@entries $spec:ident;) => {};
+    (@entries $spec:ident; $path:expr => { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $spec = $spec.dir($path, $crate::tree! { $($inner)* });
+        $crate::tree!(@entries $spec; $($($rest)*)?);
+    };
+    (@entries $spec:ident; $path:expr => $contents:expr $(, $($rest:tt)*)?) => {
+        $spec = $spec.file($path, $contents);
+        $crate::tree!(@entries $spec; $($($rest)*)?);
+    };
+    ($($entries:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut spec = $crate::TreeSpec::new();
+        $crate::tree!(@entries spec; $($entries)*);
+        spec
+    }};
+}
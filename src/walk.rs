@@ -0,0 +1,362 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ffi::OsStr;
+use std::fs::{self, ReadDir};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::instrument::timed;
+use crate::{Dir, Entry};
+
+/// How recursive operations, such as [`Dir::walk`], [`Dir::copy_dir_with`],
+/// and [`Dir::sync_to`], treat symbolic links
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SymlinkPolicy {
+    /// Symbolic links are treated as entries in their own right: they're
+    /// walked over but not into, and copied as links with the same target
+    #[default]
+    Preserve,
+    /// Symbolic links are treated as what they point to: links to
+    /// directories are descended into, and links to files are copied as
+    /// files. Following a link to one of its own ancestors is an error of
+    /// kind [`ErrorKind::InvalidInput`].
+    Follow,
+    /// Symbolic links are left out entirely
+    Skip,
+}
+
+/// A recursive, depth-first iterator over the contents of a directory.
+///
+/// Directories are yielded before their contents. By default, symbolic
+/// links are yielded, but never followed; see [`Walk::symlinks`]. Hidden
+/// entries are included unless [`Walk::include_hidden`] says otherwise.
+/// Paths are relative to the working directory.
+///
+/// Created with [`Dir::walk`].
+#[derive(Debug)]
+pub struct Walk {
+    root: PathBuf,
+    start: Option<(PathBuf, PathBuf)>,
+    stack: Vec<(ReadDir, PathBuf)>,
+    pending: Option<Error>,
+    symlinks: SymlinkPolicy,
+    include_hidden: bool,
+    /// The canonical paths of the directories in `stack`, when following
+    /// symlinks, to detect cycles
+    ancestors: Vec<PathBuf>,
+}
+
+impl Walk {
+    /// Sets how symbolic links are treated. When following them, a link to
+    /// a directory is yielded as a symlink, followed by the contents of the
+    /// directory it points to, beneath the link's own path.
+    ///
+    /// This must be set before iterating.
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Walk {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Sets whether hidden entries, as decided by [`Dir::is_hidden`], are
+    /// yielded. When they aren't, hidden directories aren't descended into
+    /// either, so nothing beneath `.git` is yielded.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let project = Dir::new("my/project");
+    /// for entry in project.walk("").include_hidden(false) {
+    ///     println!("{}", entry?.path().display());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// This must be set before iterating.
+    pub fn include_hidden(mut self, include: bool) -> Walk {
+        self.include_hidden = include;
+        self
+    }
+
+    /// Starts reading the directory at `path`, yielded as `base`, unless
+    /// following it would form a cycle
+    fn descend(&mut self, path: &Path, base: PathBuf) -> Result<()> {
+        let canonical = match self.symlinks {
+            SymlinkPolicy::Follow => {
+                let canonical = fs::canonicalize(path)?;
+                if self.ancestors.contains(&canonical) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("{path:?} is part of a symlink cycle"),
+                    ));
+                }
+                Some(canonical)
+            }
+            _ => None,
+        };
+        let read_dir = timed("read_dir", &self.root, path, || fs::read_dir(path))?;
+        self.stack.push((read_dir, base));
+        self.ancestors.extend(canonical);
+        Ok(())
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.pending.take() {
+            return Some(Err(err));
+        }
+        if let Some((start, base)) = self.start.take() {
+            if let Err(err) = self.descend(&start, base) {
+                return Some(Err(err));
+            }
+        }
+
+        loop {
+            let depth = self.stack.len();
+            let (read_dir, parent) = self.stack.last_mut()?;
+            let entry = match read_dir.next() {
+                Some(Ok(entry)) if !self.include_hidden && is_hidden(&entry) => continue,
+                Some(Ok(entry)) => Entry::new(parent, depth, entry),
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.stack.pop();
+                    if self.symlinks == SymlinkPolicy::Follow {
+                        self.ancestors.pop();
+                    }
+                    continue;
+                }
+            };
+
+            let is_dir = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => match self.symlinks {
+                    SymlinkPolicy::Preserve => false,
+                    SymlinkPolicy::Skip => continue,
+                    // Broken links are yielded as they are
+                    SymlinkPolicy::Follow => {
+                        fs::metadata(entry.abs_path()).is_ok_and(|meta| meta.is_dir())
+                    }
+                },
+                Ok(file_type) => file_type.is_dir(),
+                Err(err) => return Some(Err(err)),
+            };
+            if is_dir {
+                // Report the directory itself, then the failure to read it
+                if let Err(err) = self.descend(&entry.abs_path(), entry.path().to_owned()) {
+                    self.pending = Some(err);
+                }
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+/// Returns true if `entry` is hidden, as decided by [`Dir::is_hidden`]
+#[cfg(windows)]
+pub(crate) fn is_hidden(entry: &fs::DirEntry) -> bool {
+    entry
+        .metadata()
+        .is_ok_and(|meta| has_hidden_attribute(&meta))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn is_hidden(entry: &fs::DirEntry) -> bool {
+    is_dotfile(&entry.file_name())
+}
+
+#[cfg(windows)]
+pub(crate) fn has_hidden_attribute(meta: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_HIDDEN;
+    meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn is_dotfile(name: &OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Summary statistics for a directory tree. See [`Dir::stats`]
+pub struct TreeStats {
+    /// The number of regular files in the tree
+    pub files: u64,
+    /// The number of directories in the tree, not including the root
+    pub dirs: u64,
+    /// The number of symbolic links in the tree
+    pub symlinks: u64,
+    /// The combined size of all regular files in the tree, in bytes
+    pub total_bytes: u64,
+    /// The number of components between the root of the tree and
+    /// the deepest entry
+    pub max_depth: usize,
+    /// The deepest entry in the tree, relative to the working directory
+    pub deepest: Option<PathBuf>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Recursively walks the directory at the given path, yielding every
+    /// entry beneath it. The directory itself is not yielded.
+    ///
+    /// Errors encountered while reading a directory are yielded, and the
+    /// walk continues with the next entry.
+    ///
+    /// See: [`Walk`]
+    pub fn walk<P2: AsRef<Path>>(&self, path: P2) -> Walk {
+        let path = path.as_ref();
+        Walk {
+            root: self.0.as_ref().to_owned(),
+            start: Some((self.join(path), self.relative_base(path))),
+            stack: Vec::new(),
+            pending: None,
+            symlinks: SymlinkPolicy::Preserve,
+            include_hidden: true,
+            ancestors: Vec::new(),
+        }
+    }
+
+    /// Gathers statistics about the tree at the given path in a single
+    /// walk: the number of files, directories, and symlinks it contains,
+    /// the total size of its files, and its deepest entry.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered during the walk.
+    pub fn stats<P2: AsRef<Path>>(&self, path: P2) -> Result<TreeStats> {
+        let mut stats = TreeStats::default();
+
+        for entry in self.walk(path) {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                stats.dirs += 1;
+            } else if file_type.is_symlink() {
+                stats.symlinks += 1;
+            } else {
+                stats.files += 1;
+                stats.total_bytes += entry.metadata()?.len();
+            }
+
+            if entry.depth() > stats.max_depth {
+                stats.max_depth = entry.depth();
+                stats.deepest = Some(entry.path().to_owned());
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Returns the `n` largest regular files beneath the given path, largest
+    /// first, along with their sizes in bytes. Paths are relative to the
+    /// working directory.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered during the walk.
+    pub fn largest_files<P2: AsRef<Path>>(
+        &self,
+        path: P2,
+        n: usize,
+    ) -> Result<Vec<(PathBuf, u64)>> {
+        // Min-heap holding the n largest files seen so far
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for entry in self.walk(path) {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let size = entry.metadata()?.len();
+            heap.push(Reverse((size, entry.path().to_owned())));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+
+        Ok(heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((size, path))| (path, size))
+            .collect())
+    }
+
+    /// Returns the most recently modified regular file beneath the given
+    /// path, along with its modification time, or `None` if there are no
+    /// files. The path is relative to the working directory.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered during the walk.
+    pub fn newest_file<P2: AsRef<Path>>(&self, path: P2) -> Result<Option<(PathBuf, SystemTime)>> {
+        let mut newest: Option<(PathBuf, SystemTime)> = None;
+        for entry in self.walk(path) {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            if newest.as_ref().is_none_or(|(_, time)| modified > *time) {
+                newest = Some((entry.path().to_owned(), modified));
+            }
+        }
+        Ok(newest)
+    }
+
+    /// Returns the regular files beneath the given path whose extension is
+    /// `extension`, given without the leading dot, sorted by path. Paths
+    /// are relative to the working directory.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let project = Dir::new("my/project");
+    /// for source in project.files_with_extension("src", "rs")? {
+    ///     let code = project.read_to_string(source)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Extensions are compared exactly, so `PNG` doesn't match `png`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered during the walk.
+    pub fn files_with_extension<P2: AsRef<Path>, E: AsRef<OsStr>>(
+        &self,
+        path: P2,
+        extension: E,
+    ) -> Result<Vec<PathBuf>> {
+        self.files_with_extensions(path, [extension])
+    }
+
+    /// Returns the regular files beneath the given path whose extension is
+    /// any of `extensions`, sorted by path. See
+    /// [`Dir::files_with_extension`].
+    pub fn files_with_extensions<P2, I>(&self, path: P2, extensions: I) -> Result<Vec<PathBuf>>
+    where
+        P2: AsRef<Path>,
+        I: IntoIterator,
+        I::Item: AsRef<OsStr>,
+    {
+        let extensions: Vec<I::Item> = extensions.into_iter().collect();
+        let mut files = Vec::new();
+        for entry in self.walk(path) {
+            let entry = entry?;
+            let matches = entry.path().extension().is_some_and(|found| {
+                extensions
+                    .iter()
+                    .any(|extension| extension.as_ref() == found)
+            });
+            if matches && entry.file_type()?.is_file() {
+                files.push(entry.path().to_owned());
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
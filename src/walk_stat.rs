@@ -0,0 +1,182 @@
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::instrument::timed;
+use crate::{Dir, Entry, FileKind, Walk};
+
+/// The metadata delivered alongside each entry by [`Dir::walk_stat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryStat {
+    /// The type of the entry. Symbolic links are not followed.
+    pub kind: FileKind,
+    /// The size of the entry in bytes
+    pub size: u64,
+    /// The modification time of the entry, if the platform supports it
+    pub modified: Option<SystemTime>,
+}
+
+/// A recursive walk which fetches the size, type, and modification time
+/// of each entry as it's read.
+///
+/// Created with [`Dir::walk_stat`].
+#[derive(Debug)]
+pub struct WalkStat {
+    walk: Walk,
+    root: PathBuf,
+    #[cfg(target_os = "linux")]
+    buf: Vec<u8>,
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Walks the directory at the given path like [`Dir::walk`], yielding
+    /// each entry along with its [`EntryStat`].
+    ///
+    /// On Linux, each entry is stat'd with `statx`, asking only for its
+    /// type, size, and modification time, without forcing a sync on network
+    /// filesystems. On Windows, the metadata comes from the directory
+    /// listing itself, so no further calls are made. Elsewhere, each entry
+    /// is stat'd with `lstat`.
+    ///
+    /// ```no_run
+    /// # use working_dir::Dir;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = Dir::new("/var/lib/myapp");
+    /// let mut total = 0;
+    /// for entry in dir.walk_stat("cache") {
+    ///     let (_, stat) = entry?;
+    ///     total += stat.size;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn walk_stat<P2: AsRef<Path>>(&self, path: P2) -> WalkStat {
+        WalkStat {
+            walk: self.walk(path),
+            root: self.0.as_ref().to_owned(),
+            #[cfg(target_os = "linux")]
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for WalkStat {
+    type Item = Result<(Entry, EntryStat)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.walk.next()? {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err)),
+        };
+        let stat = self.stat(&entry);
+        Some(stat.map(|stat| (entry, stat)))
+    }
+}
+
+impl WalkStat {
+    /// Sets whether hidden entries are yielded. See
+    /// [`Walk::include_hidden`].
+    pub fn include_hidden(mut self, include: bool) -> WalkStat {
+        self.walk = self.walk.include_hidden(include);
+        self
+    }
+
+    #[cfg(target_os = "linux")]
+    fn stat(&mut self, entry: &Entry) -> Result<EntryStat> {
+        let path = entry.abs_path();
+        timed("symlink_metadata", &self.root, &path, || {
+            match sys::statx(&mut self.buf, &path) {
+                Some(stat) => stat,
+                // statx isn't supported by this kernel
+                None => entry.metadata().map(EntryStat::from),
+            }
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn stat(&mut self, entry: &Entry) -> Result<EntryStat> {
+        let path = entry.abs_path();
+        timed("symlink_metadata", &self.root, &path, || {
+            entry.metadata().map(EntryStat::from)
+        })
+    }
+}
+
+impl From<std::fs::Metadata> for EntryStat {
+    fn from(meta: std::fs::Metadata) -> EntryStat {
+        EntryStat {
+            kind: meta.file_type().into(),
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        }
+    }
+}
+
+impl crate::counters::Payload for EntryStat {}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io::{Error, Result};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use super::EntryStat;
+    use crate::FileKind;
+
+    /// Stats `path` into an [`EntryStat`], using `buf` to hold the
+    /// nul-terminated path. Returns `None` if `statx` isn't available.
+    pub(super) fn statx(buf: &mut Vec<u8>, path: &Path) -> Option<Result<EntryStat>> {
+        buf.clear();
+        buf.extend_from_slice(path.as_os_str().as_bytes());
+        buf.push(0);
+
+        let mut stat = std::mem::MaybeUninit::<libc::statx>::zeroed();
+        let flags = libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_DONT_SYNC;
+        let mask = libc::STATX_TYPE | libc::STATX_SIZE | libc::STATX_MTIME;
+        // SAFETY: buf is nul-terminated and stat is valid for writes
+        let ret = unsafe {
+            libc::statx(
+                libc::AT_FDCWD,
+                buf.as_ptr().cast(),
+                flags,
+                mask,
+                stat.as_mut_ptr(),
+            )
+        };
+        if ret != 0 {
+            let err = Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOSYS) | Some(libc::EPERM) => None,
+                _ => Some(Err(err)),
+            };
+        }
+        // SAFETY: statx succeeded, so stat has been filled in
+        let stat = unsafe { stat.assume_init() };
+
+        let kind = match u32::from(stat.stx_mode) & libc::S_IFMT {
+            libc::S_IFREG => FileKind::File,
+            libc::S_IFDIR => FileKind::Dir,
+            libc::S_IFLNK => FileKind::Symlink,
+            _ => FileKind::Other,
+        };
+        let modified = (stat.stx_mask & libc::STATX_MTIME != 0).then(|| {
+            let time = stat.stx_mtime;
+            let secs = Duration::from_secs(time.tv_sec.unsigned_abs());
+            let nanos = Duration::from_nanos(time.tv_nsec.into());
+            if time.tv_sec >= 0 {
+                SystemTime::UNIX_EPOCH + secs + nanos
+            } else {
+                SystemTime::UNIX_EPOCH - secs + nanos
+            }
+        });
+        Some(Ok(EntryStat {
+            kind,
+            size: stat.stx_size,
+            modified,
+        }))
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::{Dir, FileKind};
+
+type Filter<'a> = Box<dyn Fn(&Path) -> bool + 'a>;
+
+/// Options for [`Dir::zip`]
+pub struct ZipOptions<'a> {
+    level: Option<u32>,
+    filter: Option<Filter<'a>>,
+}
+
+impl Default for ZipOptions<'_> {
+    fn default() -> Self {
+        ZipOptions::new()
+    }
+}
+
+impl<'a> ZipOptions<'a> {
+    /// Creates the default options: every entry is included, and files are
+    /// compressed with Deflate at its default level
+    pub fn new() -> Self {
+        ZipOptions {
+            level: None,
+            filter: None,
+        }
+    }
+
+    /// Sets the Deflate compression level, from 1 (fastest) to 9
+    /// (smallest). A level of 0 stores files without compression.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.level = Some(level.min(9));
+        self
+    }
+
+    /// Only includes entries for which `filter` returns true. The filter is
+    /// given paths relative to the working directory. Excluding a
+    /// directory also excludes everything beneath it.
+    pub fn filter<F: Fn(&Path) -> bool + 'a>(mut self, filter: F) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    fn file_options(&self) -> SimpleFileOptions {
+        let options = SimpleFileOptions::default();
+        match self.level {
+            Some(0) => options.compression_method(CompressionMethod::Stored),
+            Some(level) => options
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(level as i64)),
+            None => options.compression_method(CompressionMethod::Deflated),
+        }
+    }
+}
+
+impl<P> Dir<P>
+where
+    P: AsRef<Path>,
+{
+    /// Packs the tree at `path` into a zip archive at `dest_archive`,
+    /// replacing it if it exists.
+    ///
+    /// Entries are stored in sorted order, with their paths relative to the
+    /// working directory (so packing `assets` stores `assets/logo.png`, not
+    /// `logo.png`). Directories are stored so that empty directories
+    /// survive, and symbolic links are stored as links. The archive itself is
+    /// skipped if it's inside the tree being packed. On Unix, permissions are
+    /// stored along with each entry.
+    ///
+    /// Returns the number of files and symlinks written to the archive.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error of kind [`ErrorKind::InvalidData`] if
+    /// a path isn't valid UTF-8, as zip archives can't store it portably.
+    pub fn zip<P2, P3>(&self, path: P2, dest_archive: P3, options: &ZipOptions) -> Result<u64>
+    where
+        P2: AsRef<Path>,
+        P3: AsRef<Path>,
+    {
+        let archive = self.relative_base(dest_archive.as_ref());
+        let mut entries = Vec::new();
+        for entry in self.walk(path) {
+            let entry = entry?;
+            if entry.path() != archive {
+                entries.push(entry.path().to_owned());
+            }
+        }
+        entries.sort();
+
+        let mut writer = ZipWriter::new(BufWriter::new(File::create(self.join(&archive))?));
+        let file_options = options.file_options();
+        let mut excluded: Vec<PathBuf> = Vec::new();
+        let mut written = 0;
+        for path in entries {
+            if excluded.iter().any(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            if options.filter.as_ref().is_some_and(|filter| !filter(&path)) {
+                excluded.push(path);
+                continue;
+            }
+
+            let name = archive_name(&path)?;
+            let meta = self.symlink_metadata(&path)?;
+            let entry_options = with_permissions(file_options, &meta);
+            match FileKind::from(meta.file_type()) {
+                FileKind::Dir => writer.add_directory(name, entry_options)?,
+                FileKind::File => {
+                    writer.start_file(name, entry_options)?;
+                    io::copy(&mut self.open_readonly(&path)?, &mut writer)?;
+                    written += 1;
+                }
+                FileKind::Symlink => {
+                    let target = self.read_link(&path)?;
+                    writer.add_symlink(name, archive_name(&target)?, entry_options)?;
+                    written += 1;
+                }
+                // Sockets, devices, and the like can't be archived
+                FileKind::Other => {}
+            }
+        }
+        writer.finish()?.flush()?;
+        Ok(written)
+    }
+}
+
+/// Converts a path to a zip entry name, which always uses `/` as its
+/// separator
+fn archive_name(path: &Path) -> Result<String> {
+    let Some(name) = path.to_str() else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{path:?} isn't valid UTF-8"),
+        ));
+    };
+    Ok(name.replace(std::path::MAIN_SEPARATOR, "/"))
+}
+
+#[cfg(unix)]
+fn with_permissions(options: SimpleFileOptions, meta: &fs::Metadata) -> SimpleFileOptions {
+    use std::os::unix::fs::PermissionsExt;
+    options.unix_permissions(meta.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn with_permissions(options: SimpleFileOptions, _: &fs::Metadata) -> SimpleFileOptions {
+    options
+}